@@ -6,6 +6,7 @@
     unused_assignments,
     unused_comparisons
 )]
+use pce::bus::AudioChannels;
 use pce::emulator::Emulator;
 use sdl2::audio::{AudioCallback, AudioSpecDesired, AudioStatus};
 use std::collections::VecDeque;
@@ -18,7 +19,7 @@ use std::time::{Duration, Instant};
 /// Internal emulator sample rate (must match AUDIO_SAMPLE_RATE in bus.rs).
 const EMU_SAMPLE_RATE: u32 = 44_100;
 
-/// Target buffer size in samples – enough for ~50 ms of audio.
+/// Target buffer size in stereo sample frames – enough for ~50 ms of audio.
 const TARGET_BUFFER: usize = 2205;
 /// Producer high watermark. Once reached, let the callback drain before
 /// running more emulation; this avoids the large sawtooth caused by coarse
@@ -116,11 +117,15 @@ impl AudioSdlDiagRuntime {
 }
 
 struct PcmStream {
-    buffer: Arc<Mutex<VecDeque<i16>>>,
+    /// Emulator output regrouped as `[left, right]` frames; mono output is
+    /// stored with both sides equal.
+    buffer: Arc<Mutex<VecDeque<[i16; 2]>>>,
+    /// Values per frame in the device buffer, as obtained from SDL.
+    channels: usize,
     /// Resampling state: converts from EMU_SAMPLE_RATE to the actual device rate.
     resample_ratio: f64, // device_rate / EMU_SAMPLE_RATE
     resample_phase: f64,
-    prev_sample: i16,
+    prev_sample: [i16; 2],
     underrun_count: Arc<AtomicU64>,
     consumed_samples: Arc<AtomicU64>,
     callback_count: Arc<AtomicU64>,
@@ -133,21 +138,22 @@ impl AudioCallback for PcmStream {
         let mut guard = self.buffer.lock().unwrap();
         self.callback_count.fetch_add(1, Ordering::Relaxed);
         self.consumed_samples
-            .fetch_add(out.len() as u64, Ordering::Relaxed);
+            .fetch_add((out.len() / self.channels) as u64, Ordering::Relaxed);
 
         if self.resample_ratio == 1.0 {
             // No resampling needed – fast path.
-            for sample in out.iter_mut() {
-                *sample = guard.pop_front().unwrap_or_else(|| {
+            for frame in out.chunks_exact_mut(self.channels) {
+                let sample = guard.pop_front().unwrap_or_else(|| {
                     self.underrun_count.fetch_add(1, Ordering::Relaxed);
                     self.prev_sample // repeat last sample instead of zero (less click)
                 });
-                self.prev_sample = *sample;
+                write_frame(frame, sample);
+                self.prev_sample = sample;
             }
         } else {
             // Linear interpolation resampling.
             let step = 1.0 / self.resample_ratio; // how much to advance in source per output sample
-            for sample in out.iter_mut() {
+            for frame in out.chunks_exact_mut(self.channels) {
                 // Consume whole source samples that we've moved past.
                 let skip = self.resample_phase as usize;
                 for _ in 0..skip {
@@ -160,14 +166,24 @@ impl AudioCallback for PcmStream {
                 let frac = self.resample_phase;
                 let s0 = guard.front().copied().unwrap_or(self.prev_sample);
                 let s1 = guard.get(1).copied().unwrap_or(s0);
-                let interp = s0 as f64 * (1.0 - frac) + s1 as f64 * frac;
-                *sample = interp as i16;
+                let interp = [0, 1]
+                    .map(|side| (s0[side] as f64 * (1.0 - frac) + s1[side] as f64 * frac) as i16);
+                write_frame(frame, interp);
                 self.resample_phase += step;
             }
         }
     }
 }
 
+/// Fill one device frame: left and right in the first two channels, left
+/// alone on a mono device, silence in any extra channels.
+fn write_frame(frame: &mut [i16], sample: [i16; 2]) {
+    frame.fill(0);
+    for (out, value) in frame.iter_mut().zip(sample) {
+        *out = value;
+    }
+}
+
 fn main() -> Result<(), String> {
     let rom_path = std::env::args().nth(1).ok_or_else(|| {
         "usage: cargo run --release --example audio_sdl --features audio-sdl -- <rom.pce>"
@@ -193,7 +209,7 @@ fn main() -> Result<(), String> {
 
     let desired = AudioSpecDesired {
         freq: Some(EMU_SAMPLE_RATE as i32),
-        channels: Some(1),
+        channels: Some(2),
         samples: Some(1024),
     };
 
@@ -215,14 +231,22 @@ fn main() -> Result<(), String> {
         }
         PcmStream {
             buffer: shared.clone(),
+            channels: spec.channels.max(1) as usize,
             resample_ratio: ratio,
             resample_phase: 0.0,
-            prev_sample: 0,
+            prev_sample: [0; 2],
             underrun_count: underrun_cb,
             consumed_samples: consumed_cb,
             callback_count: callback_count_cb,
         }
     })?;
+    // SDL may hand back fewer channels than asked for; only ask the
+    // emulator for stereo when the device can play it.
+    let channels = if device.spec().channels < 2 {
+        AudioChannels::Mono
+    } else {
+        AudioChannels::Stereo
+    };
 
     // Pre-buffer audio before starting playback.
     let shared_thread = shared.clone();
@@ -243,6 +267,7 @@ fn main() -> Result<(), String> {
         emu.reset();
         emu.set_video_output_enabled(false);
         emu.set_audio_batch_size(128); // ~3ms chunks, balances latency and overhead
+        emu.set_audio_channels(channels);
 
         while running_thread.load(Ordering::Relaxed) {
             let queued_before = shared_thread.lock().unwrap().len();
@@ -257,10 +282,10 @@ fn main() -> Result<(), String> {
             emu.tick();
 
             if let Some(samples) = emu.take_audio_samples() {
-                let sample_count = samples.len();
+                let sample_count = samples.len() / channels.count();
                 let mut guard = shared_thread.lock().unwrap();
-                for sample in samples {
-                    guard.push_back(sample);
+                for frame in samples.chunks_exact(channels.count()) {
+                    guard.push_back([frame[0], frame[channels.count() - 1]]);
                 }
                 let len = guard.len();
                 produced_thread.fetch_add(sample_count as u64, Ordering::Relaxed);
@@ -4,8 +4,9 @@ const HUD_TOAST_DURATION: Duration = Duration::from_millis(1600);
 const HUD_MARGIN: usize = 8;
 const HUD_PADDING: usize = 3;
 const HUD_SCALE: usize = 1;
-const HUD_BG_COLOR: u32 = 0x101010;
-const HUD_TEXT_COLOR: u32 = 0xF8F8F8;
+// Opaque greys read the same in XRGB8888 and ABGR8888 frames.
+const HUD_BG_COLOR: u32 = 0xFF10_1010;
+const HUD_TEXT_COLOR: u32 = 0xFFF8_F8F8;
 
 pub struct HudToast {
     text: String,
@@ -6,27 +6,58 @@
     unused_assignments,
     unused_comparisons
 )]
+use pce::bus::{CropMargins, OutputGeometry};
 use pce::emulator::Emulator;
 use std::{env, error::Error, fs::File, io::Write, path::Path};
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut args = env::args().skip(1);
-    let rom_path = args
-        .next()
-        .ok_or("usage: dump_frame <rom.[bin|pce]> [frames] [output.ppm] [--load-state <path>]")?;
+    let rom_path = args.next().ok_or(
+        "usage: dump_frame <rom.[bin|pce]> [frames] [output.ppm] [--load-state <path>] \
+         [--fixed <W>x<H>] [--crop <left>,<right>,<top>,<bottom>]",
+    )?;
     let frame_target: usize = args.next().and_then(|v| v.parse().ok()).unwrap_or(1);
     let output_path = args.next().unwrap_or_else(|| "frame.ppm".to_string());
-    // Optional: --load-state <path>
+    // Optional: --load-state <path>, --fixed <W>x<H>, --crop <l>,<r>,<t>,<b>
     let mut state_path: Option<String> = None;
+    let mut geometry = OutputGeometry::Native;
+    let mut crop = CropMargins::default();
     while let Some(arg) = args.next() {
-        if arg == "--load-state" {
-            state_path = args.next();
+        match arg.as_str() {
+            "--load-state" => state_path = args.next(),
+            "--fixed" => {
+                let spec = args.next().ok_or("--fixed needs <W>x<H>")?;
+                let (w, h) = spec.split_once('x').ok_or("--fixed needs <W>x<H>")?;
+                geometry = OutputGeometry::Fixed {
+                    width: w.parse()?,
+                    height: h.parse()?,
+                };
+            }
+            "--crop" => {
+                let spec = args.next().ok_or("--crop needs <l>,<r>,<t>,<b>")?;
+                let edges = spec
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<usize>, _>>()?;
+                let [left, right, top, bottom] = edges[..] else {
+                    return Err("--crop needs <l>,<r>,<t>,<b>".into());
+                };
+                crop = CropMargins {
+                    left,
+                    right,
+                    top,
+                    bottom,
+                };
+            }
+            _ => {}
         }
     }
 
     let rom = std::fs::read(&rom_path)?;
 
     let mut emulator = Emulator::new();
+    emulator.set_output_geometry(geometry);
+    emulator.set_crop_margins(crop);
     let is_pce = Path::new(&rom_path)
         .extension()
         .map(|ext| ext.eq_ignore_ascii_case("pce"))
@@ -0,0 +1,82 @@
+use egui::{self, Color32, Stroke};
+use pce::bus::{BatMap, MapRect};
+
+/// Whole background map with the on-screen area outlined.
+pub struct BatViewer {
+    pub auto_refresh: bool,
+    pub refresh_requested: bool,
+    show_viewport: bool,
+    texture: Option<egui::TextureHandle>,
+    columns: usize,
+    rows: usize,
+    viewport: Vec<MapRect>,
+}
+
+impl BatViewer {
+    pub fn new() -> Self {
+        Self {
+            auto_refresh: false,
+            refresh_requested: false,
+            show_viewport: true,
+            texture: None,
+            columns: 0,
+            rows: 0,
+            viewport: Vec::new(),
+        }
+    }
+
+    pub fn show_header(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Refresh BAT").clicked() {
+                self.refresh_requested = true;
+            }
+            ui.checkbox(&mut self.auto_refresh, "Auto");
+            ui.checkbox(&mut self.show_viewport, "Viewport");
+            if self.texture.is_some() {
+                ui.label(format!("{}x{} tiles", self.columns, self.rows));
+            }
+        });
+    }
+
+    pub fn refresh(&mut self, ctx: &egui::Context, map: BatMap) {
+        let size = [map.image.width, map.image.height];
+        let image = egui::ColorImage::from_rgba_unmultiplied(size, &map.image.pixels);
+        match &mut self.texture {
+            Some(tex) => tex.set(image, egui::TextureOptions::NEAREST),
+            None => {
+                self.texture =
+                    Some(ctx.load_texture("bat_view", image, egui::TextureOptions::NEAREST))
+            }
+        }
+        self.columns = map.columns;
+        self.rows = map.rows;
+        self.viewport = map.viewport_rects();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let Some(tex) = &self.texture else {
+            ui.label("BAT viewer not initialized.");
+            return;
+        };
+        // Wide maps are shrunk to the panel; the outline is scaled to match.
+        let size = tex.size();
+        let scale = (ui.available_width() / size[0] as f32).min(1.0);
+        let desired = egui::vec2(size[0] as f32, size[1] as f32) * scale;
+        let response = ui.image((tex.id(), desired));
+        if !self.show_viewport {
+            return;
+        }
+        let origin = response.rect.min;
+        let stroke = Stroke::new(1.0, Color32::from_rgb(0xFF, 0x40, 0x40));
+        for rect in &self.viewport {
+            let min = origin + egui::vec2(rect.x as f32, rect.y as f32) * scale;
+            let max = min + egui::vec2(rect.width as f32, rect.height as f32) * scale;
+            ui.painter().rect_stroke(
+                egui::Rect::from_min_max(min, max),
+                0.0,
+                stroke,
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+}
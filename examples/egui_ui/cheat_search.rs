@@ -1,5 +1,8 @@
 use egui::{self, Color32, RichText};
-use pce::cheat::{CheatManager, CheatSearch, SearchFilter, WORK_RAM_SIZE};
+use pce::cheat::{
+    CheatEntry, CheatManager, CheatSearch, CheatTarget, CheatWidth, SearchFilter, ValueEncoding,
+    WORK_RAM_SIZE, parse_raw_code, ram_address,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum FilterKind {
@@ -7,6 +10,7 @@ enum FilterKind {
     NotEqual,
     GreaterThan,
     LessThan,
+    InRange,
     Increased,
     Decreased,
     Changed,
@@ -23,6 +27,7 @@ impl FilterKind {
             Self::NotEqual => "Not equal to",
             Self::GreaterThan => "Greater than",
             Self::LessThan => "Less than",
+            Self::InRange => "Within range",
             Self::Increased => "Increased",
             Self::Decreased => "Decreased",
             Self::Changed => "Changed",
@@ -40,18 +45,20 @@ impl FilterKind {
                 | Self::NotEqual
                 | Self::GreaterThan
                 | Self::LessThan
+                | Self::InRange
                 | Self::IncreasedBy
                 | Self::DecreasedBy
                 | Self::BcdEqual
         )
     }
 
-    const ALL: [FilterKind; 11] = [
+    const ALL: [FilterKind; 12] = [
         Self::Equal,
         Self::BcdEqual,
         Self::NotEqual,
         Self::GreaterThan,
         Self::LessThan,
+        Self::InRange,
         Self::Increased,
         Self::Decreased,
         Self::Changed,
@@ -85,13 +92,37 @@ fn decode_bcd_at(ram: &[u8], addr: usize) -> String {
     s
 }
 
-/// Parse a u8 value: try decimal first, then hex (with optional 0x prefix).
-fn parse_u8_value(input: &str) -> Option<u8> {
+/// Parse a cheat value of the given width: decimal first, then hex.
+fn parse_cheat_value(input: &str, width: CheatWidth) -> Option<u32> {
     let s = input.trim();
-    s.parse::<u8>().ok().or_else(|| {
-        let h = s.trim_start_matches("0x").trim_start_matches("0X");
-        u8::from_str_radix(h, 16).ok()
-    })
+    s.parse::<u32>()
+        .ok()
+        .or_else(|| {
+            let h = s.trim_start_matches("0x").trim_start_matches("0X");
+            u32::from_str_radix(h, 16).ok()
+        })
+        .filter(|&v| v <= width.mask())
+}
+
+fn format_cheat_value(value: u32, width: CheatWidth) -> String {
+    format!("{:0digits$X}", value, digits = width.byte_len() * 2)
+}
+
+/// Format a search value: hex for unsigned, decimal for signed and BCD.
+fn format_search_value(value: Option<i64>, width: CheatWidth, encoding: ValueEncoding) -> String {
+    match (value, encoding) {
+        (None, _) => "-".to_string(),
+        (Some(v), ValueEncoding::Unsigned) => format_cheat_value(v as u32, width),
+        (Some(v), _) => v.to_string(),
+    }
+}
+
+/// Format a cheat's address: W:xxxx / C:xxxx for RAM, R:xxxxx for ROM.
+fn format_entry_addr(entry: &CheatEntry, wram_size: usize) -> String {
+    match entry.target {
+        CheatTarget::Ram => format_addr(entry.address, wram_size),
+        CheatTarget::Rom => format!("R:{:05X}", entry.address),
+    }
 }
 
 /// Format an address with region label: W:xxxx or C:xxxx
@@ -104,47 +135,23 @@ fn format_addr(addr: u32, wram_size: usize) -> String {
 }
 
 /// Parse a PCE cheat address. Supports:
-/// - `F8xxxx` → Work RAM offset (xxxx & 0x1FFF)
+/// - `F8xxxx` → Work RAM offset (xxxx & 0x1FFF), `80xxxx-F7xxxx` → cart RAM
 /// - `$1297` / `0x1297` / `1297` → direct offset into combined buffer
-fn parse_cheat_addr(input: &str, wram_size: usize, cram_size: usize) -> Option<u32> {
+/// - `$2000-$3FFF` → MPR1 work RAM offset
+fn parse_cheat_addr(input: &str, cram_size: usize) -> Option<u32> {
     let s = input.trim().trim_start_matches('$');
     let s = s.trim_start_matches("0x").trim_start_matches("0X");
     let raw = u32::from_str_radix(s, 16).ok()?;
+    ram_address(raw, cram_size)
+}
 
-    // PCE bank-prefixed format: top byte is bank number
-    if raw > 0xFFFF {
-        let bank = (raw >> 16) as u8;
-        let offset = (raw & 0x1FFF) as u32; // mask to page size
-        match bank {
-            0xF8 => {
-                // Work RAM
-                if (offset as usize) < wram_size {
-                    return Some(offset);
-                }
-            }
-            0x80..=0xF7 if cram_size > 0 => {
-                // Cart RAM — bank $80 base, each bank is 8KB page
-                let cart_offset = ((bank as usize - 0x80) * 0x2000) + offset as usize;
-                if cart_offset < cram_size {
-                    return Some((wram_size + cart_offset) as u32);
-                }
-            }
-            _ => {}
-        }
-        None
-    } else if (raw as usize) < wram_size + cram_size {
-        Some(raw)
-    } else if (0x2000..0x4000).contains(&raw) {
-        // CPU address $2000-$3FFF → MPR1 WRAM offset
-        let offset = (raw - 0x2000) as usize;
-        if offset < wram_size {
-            Some(offset as u32)
-        } else {
-            None
-        }
-    } else {
-        None
-    }
+/// Cheat lists in other formats that sit next to the JSON file:
+/// `cheats/<rom>.cht` (libretro) or `cheats/<rom>.txt` (raw codes).
+fn import_candidate(cheat_path: &std::path::Path) -> Option<std::path::PathBuf> {
+    ["cht", "txt"]
+        .into_iter()
+        .map(|ext| cheat_path.with_extension(ext))
+        .find(|path| path.exists())
 }
 
 pub struct CheatSearchUi {
@@ -152,8 +159,15 @@ pub struct CheatSearchUi {
     pub manager: CheatManager,
     filter_kind: FilterKind,
     filter_value: String,
+    /// Upper bound for `FilterKind::InRange`.
+    filter_value_hi: String,
+    search_width: CheatWidth,
+    search_encoding: ValueEncoding,
     new_cheat_label: String,
     new_cheat_value: String,
+    new_cheat_width: CheatWidth,
+    /// New cheats patch ROM (address is a ROM offset) instead of RAM.
+    new_cheat_rom: bool,
     /// Number of BCD digits from the last BCD search (0 = not a BCD search).
     last_bcd_digits: usize,
     /// Whether cheats have been loaded from file (one-shot on first show).
@@ -167,8 +181,13 @@ impl CheatSearchUi {
             manager: CheatManager::new(),
             filter_kind: FilterKind::Equal,
             filter_value: String::new(),
+            filter_value_hi: String::new(),
+            search_width: CheatWidth::U8,
+            search_encoding: ValueEncoding::Unsigned,
             new_cheat_label: String::new(),
             new_cheat_value: String::new(),
+            new_cheat_width: CheatWidth::U8,
+            new_cheat_rom: false,
             last_bcd_digits: 0,
             cheats_loaded: false,
         }
@@ -226,6 +245,32 @@ impl CheatSearchUi {
             }
         });
 
+        ui.horizontal(|ui| {
+            ui.label("Type:");
+            egui::ComboBox::from_id_salt("search_width")
+                .selected_text(self.search_width.label())
+                .width(50.0)
+                .show_ui(ui, |ui| {
+                    for width in CheatWidth::ALL {
+                        ui.selectable_value(&mut self.search_width, width, width.label());
+                    }
+                });
+            egui::ComboBox::from_id_salt("search_encoding")
+                .selected_text(self.search_encoding.label())
+                .width(90.0)
+                .show_ui(ui, |ui| {
+                    for encoding in ValueEncoding::ALL {
+                        ui.selectable_value(&mut self.search_encoding, encoding, encoding.label());
+                    }
+                });
+        });
+        if (self.search_width, self.search_encoding)
+            != (self.search.width(), self.search.encoding())
+        {
+            self.search
+                .set_value_type(self.search_width, self.search_encoding);
+        }
+
         ui.separator();
 
         ui.horizontal(|ui| {
@@ -243,6 +288,10 @@ impl CheatSearchUi {
                 ui.label("Value:");
                 ui.add(egui::TextEdit::singleline(&mut self.filter_value).desired_width(50.0));
             }
+            if self.filter_kind == FilterKind::InRange {
+                ui.label("..");
+                ui.add(egui::TextEdit::singleline(&mut self.filter_value_hi).desired_width(50.0));
+            }
 
             if ui.button("Apply").clicked() {
                 if let Some(filter) = self.build_filter() {
@@ -258,8 +307,9 @@ impl CheatSearchUi {
 
         ui.separator();
 
-        let candidates = self.search.candidates();
-        let snap = self.search.previous_snapshot();
+        let search = &self.search;
+        let candidates = search.candidates();
+        let (width, encoding) = (search.width(), search.encoding());
 
         ui.label(format!("Results: {}", candidates.len()));
         ui.horizontal(|ui| {
@@ -282,13 +332,13 @@ impl CheatSearchUi {
                     let Some(&addr) = candidates.get(row_idx) else {
                         continue;
                     };
-                    let cur = ram.get(addr as usize).copied().unwrap_or(0);
-                    let prev = snap.map(|s| s.get(addr)).unwrap_or(0);
+                    let cur = search.value_at(ram, addr);
+                    let prev = search.previous_value(addr);
 
                     ui.horizontal(|ui| {
                         ui.label(format_addr(addr, wram_size));
-                        ui.label(format!("{:02X}", prev));
-                        ui.label(format!("{:02X}", cur));
+                        ui.label(format_search_value(prev, width, encoding));
+                        ui.label(format_search_value(cur, width, encoding));
                         // Decode BCD value from consecutive bytes (up to 5 digits)
                         ui.label(decode_bcd_at(ram, addr as usize));
                         if bcd_n >= 2 {
@@ -305,7 +355,15 @@ impl CheatSearchUi {
                                 }
                             }
                         } else if ui.small_button("Add").clicked() {
-                            self.manager.add(addr, cur, format_addr(addr, wram_size));
+                            // Freeze the raw bytes, whatever the encoding.
+                            let start = addr as usize;
+                            if let Some(bytes) = ram.get(start..start + width.byte_len()) {
+                                let raw = width.read(|i| bytes[i]);
+                                self.manager.entries.push(
+                                    CheatEntry::new(addr, raw, format_addr(addr, wram_size))
+                                        .with_width(width),
+                                );
+                            }
                         }
                     });
                 }
@@ -338,6 +396,19 @@ impl CheatSearchUi {
                         }
                     }
                 }
+                if let Some(import_path) = import_candidate(path) {
+                    if ui.button("Import").clicked() {
+                        let cram_size = ram.len().saturating_sub(wram_size);
+                        match self.manager.import_file(&import_path, cram_size) {
+                            Ok(count) => eprintln!(
+                                "Imported {} cheats from {}",
+                                count,
+                                import_path.display()
+                            ),
+                            Err(e) => eprintln!("Failed to import cheats: {}", e),
+                        }
+                    }
+                }
             }
         });
 
@@ -350,16 +421,25 @@ impl CheatSearchUi {
                 for (i, entry) in self.manager.entries.iter_mut().enumerate() {
                     ui.horizontal(|ui| {
                         ui.checkbox(&mut entry.enabled, "");
-                        ui.label(format_addr(entry.address, wram_size));
+                        ui.label(format_entry_addr(entry, wram_size));
+                        ui.label(entry.width.label());
+                        if let Some(compare) = entry.compare {
+                            ui.label(format!("if {}", format_cheat_value(compare, entry.width)));
+                        }
                         ui.label("=");
-                        let mut val_str = format!("{:02X}", entry.value);
-                        let resp =
-                            ui.add(egui::TextEdit::singleline(&mut val_str).desired_width(25.0));
+                        let mut val_str = format_cheat_value(entry.value, entry.width);
+                        let resp = ui.add(
+                            egui::TextEdit::singleline(&mut val_str)
+                                .desired_width(12.5 * entry.width.byte_len() as f32 + 12.5),
+                        );
                         if resp.changed() {
-                            if let Some(v) = parse_u8_value(&val_str) {
+                            if let Some(v) = parse_cheat_value(&val_str, entry.width) {
                                 entry.value = v;
                             }
                         }
+                        if entry.one_shot {
+                            ui.label("1x");
+                        }
                         ui.text_edit_singleline(&mut entry.label);
                         if ui.small_button("X").clicked() {
                             remove_idx = Some(i);
@@ -378,21 +458,61 @@ impl CheatSearchUi {
             ui.add(
                 egui::TextEdit::singleline(&mut self.new_cheat_label)
                     .desired_width(60.0)
-                    .hint_text("F8xxxx"),
+                    .hint_text(if self.new_cheat_rom {
+                        "xxxxx"
+                    } else {
+                        "F8xxxx"
+                    }),
             );
+            egui::ComboBox::from_id_salt("new_cheat_width")
+                .selected_text(self.new_cheat_width.label())
+                .width(50.0)
+                .show_ui(ui, |ui| {
+                    for width in CheatWidth::ALL {
+                        ui.selectable_value(&mut self.new_cheat_width, width, width.label());
+                    }
+                });
             ui.label("=");
             ui.add(
                 egui::TextEdit::singleline(&mut self.new_cheat_value)
-                    .desired_width(25.0)
+                    .desired_width(12.5 * self.new_cheat_width.byte_len() as f32 + 12.5)
                     .hint_text("xx"),
             );
+            ui.checkbox(&mut self.new_cheat_rom, "ROM");
             let cram_size = ram.len().saturating_sub(wram_size);
             if ui.button("Add").clicked() {
+                // A full raw code (`F80044:03`, `1F0044:03`) ignores the value box.
+                if self.new_cheat_label.contains(':') {
+                    match parse_raw_code(&self.new_cheat_label, cram_size) {
+                        Ok(entry) => {
+                            self.manager.entries.push(entry);
+                            self.new_cheat_label.clear();
+                        }
+                        Err(e) => eprintln!("Invalid cheat code: {}", e),
+                    }
+                    return;
+                }
+                let addr = if self.new_cheat_rom {
+                    let s = self.new_cheat_label.trim().trim_start_matches('$');
+                    let s = s.trim_start_matches("0x").trim_start_matches("0X");
+                    u32::from_str_radix(s, 16).ok()
+                } else {
+                    parse_cheat_addr(&self.new_cheat_label, cram_size)
+                };
                 if let (Some(addr), Some(val)) = (
-                    parse_cheat_addr(&self.new_cheat_label, wram_size, cram_size),
-                    parse_u8_value(&self.new_cheat_value),
+                    addr,
+                    parse_cheat_value(&self.new_cheat_value, self.new_cheat_width),
                 ) {
-                    self.manager.add(addr, val, format_addr(addr, wram_size));
+                    let (target, label) = if self.new_cheat_rom {
+                        (CheatTarget::Rom, format!("R:{:05X}", addr))
+                    } else {
+                        (CheatTarget::Ram, format_addr(addr, wram_size))
+                    };
+                    self.manager.entries.push(
+                        CheatEntry::new(addr, val, label)
+                            .with_width(self.new_cheat_width)
+                            .with_target(target),
+                    );
                     self.new_cheat_label.clear();
                     self.new_cheat_value.clear();
                 }
@@ -401,26 +521,28 @@ impl CheatSearchUi {
     }
 
     fn build_filter(&self) -> Option<SearchFilter> {
-        let parse_val = || {
-            u8::from_str_radix(self.filter_value.trim(), 10)
-                .ok()
-                .or_else(|| {
-                    u8::from_str_radix(
-                        self.filter_value
-                            .trim()
-                            .trim_start_matches("0x")
-                            .trim_start_matches("0X"),
-                        16,
-                    )
-                    .ok()
-                })
+        // Decimal first, then hex; BCD values are always decimal.
+        let parse = |text: &str| {
+            let text = text.trim();
+            text.parse::<i64>().ok().or_else(|| {
+                if self.search_encoding == ValueEncoding::PackedBcd {
+                    return None;
+                }
+                let hex = text.trim_start_matches("0x").trim_start_matches("0X");
+                i64::from_str_radix(hex, 16).ok()
+            })
         };
+        let parse_val = || parse(&self.filter_value);
 
         match self.filter_kind {
             FilterKind::Equal => parse_val().map(SearchFilter::Equal),
             FilterKind::NotEqual => parse_val().map(SearchFilter::NotEqual),
             FilterKind::GreaterThan => parse_val().map(SearchFilter::GreaterThan),
             FilterKind::LessThan => parse_val().map(SearchFilter::LessThan),
+            FilterKind::InRange => {
+                let (lo, hi) = (parse_val()?, parse(&self.filter_value_hi)?);
+                Some(SearchFilter::InRange(lo.min(hi), lo.max(hi)))
+            }
             FilterKind::Increased => Some(SearchFilter::Increased),
             FilterKind::Decreased => Some(SearchFilter::Decreased),
             FilterKind::Changed => Some(SearchFilter::Changed),
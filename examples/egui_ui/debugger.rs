@@ -1,19 +1,52 @@
 use egui::{self, Color32, FontId, RichText};
-use pce::debugger::{DebugBreak, Debugger};
+use pce::bus::LayerToggles;
+use pce::debugger::{CallFrame, DebugBreak, Debugger, HitCondition};
+use pce::disasm;
+use pce::symbols::SymbolTable;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum DebuggerAction {
     None,
     TogglePause,
     Step,
+    StepOver,
+    StepOut,
+    RunTo(u16),
     ClearBreak,
-    AddBreakpoint(u16),
+    /// Breakpoint on a symbol name or hex address as typed; the debugger
+    /// resolves it so labels that look like hex still find their symbol.
+    AddBreakpointAt {
+        location: String,
+        condition: String,
+        hit_condition: HitCondition,
+    },
     RemoveBreakpoint(u16),
+    ToggleBreakpoint(u16),
+    ResetHitCounts,
+    SetLayers(LayerToggles),
+    SetPaletteWord {
+        index: usize,
+        word: u16,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HitMode {
+    Always,
+    Equal,
+    AtLeast,
+    Multiple,
 }
 
 pub struct DebuggerUi {
     breakpoint_input: String,
+    run_to_input: String,
+    condition_input: String,
+    hit_mode: HitMode,
+    hit_count_input: String,
+    pub breakpoint_error: Option<String>,
     pub last_action: DebuggerAction,
+    pub disassembly: DisassemblyView,
     pub vram_viewer: VramViewer,
 }
 
@@ -21,7 +54,13 @@ impl DebuggerUi {
     pub fn new() -> Self {
         Self {
             breakpoint_input: String::new(),
+            run_to_input: String::new(),
+            condition_input: String::new(),
+            hit_mode: HitMode::Always,
+            hit_count_input: String::new(),
+            breakpoint_error: None,
             last_action: DebuggerAction::None,
+            disassembly: DisassemblyView::new(),
             vram_viewer: VramViewer::new(),
         }
     }
@@ -41,29 +80,145 @@ impl DebuggerUi {
             if ui.button("Step").clicked() {
                 self.last_action = DebuggerAction::Step;
             }
+            if ui.button("Step Over").clicked() {
+                self.last_action = DebuggerAction::StepOver;
+            }
+            if ui.button("Step Out").clicked() {
+                self.last_action = DebuggerAction::StepOut;
+            }
             if ui.button("Clear Break").clicked() {
                 self.last_action = DebuggerAction::ClearBreak;
             }
         });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.run_to_input).desired_width(80.0));
+            if ui.button("Run To").clicked() {
+                if let Some(pc) = parse_hex_u16(&self.run_to_input) {
+                    self.last_action = DebuggerAction::RunTo(pc);
+                }
+            }
+        });
         ui.separator();
         ui.label("Breakpoints:");
         ui.horizontal(|ui| {
             ui.add(egui::TextEdit::singleline(&mut self.breakpoint_input).desired_width(80.0));
             if ui.button("Add").clicked() {
-                if let Some(pc) = parse_hex_u16(&self.breakpoint_input) {
-                    self.last_action = DebuggerAction::AddBreakpoint(pc);
-                }
+                self.submit_breakpoint();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("if");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.condition_input)
+                    .hint_text("A == $3F && [$2040] > 5")
+                    .desired_width(220.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("hits");
+            egui::ComboBox::from_id_salt("bp_hit_mode")
+                .selected_text(match self.hit_mode {
+                    HitMode::Always => "always",
+                    HitMode::Equal => "== N",
+                    HitMode::AtLeast => ">= N",
+                    HitMode::Multiple => "every N",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.hit_mode, HitMode::Always, "always");
+                    ui.selectable_value(&mut self.hit_mode, HitMode::Equal, "== N");
+                    ui.selectable_value(&mut self.hit_mode, HitMode::AtLeast, ">= N");
+                    ui.selectable_value(&mut self.hit_mode, HitMode::Multiple, "every N");
+                });
+            if self.hit_mode != HitMode::Always {
+                ui.add(egui::TextEdit::singleline(&mut self.hit_count_input).desired_width(50.0));
+            }
+        });
+        if let Some(err) = &self.breakpoint_error {
+            ui.label(RichText::new(err).color(Color32::LIGHT_RED));
+        }
+    }
+
+    fn submit_breakpoint(&mut self) {
+        let location = self.breakpoint_input.trim();
+        if location.is_empty() {
+            self.breakpoint_error = Some("invalid address".to_string());
+            return;
+        }
+        let count = self.hit_count_input.trim().parse::<u32>().ok();
+        let hit_condition = match (self.hit_mode, count) {
+            (HitMode::Always, _) => HitCondition::Always,
+            (HitMode::Equal, Some(n)) => HitCondition::Equal(n),
+            (HitMode::AtLeast, Some(n)) => HitCondition::AtLeast(n),
+            (HitMode::Multiple, Some(n)) if n > 0 => HitCondition::Multiple(n),
+            _ => {
+                self.breakpoint_error = Some("invalid hit count".to_string());
+                return;
             }
+        };
+        self.breakpoint_error = None;
+        self.last_action = DebuggerAction::AddBreakpointAt {
+            location: location.to_string(),
+            condition: self.condition_input.trim().to_string(),
+            hit_condition,
+        };
+    }
+
+    /// Checkboxes for what the renderer draws.
+    pub fn show_layer_toggles(&mut self, ui: &mut egui::Ui, current: LayerToggles) {
+        let mut layers = current;
+        ui.label("Layers:");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut layers.background, "BG");
+            ui.checkbox(&mut layers.sprites, "Sprites");
+            ui.checkbox(&mut layers.sprite_priority, "Priority");
+            ui.checkbox(&mut layers.sprite_line_limit, "16/line limit");
         });
+        egui::CollapsingHeader::new("Individual sprites")
+            .id_salt("layer_sprites")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("All").clicked() {
+                        layers.hidden_sprites = 0;
+                    }
+                    if ui.button("None").clicked() {
+                        layers.hidden_sprites = u64::MAX;
+                    }
+                });
+                egui::Grid::new("layer_sprite_grid").show(ui, |ui| {
+                    for index in 0..64 {
+                        let mut visible = layers.sprite_visible(index);
+                        if ui.checkbox(&mut visible, format!("{index:02}")).changed() {
+                            layers.set_sprite_visible(index, visible);
+                        }
+                        if index % 8 == 7 {
+                            ui.end_row();
+                        }
+                    }
+                });
+            });
+        if layers != current {
+            self.last_action = DebuggerAction::SetLayers(layers);
+        }
     }
 
     pub fn show_breakpoint_list(&mut self, ui: &mut egui::Ui, debugger: &Debugger) {
         let mono = FontId::monospace(12.0);
         let mut any = false;
-        for &pc in &debugger.breakpoints {
+        for (&pc, bp) in &debugger.breakpoints {
             any = true;
             ui.horizontal(|ui| {
-                ui.label(RichText::new(format!("${:04X}", pc)).font(mono.clone()));
+                let mut text = format!("${:04X}", pc);
+                if let Some(cond) = &bp.condition {
+                    text.push_str(&format!(" if {}", cond.source()));
+                }
+                match bp.hit_condition {
+                    HitCondition::Always => {}
+                    HitCondition::Equal(n) => text.push_str(&format!(" hit=={n}")),
+                    HitCondition::AtLeast(n) => text.push_str(&format!(" hit>={n}")),
+                    HitCondition::Multiple(n) => text.push_str(&format!(" hit%{n}")),
+                }
+                text.push_str(&format!(" [{}]", bp.hits));
+                ui.label(RichText::new(text).font(mono.clone()));
                 if ui.button("Remove").clicked() {
                     self.last_action = DebuggerAction::RemoveBreakpoint(pc);
                 }
@@ -71,8 +226,120 @@ impl DebuggerUi {
         }
         if !any {
             ui.label(RichText::new("(none)").color(Color32::GRAY));
+        } else if ui.button("Reset hit counts").clicked() {
+            self.last_action = DebuggerAction::ResetHitCounts;
         }
     }
+
+    pub fn show_disassembly(
+        &mut self,
+        ui: &mut egui::Ui,
+        debugger: &Debugger,
+        pc: u16,
+        mpr: &[u8; 8],
+        read: &dyn Fn(u16) -> u8,
+    ) {
+        if let Some(addr) = self.disassembly.show(ui, debugger, pc, mpr, read) {
+            self.last_action = DebuggerAction::ToggleBreakpoint(addr);
+        }
+    }
+}
+
+/// Disassembly listing around the PC (or a pinned address). Clicking a
+/// line toggles a breakpoint on it.
+pub struct DisassemblyView {
+    pub follow_pc: bool,
+    pub lines_before: usize,
+    pub lines_after: usize,
+    address_input: String,
+    anchor: u16,
+}
+
+impl DisassemblyView {
+    pub fn new() -> Self {
+        Self {
+            follow_pc: true,
+            lines_before: 6,
+            lines_after: 16,
+            address_input: String::new(),
+            anchor: 0,
+        }
+    }
+
+    /// Draw the listing; returns the address of a clicked line.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        debugger: &Debugger,
+        pc: u16,
+        mpr: &[u8; 8],
+        read: &dyn Fn(u16) -> u8,
+    ) -> Option<u16> {
+        ui.heading("Disassembly");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.follow_pc, "Follow PC");
+            ui.add(egui::TextEdit::singleline(&mut self.address_input).desired_width(60.0));
+            if ui.button("Go").clicked() {
+                if let Some(addr) = parse_hex_u16(&self.address_input) {
+                    self.anchor = addr;
+                    self.follow_pc = false;
+                }
+            }
+        });
+        if self.follow_pc {
+            self.anchor = pc;
+        }
+        let mono = FontId::monospace(12.0);
+        let mut clicked = None;
+        let listing =
+            disasm::disassemble_around(self.anchor, self.lines_before, self.lines_after, read);
+        let symbols = (!debugger.symbols.is_empty()).then_some(&debugger.symbols);
+        for inst in &listing {
+            if let Some(label) = symbols.and_then(|s| s.lookup(inst.address, mpr)) {
+                ui.label(
+                    RichText::new(format!("   {label}:"))
+                        .font(mono.clone())
+                        .color(Color32::LIGHT_BLUE),
+                );
+            }
+            let has_bp = debugger.breakpoints.get(&inst.address);
+            let marker = match (has_bp, inst.address == pc) {
+                (Some(_), true) => "*>",
+                (Some(_), false) => "* ",
+                (None, true) => " >",
+                (None, false) => "  ",
+            };
+            let mut text = RichText::new(format!("{marker} {}", inst.listing_line(mpr, symbols)))
+                .font(mono.clone());
+            text = match has_bp {
+                Some(bp) if bp.enabled => text.color(Color32::LIGHT_RED),
+                Some(_) => text.color(Color32::from_rgb(160, 100, 100)),
+                None if !inst.official => text.color(Color32::GRAY),
+                None => text,
+            };
+            if inst.address == pc {
+                text = text.background_color(Color32::from_rgb(60, 60, 20));
+            }
+            let response = ui
+                .add(egui::Label::new(text).sense(egui::Sense::click()))
+                .on_hover_text("Click to toggle breakpoint");
+            if response.clicked() {
+                clicked = Some(inst.address);
+            }
+        }
+        ui.label(
+            RichText::new(format!(
+                "MPR {}",
+                mpr.iter()
+                    .map(|m| format!("{m:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ))
+            .font(mono)
+            .color(Color32::GRAY),
+        );
+        clicked
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -132,10 +399,53 @@ pub fn show_registers(ui: &mut egui::Ui, cpu: CpuSnapshot, vdc: VdcSnapshot) {
     );
 }
 
+/// Shadow call stack, innermost frame first. Targets are labelled from
+/// `symbols` when the callee is named.
+pub fn show_call_stack(ui: &mut egui::Ui, frames: &[CallFrame], symbols: &SymbolTable) {
+    let mono = FontId::monospace(12.0);
+    ui.heading("Call Stack");
+    if frames.is_empty() {
+        ui.label(RichText::new("(empty)").color(Color32::GRAY));
+        return;
+    }
+    for frame in frames.iter().rev() {
+        let color = if frame.is_interrupt() {
+            Color32::from_rgb(255, 200, 120)
+        } else {
+            Color32::LIGHT_GRAY
+        };
+        let mut text = format!(
+            "{:<5} {:02X}:{:04X} <- {:02X}:{:04X}",
+            frame.kind.label(),
+            frame.target_bank(),
+            frame.target,
+            frame.caller_bank(),
+            frame.caller_pc
+        );
+        if let Some(name) = symbols.lookup_banked(frame.target_bank(), frame.target) {
+            text.push_str(&format!("  {name}"));
+        }
+        ui.label(RichText::new(text).font(mono.clone()).color(color))
+            .on_hover_text(format!(
+                "SP ${:02X}  MPR {}",
+                frame.sp,
+                frame
+                    .mpr
+                    .iter()
+                    .map(|m| format!("{m:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+    }
+}
+
 pub fn describe_break(break_event: DebugBreak) -> &'static str {
     match break_event {
         DebugBreak::Breakpoint(_) => "BREAKPOINT",
         DebugBreak::Step(_) => "STEP",
+        DebugBreak::StepOver(_) => "STEP OVER",
+        DebugBreak::StepOut(_) => "STEP OUT",
+        DebugBreak::RunTo(_) => "RUN TO",
     }
 }
 
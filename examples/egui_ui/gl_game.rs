@@ -41,7 +41,7 @@ pub struct GlGameRenderer {
     texture: GLuint,
     tex_w: usize,
     tex_h: usize,
-    rgba_buf: Vec<u8>,
+    pixel_aspect: f64,
 }
 
 impl GlGameRenderer {
@@ -119,22 +119,14 @@ impl GlGameRenderer {
                 texture,
                 tex_w: 0,
                 tex_h: 0,
-                rgba_buf: Vec::new(),
+                pixel_aspect: 1.0,
             }
         }
     }
 
+    /// Upload a frame of `PixelFormat::Abgr8888` pixels as-is.
     pub fn upload_frame(&mut self, frame: &[u32], w: usize, h: usize) {
-        let pixel_count = w * h;
-        self.rgba_buf.resize(pixel_count * 4, 0xFF);
-        for (i, &pixel) in frame.iter().take(pixel_count).enumerate() {
-            let off = i * 4;
-            self.rgba_buf[off] = ((pixel >> 16) & 0xFF) as u8;
-            self.rgba_buf[off + 1] = ((pixel >> 8) & 0xFF) as u8;
-            self.rgba_buf[off + 2] = (pixel & 0xFF) as u8;
-            // alpha stays 0xFF from resize
-        }
-
+        assert!(frame.len() >= w * h, "frame smaller than {w}x{h}");
         unsafe {
             gl::BindTexture(gl::TEXTURE_2D, self.texture);
             if w != self.tex_w || h != self.tex_h {
@@ -146,8 +138,8 @@ impl GlGameRenderer {
                     h as GLsizei,
                     0,
                     gl::RGBA,
-                    gl::UNSIGNED_BYTE,
-                    self.rgba_buf.as_ptr() as *const _,
+                    gl::UNSIGNED_INT_8_8_8_8_REV,
+                    frame.as_ptr() as *const _,
                 );
                 self.tex_w = w;
                 self.tex_h = h;
@@ -160,16 +152,21 @@ impl GlGameRenderer {
                     w as GLsizei,
                     h as GLsizei,
                     gl::RGBA,
-                    gl::UNSIGNED_BYTE,
-                    self.rgba_buf.as_ptr() as *const _,
+                    gl::UNSIGNED_INT_8_8_8_8_REV,
+                    frame.as_ptr() as *const _,
                 );
             }
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
     }
 
+    /// Width/height of one emulated pixel (`Emulator::pixel_aspect_ratio`).
+    pub fn set_pixel_aspect(&mut self, pixel_aspect: f64) {
+        self.pixel_aspect = pixel_aspect;
+    }
+
     /// Draw the game quad into the given viewport region, letter/pillar-boxed
-    /// to maintain the original aspect ratio (tex_w : tex_h).
+    /// to maintain the display aspect ratio (tex_w * pixel_aspect : tex_h).
     /// `vp_x, vp_y` are in GL coordinates (Y from bottom).
     pub fn draw(&self, vp_x: i32, vp_y: i32, vp_w: i32, vp_h: i32) {
         if vp_w <= 0 || vp_h <= 0 || self.tex_w == 0 || self.tex_h == 0 {
@@ -177,7 +174,7 @@ impl GlGameRenderer {
         }
 
         // Compute letterbox/pillarbox viewport preserving source aspect ratio
-        let src_aspect = self.tex_w as f64 / self.tex_h as f64;
+        let src_aspect = self.tex_w as f64 * self.pixel_aspect / self.tex_h as f64;
         let dst_aspect = vp_w as f64 / vp_h as f64;
 
         let (fit_w, fit_h) = if dst_aspect > src_aspect {
@@ -1,11 +1,17 @@
+pub mod bat_viewer;
 pub mod cheat_search;
 pub mod debugger;
 pub mod gl_game;
 pub mod hex_viewer;
+pub mod palette_viewer;
+pub mod sprite_viewer;
 
+use bat_viewer::BatViewer;
 use cheat_search::CheatSearchUi;
 use debugger::{CpuSnapshot, DebuggerAction, DebuggerUi, VdcSnapshot};
 use hex_viewer::HexViewerState;
+use palette_viewer::PaletteViewer;
+use sprite_viewer::SpriteViewer;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ActiveTab {
@@ -19,6 +25,9 @@ pub struct CheatToolUi {
     pub hex_viewer: HexViewerState,
     pub cheat_search_ui: CheatSearchUi,
     pub debugger_ui: DebuggerUi,
+    pub bat_viewer: BatViewer,
+    pub sprite_viewer: SpriteViewer,
+    pub palette_viewer: PaletteViewer,
     pub panel_visible: bool,
     /// Frozen snapshot shown in the panel. Updated only on Refresh.
     pub ram_snapshot: Vec<u8>,
@@ -36,8 +45,16 @@ pub struct DebuggerPanelData<'a> {
     pub debugger: &'a pce::debugger::Debugger,
     pub cpu: CpuSnapshot,
     pub vdc: VdcSnapshot,
+    pub call_stack: &'a [pce::debugger::CallFrame],
+    pub mpr: [u8; 8],
+    pub read_memory: &'a dyn Fn(u16) -> u8,
     pub vram: &'a [u16],
     pub palette_rgb: &'a dyn Fn(usize) -> u32,
+    pub palette_word: &'a dyn Fn(usize) -> u16,
+    pub palette_writer: &'a dyn Fn(usize) -> Option<pce::bus::PaletteWriter>,
+    pub layers: pce::bus::LayerToggles,
+    pub render_bat_map: &'a dyn Fn() -> pce::bus::BatMap,
+    pub sprites: &'a dyn Fn() -> Vec<(pce::bus::SpriteEntry, pce::bus::RgbaImage)>,
     pub egui_ctx: &'a egui::Context,
 }
 
@@ -48,6 +65,9 @@ impl CheatToolUi {
             hex_viewer: HexViewerState::new(),
             cheat_search_ui: CheatSearchUi::new(),
             debugger_ui: DebuggerUi::new(),
+            bat_viewer: BatViewer::new(),
+            sprite_viewer: SpriteViewer::new(),
+            palette_viewer: PaletteViewer::new(),
             panel_visible: false,
             ram_snapshot: vec![0u8; 0x2000],
             refresh_requested: false,
@@ -113,11 +133,28 @@ impl CheatToolUi {
                 if let Some(debug) = debug {
                     self.debugger_ui.show(ui);
                     ui.separator();
-                    debugger::show_registers(ui, debug.cpu, debug.vdc);
+                    ui.columns(2, |cols| {
+                        debugger::show_registers(&mut cols[0], debug.cpu, debug.vdc);
+                        debugger::show_call_stack(
+                            &mut cols[1],
+                            debug.call_stack,
+                            &debug.debugger.symbols,
+                        );
+                    });
+                    ui.separator();
+                    self.debugger_ui.show_disassembly(
+                        ui,
+                        debug.debugger,
+                        debug.cpu.pc,
+                        &debug.mpr,
+                        debug.read_memory,
+                    );
                     ui.separator();
                     ui.label("Breakpoints:");
                     self.debugger_ui.show_breakpoint_list(ui, debug.debugger);
                     ui.separator();
+                    self.debugger_ui.show_layer_toggles(ui, debug.layers);
+                    ui.separator();
                     self.debugger_ui.vram_viewer.show_header(ui);
                     if self.debugger_ui.vram_viewer.auto_refresh {
                         self.debugger_ui.vram_viewer.refresh_requested = true;
@@ -132,6 +169,34 @@ impl CheatToolUi {
                         self.debugger_ui.vram_viewer.refresh_requested = false;
                     }
                     self.debugger_ui.vram_viewer.show(ui);
+                    ui.separator();
+                    self.bat_viewer.show_header(ui);
+                    if self.bat_viewer.auto_refresh || self.bat_viewer.refresh_requested {
+                        self.bat_viewer
+                            .refresh(debug.egui_ctx, (debug.render_bat_map)());
+                        self.bat_viewer.refresh_requested = false;
+                    }
+                    self.bat_viewer.show(ui);
+                    ui.separator();
+                    self.sprite_viewer.show_header(ui);
+                    if self.sprite_viewer.auto_refresh || self.sprite_viewer.refresh_requested {
+                        self.sprite_viewer
+                            .refresh(debug.egui_ctx, (debug.sprites)());
+                        self.sprite_viewer.refresh_requested = false;
+                    }
+                    self.sprite_viewer.show(ui);
+                    ui.separator();
+                    ui.label("Palette:");
+                    self.palette_viewer.show(
+                        ui,
+                        debug.palette_word,
+                        debug.palette_rgb,
+                        debug.palette_writer,
+                    );
+                    if let Some((index, word)) = self.palette_viewer.take_edit() {
+                        self.debugger_ui.last_action =
+                            DebuggerAction::SetPaletteWord { index, word };
+                    }
                     debug_action = self.debugger_ui.take_action();
                 } else {
                     ui.label("Debugger data unavailable.");
@@ -0,0 +1,129 @@
+use egui::{self, Color32, FontId, RichText, Sense, Stroke};
+use pce::bus::PaletteWriter;
+
+const COLUMNS: usize = 32;
+const ROWS: usize = 16;
+const CELL: f32 = 10.0;
+
+/// All 512 VCE entries as a 32x16 grid (BG palettes on the top half,
+/// sprite palettes below), with an editor for the picked entry.
+pub struct PaletteViewer {
+    selected: Option<usize>,
+    edit: Option<(usize, u16)>,
+}
+
+impl PaletteViewer {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            edit: None,
+        }
+    }
+
+    /// Palette write requested by the editor since the last call.
+    pub fn take_edit(&mut self) -> Option<(usize, u16)> {
+        self.edit.take()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        palette_word: &dyn Fn(usize) -> u16,
+        palette_rgb: &dyn Fn(usize) -> u32,
+        palette_writer: &dyn Fn(usize) -> Option<PaletteWriter>,
+    ) {
+        let size = egui::vec2(COLUMNS as f32 * CELL, ROWS as f32 * CELL);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+        let cell_rect = |index: usize| {
+            let min = rect.min
+                + egui::vec2(
+                    (index % COLUMNS) as f32 * CELL,
+                    (index / COLUMNS) as f32 * CELL,
+                );
+            egui::Rect::from_min_size(min, egui::vec2(CELL, CELL))
+        };
+        for index in 0..COLUMNS * ROWS {
+            painter.rect_filled(cell_rect(index), 0.0, to_color32(palette_rgb(index)));
+        }
+        let index_at = |pos: egui::Pos2| {
+            let offset = pos - rect.min;
+            let column = (offset.x / CELL) as usize;
+            let row = (offset.y / CELL) as usize;
+            (column < COLUMNS && row < ROWS).then_some(row * COLUMNS + column)
+        };
+        if let Some(index) = response.hover_pos().and_then(index_at) {
+            painter.rect_stroke(
+                cell_rect(index),
+                0.0,
+                Stroke::new(1.0, Color32::WHITE),
+                egui::StrokeKind::Inside,
+            );
+            response.clone().on_hover_ui_at_pointer(|ui| {
+                ui.label(describe_entry(
+                    index,
+                    palette_word(index),
+                    palette_rgb(index),
+                    palette_writer(index),
+                ));
+            });
+        }
+        if response.clicked() {
+            self.selected = response.interact_pointer_pos().and_then(index_at);
+        }
+        let Some(index) = self.selected else {
+            ui.label("Click an entry to edit it.");
+            return;
+        };
+        painter.rect_stroke(
+            cell_rect(index),
+            0.0,
+            Stroke::new(2.0, Color32::YELLOW),
+            egui::StrokeKind::Inside,
+        );
+
+        let word = palette_word(index);
+        ui.label(
+            RichText::new(describe_entry(
+                index,
+                word,
+                palette_rgb(index),
+                palette_writer(index),
+            ))
+            .font(FontId::monospace(12.0)),
+        );
+        let mut green = (word >> 6) & 0x07;
+        let mut red = (word >> 3) & 0x07;
+        let mut blue = word & 0x07;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            for (label, value) in [("G", &mut green), ("R", &mut red), ("B", &mut blue)] {
+                ui.label(label);
+                changed |= ui
+                    .add(egui::DragValue::new(value).range(0..=7).speed(0.05))
+                    .changed();
+            }
+        });
+        if changed {
+            self.edit = Some((index, (green << 6) | (red << 3) | blue));
+        }
+    }
+}
+
+fn to_color32(rgb: u32) -> Color32 {
+    Color32::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+fn describe_entry(index: usize, word: u16, rgb: u32, writer: Option<PaletteWriter>) -> String {
+    let writer = match writer {
+        Some(PaletteWriter::Cpu(pc)) => format!("PC ${pc:04X}"),
+        Some(PaletteWriter::CramDma) => "CRAM DMA".to_string(),
+        Some(PaletteWriter::Host) => "editor".to_string(),
+        None => "-".to_string(),
+    };
+    format!(
+        "${index:03X} (pal {:02}, col {:X}) GRB ${word:03X} RGB #{rgb:06X} by {writer}",
+        index >> 4,
+        index & 0x0F
+    )
+}
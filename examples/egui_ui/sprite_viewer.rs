@@ -0,0 +1,130 @@
+use egui::{self, Color32, RichText};
+use pce::bus::{RgbaImage, SpriteEntry};
+
+/// SATB inspector: one row per sprite with its pattern drawn beside it.
+pub struct SpriteViewer {
+    pub auto_refresh: bool,
+    pub refresh_requested: bool,
+    visible_only: bool,
+    /// Sprite picked in the list; the host outlines it on the game screen.
+    pub selected: Option<usize>,
+    sprites: Vec<(SpriteEntry, egui::TextureHandle)>,
+}
+
+impl SpriteViewer {
+    pub fn new() -> Self {
+        Self {
+            auto_refresh: false,
+            refresh_requested: false,
+            visible_only: true,
+            selected: None,
+            sprites: Vec::new(),
+        }
+    }
+
+    pub fn show_header(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Refresh SATB").clicked() {
+                self.refresh_requested = true;
+            }
+            ui.checkbox(&mut self.auto_refresh, "Auto");
+            ui.checkbox(&mut self.visible_only, "Visible only");
+        });
+    }
+
+    pub fn refresh(&mut self, ctx: &egui::Context, sprites: Vec<(SpriteEntry, RgbaImage)>) {
+        let old = std::mem::take(&mut self.sprites);
+        let mut textures = old.into_iter().map(|(_, tex)| tex);
+        for (entry, image) in sprites {
+            let size = [image.width, image.height];
+            let image = egui::ColorImage::from_rgba_unmultiplied(size, &image.pixels);
+            let texture = match textures.next() {
+                Some(mut tex) => {
+                    tex.set(image, egui::TextureOptions::NEAREST);
+                    tex
+                }
+                None => ctx.load_texture(
+                    format!("sprite_view_{}", entry.index),
+                    image,
+                    egui::TextureOptions::NEAREST,
+                ),
+            };
+            self.sprites.push((entry, texture));
+        }
+    }
+
+    /// The selected sprite, if it is on screen.
+    pub fn highlighted(&self) -> Option<SpriteEntry> {
+        let index = self.selected?;
+        self.sprites
+            .iter()
+            .map(|(entry, _)| *entry)
+            .find(|entry| entry.index == index && entry.visible)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if self.sprites.is_empty() {
+            ui.label("Sprite viewer not initialized.");
+            return;
+        }
+        let mono = egui::FontId::monospace(12.0);
+        for (entry, texture) in &self.sprites {
+            if self.visible_only && !entry.visible {
+                continue;
+            }
+            ui.horizontal(|ui| {
+                let size = egui::vec2(entry.width as f32, entry.height as f32);
+                ui.add(egui::Image::new((texture.id(), size)).bg_fill(Color32::from_gray(0x30)));
+                let mut flags = String::new();
+                flags.push(if entry.h_flip { 'H' } else { '-' });
+                flags.push(if entry.v_flip { 'V' } else { '-' });
+                flags.push(if entry.high_priority { 'P' } else { '-' });
+                let text = format!(
+                    "#{:02} X{:4} Y{:4} PAT ${:03X} {}x{} PAL {:X} {}",
+                    entry.index,
+                    entry.x,
+                    entry.y,
+                    entry.pattern,
+                    entry.width,
+                    entry.height,
+                    entry.palette,
+                    flags
+                );
+                let mut label = RichText::new(text).font(mono.clone());
+                if !entry.visible {
+                    label = label.color(Color32::GRAY);
+                }
+                let selected = self.selected == Some(entry.index);
+                if ui.selectable_label(selected, label).clicked() {
+                    self.selected = if selected { None } else { Some(entry.index) };
+                }
+            });
+        }
+    }
+}
+
+/// Outline `sprite` on a frame of `width` x `height` pixels whose top-left
+/// pixel is the first active dot and line.
+pub fn outline_sprite(
+    frame: &mut [u32],
+    width: usize,
+    height: usize,
+    sprite: SpriteEntry,
+    colour: u32,
+) {
+    let left = sprite.x.max(0) as usize;
+    let top = sprite.y.max(0) as usize;
+    let right = ((sprite.x + sprite.width as i32).max(0) as usize).min(width);
+    let bottom = ((sprite.y + sprite.height as i32).max(0) as usize).min(height);
+    if left >= right || top >= bottom {
+        return;
+    }
+    for x in left..right {
+        frame[top * width + x] = colour;
+        frame[(bottom - 1) * width + x] = colour;
+    }
+    for y in top..bottom {
+        frame[y * width + left] = colour;
+        frame[y * width + right - 1] = colour;
+    }
+}
@@ -6,6 +6,7 @@
     unused_assignments,
     unused_comparisons
 )]
+use pce::bus::AudioChannels;
 use pce::emulator::Emulator;
 use std::error::Error;
 use std::io::Write;
@@ -14,6 +15,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let rom = std::fs::read("roms/Power League III (Japan).pce")?;
     let mut emu = Emulator::new();
     emu.load_hucard(&rom)?;
+    emu.set_audio_channels(AudioChannels::Stereo);
     emu.reset();
 
     let target_frames = 600u64; // 10 seconds
@@ -71,10 +73,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     let sample_rate = 44100u32;
     let path = "pl3_title.wav";
     write_wav(path, sample_rate, &all_samples)?;
+    let sample_frames = all_samples.len() / 2;
     println!(
-        "Wrote {} samples ({:.2} sec) to {}",
-        all_samples.len(),
-        all_samples.len() as f64 / sample_rate as f64,
+        "Wrote {} stereo samples ({:.2} sec) to {}",
+        sample_frames,
+        sample_frames as f64 / sample_rate as f64,
         path
     );
     println!("Frames: {}", frames);
@@ -110,10 +113,10 @@ fn write_wav(path: &str, sample_rate: u32, samples: &[i16]) -> Result<(), Box<dy
     f.write_all(b"fmt ")?;
     f.write_all(&16u32.to_le_bytes())?; // chunk size
     f.write_all(&1u16.to_le_bytes())?; // PCM
-    f.write_all(&1u16.to_le_bytes())?; // mono
+    f.write_all(&2u16.to_le_bytes())?; // stereo
     f.write_all(&sample_rate.to_le_bytes())?;
-    f.write_all(&(sample_rate * 2).to_le_bytes())?; // byte rate
-    f.write_all(&2u16.to_le_bytes())?; // block align
+    f.write_all(&(sample_rate * 4).to_le_bytes())?; // byte rate
+    f.write_all(&4u16.to_le_bytes())?; // block align
     f.write_all(&16u16.to_le_bytes())?; // bits per sample
 
     // data chunk
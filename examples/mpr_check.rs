@@ -16,9 +16,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     emu.reset();
 
     // Check MPR values after reset
-    println!("MPR values after reset:");
-    for i in 0..8 {
-        println!("  MPR[{}] = ${:02X}", i, emu.bus.mpr(i));
+    println!("Memory map after reset:");
+    for region in emu.bus.memory_map() {
+        println!("  {region}");
     }
 
     // Run a few frames
@@ -29,15 +29,15 @@ fn main() -> Result<(), Box<dyn Error>> {
         if emu.take_frame().is_some() {
             frames += 1;
             if frames == 1 {
-                println!("\nMPR values after frame 1:");
-                for i in 0..8 {
-                    println!("  MPR[{}] = ${:02X}", i, emu.bus.mpr(i));
+                println!("\nMemory map after frame 1:");
+                for region in emu.bus.memory_map() {
+                    println!("  {region}");
                 }
             }
             if frames == 5 {
-                println!("\nMPR values after frame 5:");
-                for i in 0..8 {
-                    println!("  MPR[{}] = ${:02X}", i, emu.bus.mpr(i));
+                println!("\nMemory map after frame 5:");
+                for region in emu.bus.memory_map() {
+                    println!("  {region}");
                 }
 
                 // Read VDC status through direct I/O vs through $0000
@@ -15,11 +15,15 @@ mod hud_toast;
 use config::{AppConfig, ParsedBindings};
 use egui_ui::debugger::{CpuSnapshot, DebuggerAction, VdcSnapshot, describe_break};
 use egui_ui::gl_game::GlGameRenderer;
-use egui_ui::{CheatToolUi, DebuggerPanelData};
+use egui_ui::sprite_viewer::outline_sprite;
+use egui_ui::{ActiveTab, CheatToolUi, DebuggerPanelData};
 use hud_toast::{HudToast, draw_hud_toast, show_hud_toast};
-use pce::bus::AudioDiagnostics;
-use pce::debugger::{DebugTick, Debugger};
+use pce::bus::{AudioChannels, AudioDiagnostics, PixelFormat};
+use pce::cdl::cdl_path_for;
+use pce::debugger::{DebugTick, Debugger, HitCondition};
 use pce::emulator::Emulator;
+use pce::patch::{patch_hucard_image, patch_path_for};
+use pce::symbols::SymbolTable;
 use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::event::Event;
 use sdl2::keyboard::{Keycode, Mod};
@@ -32,6 +36,10 @@ use egui_sdl2_gl::ShaderVersion;
 use egui_sdl2_gl::gl;
 
 const PANEL_WIDTH_MIN: f32 = 300.0;
+/// The emulator hands out interleaved left/right samples.
+const AUDIO_CHANNELS: usize = 2;
+/// Outline drawn round the sprite picked in the debugger (ABGR8888 red).
+const SPRITE_HIGHLIGHT_COLOUR: u32 = 0xFF40_40FF;
 
 #[derive(Clone, Copy, Default)]
 struct QueueWriteStats {
@@ -152,11 +160,28 @@ fn main() -> Result<(), String> {
     let raw_args: Vec<String> = std::env::args().skip(1).collect();
     let (config_path, args) = config::parse_config_path(&raw_args);
     let mut args = args.into_iter();
-    let rom_path = args
-        .next()
-        .ok_or_else(|| "usage: video_sdl_egui <rom.[bin|pce]> [--config path.json]".to_string())?;
-    let rom = std::fs::read(&rom_path)
+    let rom_path = args.next().ok_or_else(|| {
+        "usage: video_sdl_egui <rom.[bin|pce]> [--config path.json] [--cdl] [--sym path.sym]"
+            .to_string()
+    })?;
+    let args: Vec<String> = args.collect();
+    let log_rom_usage = args.iter().any(|arg| arg == "--cdl");
+    let sym_path = args
+        .iter()
+        .position(|arg| arg == "--sym")
+        .and_then(|index| args.get(index + 1))
+        .map(PathBuf::from)
+        .or_else(|| {
+            let path = Path::new(&rom_path).with_extension("sym");
+            path.exists().then_some(path)
+        });
+    let mut rom = std::fs::read(&rom_path)
         .map_err(|err| format!("failed to read ROM {}: {err}", rom_path))?;
+    if let Some(patch_path) = patch_path_for(Path::new(&rom_path)) {
+        let patch = std::fs::read(&patch_path).map_err(|err| err.to_string())?;
+        rom = patch_hucard_image(&rom, &patch)
+            .map_err(|err| format!("failed to apply patch {}: {err}", patch_path.display()))?;
+    }
 
     let config_path = config_path.map(std::path::PathBuf::from).or_else(|| {
         let path = std::path::PathBuf::from("pce_config.json");
@@ -169,16 +194,37 @@ fn main() -> Result<(), String> {
     let auto_fire_period_ns = 1_000_000_000u128 / perf.auto_fire_hz.max(1);
 
     let mut emulator = Emulator::new();
+    // The GL renderer uploads frames straight into an RGBA texture.
+    emulator.set_pixel_format(PixelFormat::Abgr8888);
     let is_pce = Path::new(&rom_path)
         .extension()
         .map(|ext| ext.eq_ignore_ascii_case("pce"))
         .unwrap_or(false);
     let backup_path = Path::new(&rom_path).with_extension("sav");
     let bram_path = Path::new(&rom_path).with_extension("brm");
+    let cdl_path = cdl_path_for(Path::new(&rom_path));
     if is_pce {
         emulator
             .load_hucard(&rom)
             .map_err(|err| format!("failed to load HuCard: {err}"))?;
+        if log_rom_usage {
+            emulator.set_code_data_log_enabled(true);
+            if cdl_path.exists() {
+                let merged = std::fs::read(&cdl_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|bytes| {
+                        emulator
+                            .load_code_data_log(&bytes)
+                            .map_err(|err| err.to_string())
+                    });
+                if let Err(err) = merged {
+                    eprintln!(
+                        "warning: failed to load CDL from {}: {err}",
+                        cdl_path.display()
+                    );
+                }
+            }
+        }
         if backup_path.exists() {
             match std::fs::read(&backup_path) {
                 Ok(bytes) => {
@@ -217,12 +263,14 @@ fn main() -> Result<(), String> {
         emulator.load_program(0xC000, &rom);
     }
     emulator.set_audio_batch_size(perf.emu_audio_batch);
+    emulator.set_audio_channels(AudioChannels::Stereo);
     emulator.reset();
 
     let mut current_width = emulator.display_width();
     let mut current_height = emulator.display_height();
     let game_h = (current_height as u32) * config.window_scale;
-    let game_w = game_h * 4 / 3;
+    let game_w =
+        (current_width as f64 * emulator.pixel_aspect_ratio()).round() as u32 * config.window_scale;
 
     let sdl = sdl2::init().map_err(|e| e.to_string())?;
     let audio_subsystem = sdl.audio().map_err(|e| e.to_string())?;
@@ -259,7 +307,7 @@ fn main() -> Result<(), String> {
     // Audio
     let desired_audio = AudioSpecDesired {
         freq: Some(44_100),
-        channels: Some(1),
+        channels: Some(AUDIO_CHANNELS as u8),
         samples: Some(perf.audio_batch as u16),
     };
     let audio_device = audio_subsystem
@@ -286,6 +334,15 @@ fn main() -> Result<(), String> {
     let mut game_renderer = GlGameRenderer::new();
     let mut cheat_ui = CheatToolUi::new();
     let mut debugger = Debugger::new();
+    if let Some(path) = &sym_path {
+        match SymbolTable::load(path) {
+            Ok(symbols) => debugger.symbols = symbols,
+            Err(err) => eprintln!(
+                "warning: failed to load symbols from {}: {err}",
+                path.display()
+            ),
+        }
+    }
     let mut prev_panel_visible = cheat_ui.panel_visible;
     let mut panel_width_px: u32 = config.panel_width.max(PANEL_WIDTH_MIN as u32);
     let text_input = video.text_input();
@@ -491,25 +548,8 @@ fn main() -> Result<(), String> {
             current_height = new_height;
         }
 
-        // Apply cheats every iteration (to both work RAM and cart RAM)
-        {
-            let wram_len = emulator.work_ram().len();
-            let mgr = &cheat_ui.cheat_search_ui.manager;
-            for entry in &mgr.entries {
-                if !entry.enabled {
-                    continue;
-                }
-                let addr = entry.address as usize;
-                if addr < wram_len {
-                    emulator.work_ram_mut()[addr] = entry.value;
-                } else if let Some(cram) = emulator.backup_ram_mut() {
-                    let cram_addr = addr - wram_len;
-                    if cram_addr < cram.len() {
-                        cram[cram_addr] = entry.value;
-                    }
-                }
-            }
-        }
+        // Apply cheats every iteration (work RAM, cart RAM and ROM patches)
+        emulator.apply_cheats(&mut cheat_ui.cheat_search_ui.manager);
 
         // Upload game frame to GL texture
         if frame_buf_ready {
@@ -519,7 +559,20 @@ fn main() -> Result<(), String> {
                 current_height,
                 &mut hud_toast,
             );
+            if cheat_ui.panel_visible
+                && cheat_ui.active_tab == ActiveTab::Debugger
+                && let Some(sprite) = cheat_ui.sprite_viewer.highlighted()
+            {
+                outline_sprite(
+                    &mut frame_buf,
+                    current_width,
+                    current_height,
+                    sprite,
+                    SPRITE_HIGHLIGHT_COLOUR,
+                );
+            }
             game_renderer.upload_frame(&frame_buf, current_width, current_height);
+            game_renderer.set_pixel_aspect(emulator.pixel_aspect_ratio());
             frame_buf_ready = false;
         }
 
@@ -592,8 +645,24 @@ fn main() -> Result<(), String> {
                                             in_vblank: emulator.bus.vdc_in_vblank(),
                                             vram_dma_busy: emulator.bus.vdc_busy_cycles() > 0,
                                         },
+                                        call_stack: emulator.cpu.call_stack().frames(),
+                                        mpr: emulator.bus.mpr_array(),
+                                        read_memory: &|addr| emulator.bus.peek(addr),
                                         vram: emulator.bus.vdc_vram(),
                                         palette_rgb: &|idx| emulator.bus.vce_palette_rgb(idx),
+                                        palette_word: &|idx| emulator.bus.vce_palette_word(idx),
+                                        palette_writer: &|idx| emulator.bus.vce_palette_writer(idx),
+                                        layers: emulator.layer_toggles(),
+                                        render_bat_map: &|| emulator.render_bat_map(),
+                                        sprites: &|| {
+                                            emulator
+                                                .sprite_entries()
+                                                .into_iter()
+                                                .map(|sprite| {
+                                                    (sprite, emulator.render_sprite(sprite.index))
+                                                })
+                                                .collect()
+                                        },
                                         egui_ctx: ctx,
                                     };
                                     debug_action = cheat_ui.show_panel(
@@ -636,6 +705,9 @@ fn main() -> Result<(), String> {
                     DebuggerAction::None => {}
                     DebuggerAction::TogglePause => {
                         debugger.paused = !debugger.paused;
+                        if debugger.paused {
+                            debugger.cancel_step();
+                        }
                         show_hud_toast(
                             &mut hud_toast,
                             if debugger.paused { "PAUSE" } else { "RUN" },
@@ -644,17 +716,59 @@ fn main() -> Result<(), String> {
                     DebuggerAction::Step => {
                         debugger.request_step();
                     }
+                    DebuggerAction::StepOver => {
+                        debugger.request_step_over();
+                    }
+                    DebuggerAction::StepOut => {
+                        debugger.request_step_out();
+                    }
+                    DebuggerAction::RunTo(pc) => {
+                        debugger.request_run_to(pc);
+                        show_hud_toast(&mut hud_toast, format!("RUN TO ${pc:04X}"));
+                    }
                     DebuggerAction::ClearBreak => {
                         debugger.clear_break();
                     }
-                    DebuggerAction::AddBreakpoint(pc) => {
-                        debugger.add_breakpoint(pc);
-                        show_hud_toast(&mut hud_toast, format!("BP ${pc:04X}"));
-                    }
+                    DebuggerAction::AddBreakpointAt {
+                        location,
+                        condition,
+                        hit_condition,
+                    } => match debugger.add_breakpoint_at(&location, &condition, hit_condition) {
+                        Ok(pc) => {
+                            let conditional =
+                                !condition.is_empty() || hit_condition != HitCondition::Always;
+                            let mark = if conditional { "?" } else { "" };
+                            show_hud_toast(
+                                &mut hud_toast,
+                                format!("BP {location} ${pc:04X}{mark}"),
+                            );
+                        }
+                        Err(err) => {
+                            cheat_ui.debugger_ui.breakpoint_error = Some(err);
+                        }
+                    },
                     DebuggerAction::RemoveBreakpoint(pc) => {
                         debugger.remove_breakpoint(pc);
                         show_hud_toast(&mut hud_toast, format!("BP- ${pc:04X}"));
                     }
+                    DebuggerAction::ToggleBreakpoint(pc) => {
+                        debugger.toggle_breakpoint(pc);
+                        let sign = if debugger.has_breakpoint(pc) {
+                            '+'
+                        } else {
+                            '-'
+                        };
+                        show_hud_toast(&mut hud_toast, format!("BP{sign} ${pc:04X}"));
+                    }
+                    DebuggerAction::ResetHitCounts => {
+                        debugger.reset_hit_counts();
+                    }
+                    DebuggerAction::SetLayers(layers) => {
+                        emulator.set_layer_toggles(layers);
+                    }
+                    DebuggerAction::SetPaletteWord { index, word } => {
+                        emulator.bus.set_vce_palette_word(index, word);
+                    }
                 }
             }
 
@@ -686,13 +800,22 @@ fn main() -> Result<(), String> {
                 bram_path.display()
             );
         }
+        if let Some(log) = emulator.save_code_data_log() {
+            if let Err(err) = std::fs::write(&cdl_path, log) {
+                eprintln!(
+                    "warning: failed to write CDL to {}: {err}",
+                    cdl_path.display()
+                );
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Sample frames waiting in the device queue.
 fn queued_samples(device: &AudioQueue<i16>) -> usize {
-    device.size() as usize / std::mem::size_of::<i16>()
+    device.size() as usize / (std::mem::size_of::<i16>() * AUDIO_CHANNELS)
 }
 
 fn queue_audio_samples(
@@ -701,24 +824,25 @@ fn queue_audio_samples(
     audio_queue_max: usize,
 ) -> Result<QueueWriteStats, String> {
     let available = audio_queue_max.saturating_sub(queued_samples(device));
+    let frames = samples.len() / AUDIO_CHANNELS;
     if available == 0 {
         return Ok(QueueWriteStats {
             queued: 0,
-            dropped: samples.len(),
+            dropped: frames,
         });
     }
-    if samples.len() > available {
+    if frames > available {
         device
-            .queue_audio(&samples[..available])
+            .queue_audio(&samples[..available * AUDIO_CHANNELS])
             .map_err(|e| e.to_string())?;
         Ok(QueueWriteStats {
             queued: available,
-            dropped: samples.len() - available,
+            dropped: frames - available,
         })
     } else {
         device.queue_audio(samples).map_err(|e| e.to_string())?;
         Ok(QueueWriteStats {
-            queued: samples.len(),
+            queued: frames,
             dropped: 0,
         })
     }
@@ -13,6 +13,7 @@ mod hud_toast;
 
 use config::{AppConfig, ParsedBindings};
 use hud_toast::{HudToast, draw_hud_toast, show_hud_toast};
+use pce::bus::AudioChannels;
 use pce::emulator::Emulator;
 use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::event::Event;
@@ -23,6 +24,9 @@ use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
+/// The emulator hands out interleaved left/right samples.
+const AUDIO_CHANNELS: usize = 2;
+
 fn main() -> Result<(), String> {
     let raw_args: Vec<String> = std::env::args().skip(1).collect();
     let (config_path, args) = config::parse_config_path(&raw_args);
@@ -56,15 +60,18 @@ fn main() -> Result<(), String> {
         emulator.load_program(0xC000, &rom);
     }
     emulator.set_audio_batch_size(perf.emu_audio_batch);
+    emulator.set_audio_channels(AudioChannels::Stereo);
     emulator.reset();
 
     let mut current_width = emulator.display_width();
     let mut current_height = emulator.display_height();
 
-    // PC Engine outputs to a 4:3 CRT regardless of dot clock / pixel count.
-    // Window size is always 4:3, with height as reference.
+    // Pixels are not square: scale the width by the VCE dot clock's pixel
+    // aspect ratio so 256-, 336- and 512-wide modes all show at their
+    // real shape.
     let win_h = (current_height as u32) * config.window_scale;
-    let win_w = win_h * 4 / 3;
+    let win_w =
+        display_width_px(current_width, emulator.pixel_aspect_ratio()) * config.window_scale;
 
     let sdl = sdl2::init().map_err(|e| e.to_string())?;
     let audio = sdl.audio().map_err(|e| e.to_string())?;
@@ -92,7 +99,7 @@ fn main() -> Result<(), String> {
         .map_err(|e| e.to_string())?;
     let desired_audio = AudioSpecDesired {
         freq: Some(44_100),
-        channels: Some(1),
+        channels: Some(AUDIO_CHANNELS as u8),
         samples: Some(perf.audio_batch as u16),
     };
     let audio_device = audio
@@ -223,7 +230,8 @@ fn main() -> Result<(), String> {
             current_width = new_width;
             current_height = new_height;
             let h = (current_height as u32) * config.window_scale;
-            let w = h * 4 / 3;
+            let w = display_width_px(current_width, emulator.pixel_aspect_ratio())
+                * config.window_scale;
             canvas
                 .window_mut()
                 .set_size(w, h)
@@ -246,8 +254,9 @@ fn main() -> Result<(), String> {
                 draw_hud_toast(&mut frame, current_width, current_height, &mut hud_toast);
                 update_texture(&mut texture, &frame, current_width)?;
                 canvas.clear();
-                // Stretch texture to fill the window at 4:3 aspect ratio.
-                // SDL handles non-integer scaling via the dest rect.
+                // Stretch texture to fill the window, which is sized to the
+                // frame's display aspect ratio.  SDL handles non-integer
+                // scaling via the dest rect.
                 let (win_w, win_h) = canvas.output_size().map_err(|e| e.to_string())?;
                 canvas.copy(&texture, None, Some(Rect::new(0, 0, win_w, win_h)))?;
                 canvas.present();
@@ -267,8 +276,14 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
+/// Unscaled window width that shows `width` emulated pixels at their shape.
+fn display_width_px(width: usize, pixel_aspect: f64) -> u32 {
+    (width as f64 * pixel_aspect).round().max(1.0) as u32
+}
+
+/// Sample frames waiting in the device queue.
 fn queued_samples(device: &AudioQueue<i16>) -> usize {
-    device.size() as usize / std::mem::size_of::<i16>()
+    device.size() as usize / (std::mem::size_of::<i16>() * AUDIO_CHANNELS)
 }
 
 fn queue_audio_samples(
@@ -276,7 +291,7 @@ fn queue_audio_samples(
     samples: &[i16],
     audio_queue_max: usize,
 ) -> Result<(), String> {
-    let available = audio_queue_max.saturating_sub(queued_samples(device));
+    let available = audio_queue_max.saturating_sub(queued_samples(device)) * AUDIO_CHANNELS;
     if available == 0 {
         return Ok(());
     }
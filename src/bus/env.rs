@@ -1,4 +1,5 @@
 use super::Bus;
+use super::types::LayerToggles;
 
 /// Cached env-var flag: returns `true` when the env var is set (`.is_ok()`).
 macro_rules! env_bool {
@@ -115,6 +116,23 @@ impl Bus {
     env_option_usize!(env_bg_map_width_override, "PCE_BG_MAP_W_TILES", nonzero);
     env_option_usize!(env_sprite_max_entries, "PCE_SPR_MAX_ENTRIES");
 
+    /// Starting layer toggles: `PCE_DEBUG_BG_ONLY` hides sprites,
+    /// `PCE_DEBUG_SPR_ONLY` hides BG and `PCE_NO_SPR_LINE_LIMIT` lifts the
+    /// 16-sprite line limit.
+    pub(crate) fn env_layer_toggles() -> LayerToggles {
+        let mut layers = LayerToggles::default();
+        if Self::env_debug_bg_only() {
+            layers.sprites = false;
+        }
+        if Self::env_debug_spr_only() {
+            layers.background = false;
+        }
+        if Self::env_no_sprite_line_limit() {
+            layers.sprite_line_limit = false;
+        }
+        layers
+    }
+
     // Special env helpers with custom parsing (hex u8, hex-or-decimal i32)
     #[inline]
     pub(crate) fn env_pad_default() -> u8 {
@@ -1,7 +1,7 @@
 use super::*;
 
 impl Bus {
-    pub(super) fn read_control_register(&mut self, offset: usize) -> Option<u8> {
+    pub(super) fn read_control_register(&self, offset: usize) -> Option<u8> {
         match Self::decode_control_register(offset)? {
             ControlRegister::TimerCounter => Some(self.timer.read_counter()),
             ControlRegister::TimerControl => Some(self.timer.control()),
@@ -158,14 +158,19 @@ impl Bus {
         Self::vdc_port_kind(offset).is_some() || matches!(offset, 0x0400..=0x07FF | 0x1C40..=0x1C47)
     }
 
-    pub(super) fn read_io_internal(&mut self, raw_offset: usize) -> u8 {
-        // The HuC6280 only decodes A0–A10 for the hardware page; fold everything
-        // into 0x0000–0x1FFF first, then optional 0x0200 folding for debug.
+    /// Fold a hardware-page offset the way the HuC6280 decodes it: only
+    /// A0–A12 matter, plus the optional 0x0200 folding used for debugging.
+    fn fold_io_offset(raw_offset: usize) -> usize {
         let mut offset = raw_offset & 0x1FFF;
         offset = Self::normalized_io_offset(offset);
         if Self::env_route_02xx_hw() && offset >= 0x0200 && offset < 0x0220 {
             offset &= 0x01FF; // map 0x0200–0x021F to 0x0000–0x001F
         }
+        offset
+    }
+
+    pub(super) fn read_io_internal(&mut self, raw_offset: usize) -> u8 {
+        let offset = Self::fold_io_offset(raw_offset);
         if let Some(port) = Self::vdc_port_kind(offset) {
             #[cfg(feature = "trace_hw_writes")]
             {
@@ -184,13 +189,43 @@ impl Bus {
                 let sub = (offset & 0x0007) as u16;
                 self.read_vce_port(sub)
             }
+            0x1C62 => self.psg.read_data(),
+            BRAM_LOCK_PORT => {
+                *self.bram_unlocked = false;
+                0xFF
+            }
+            _ => self.peek_io(offset),
+        }
+    }
+
+    /// Value a hardware-page read at `raw_offset` would return, without the
+    /// side effects: VDC status stays unacknowledged, VDC/VCE read latches
+    /// and auto-increment are left alone, and BRAM stays unlocked.
+    pub(super) fn peek_io(&self, raw_offset: usize) -> u8 {
+        let offset = Self::fold_io_offset(raw_offset);
+        if let Some(port) = Self::vdc_port_kind(offset) {
+            return match port {
+                VdcPort::Control => self.vdc.peek_status(),
+                VdcPort::Data => self.vdc.peek_port(if offset & 0x01 != 0 { 2 } else { 1 }),
+            };
+        }
+        match offset {
+            0x0400..=0x07FF | 0x1C40..=0x1C47 => match offset & 0x0007 {
+                0x00 => self.vce.read_control_low(),
+                0x01 => self.vce.read_control_high(),
+                0x02 => self.vce.read_address_low(),
+                0x03 => self.vce.read_address_high(),
+                0x04 => self.vce.peek_data_low(),
+                0x05 => self.vce.peek_data_high(),
+                _ => 0xFF,
+            },
             // HuC6280 PSG native map is direct registers at $0800-$080F.
             0x0800..=0x0BFF => self.psg.read_direct(offset & 0x0F),
             // Keep legacy 4-port mirror behavior for older tests/tooling.
             0x1C60..=0x1C63 => match offset & 0x03 {
                 0x00 => self.psg.read_address(),
                 0x01 => self.io[offset],
-                0x02 => self.psg.read_data(),
+                0x02 => self.psg.read_direct(self.psg.read_address() as usize),
                 _ => self.psg.read_status(),
             },
             0x0C00..=0x0FFF => {
@@ -214,13 +249,7 @@ impl Bus {
                     self.io[offset]
                 }
             }
-            0x1800..=0x1BFF => match offset {
-                BRAM_LOCK_PORT => {
-                    *self.bram_unlocked = false;
-                    0xFF
-                }
-                _ => 0xFF,
-            },
+            0x1800..=0x1BFF => 0xFF,
             0x1C00..=0x1FFF => {
                 if let Some(value) = self.read_control_register(offset) {
                     value
@@ -233,12 +262,7 @@ impl Bus {
     }
 
     pub(super) fn write_io_internal(&mut self, raw_offset: usize, value: u8) {
-        // Fold to 0x0000–0x1FFF to mirror HuC6280 hardware page decode.
-        let mut offset = raw_offset & 0x1FFF;
-        offset = Self::normalized_io_offset(offset);
-        if Self::env_route_02xx_hw() && offset >= 0x0200 && offset < 0x0220 {
-            offset &= 0x01FF; // map 0x0200–0x021F to 0x0000–0x001F
-        }
+        let offset = Self::fold_io_offset(raw_offset);
         if let Some(port) = Self::vdc_port_kind(offset) {
             #[cfg(feature = "trace_hw_writes")]
             {
@@ -332,12 +356,23 @@ impl Bus {
             self.note_vce_palette_access_flicker();
         }
         match addr & 0x0007 {
-            0x00 => self.vce.write_control_low(value),
+            0x00 => {
+                self.vce.write_control_low(value);
+                self.vdc.set_frame_lines(self.vce.frame_lines());
+            }
             0x01 => self.vce.write_control_high(value),
             0x02 => self.vce.write_address_low(value),
             0x03 => self.vce.write_address_high(value),
-            0x04 => self.vce.write_data_low(value),
-            0x05 => self.vce.write_data_high(value),
+            0x04 | 0x05 => {
+                let index = self.vce.address_index();
+                if addr & 0x0007 == 0x04 {
+                    self.vce.write_data_low(value);
+                } else {
+                    self.vce.write_data_high(value);
+                }
+                self.palette_writes.writers[index] =
+                    Some(PaletteWriter::Cpu(self.palette_writes.pc));
+            }
             _ => {}
         }
     }
@@ -7,11 +7,91 @@ impl Bus {
         (self.banks[index], offset)
     }
 
+    /// MPR slot, bank and backing-store offset that `addr` maps to.
+    pub fn describe_address(&self, addr: u16) -> MemoryRegion {
+        let (mapping, offset) = self.resolve(addr);
+        let slot = (addr as usize) >> 13;
+        MemoryRegion {
+            addr,
+            slot,
+            mpr: self.mpr[slot],
+            mapping,
+            offset: mapping.base() + offset,
+        }
+    }
+
+    /// The eight current mappings, one per MPR slot, described at the start
+    /// of each page.
+    pub fn memory_map(&self) -> [MemoryRegion; NUM_BANKS] {
+        std::array::from_fn(|slot| self.describe_address((slot * PAGE_SIZE) as u16))
+    }
+
+    /// Byte a CPU read of `addr` would return under the current mapping,
+    /// without side effects: I/O status stays unacknowledged, read latches
+    /// do not advance, BRAM stays unlocked and the code/data logger is not
+    /// touched.
+    pub fn peek(&self, addr: u16) -> u8 {
+        if (0x2000..=0x3FFF).contains(&addr)
+            && (matches!(self.banks.get(1), Some(BankMapping::Hardware))
+                || Self::env_relax_io_mirror()
+                || Self::env_extreme_mirror()
+                || Self::env_vdc_ultra_mirror())
+        {
+            return self.peek_io((addr - 0x2000) as usize);
+        }
+        let (mapping, offset) = self.resolve(addr);
+        if matches!(mapping, BankMapping::Hardware)
+            && let Some(index) = Self::mpr_index_for_addr(addr)
+        {
+            return self.mpr[index];
+        }
+        self.peek_mapping(mapping, offset)
+    }
+
+    /// Byte at `offset` within the 8 KiB page that MPR value `bank` selects,
+    /// whether or not it is currently mapped. Same rules as [`Bus::peek`].
+    pub fn peek_physical(&self, bank: u8, offset: usize) -> u8 {
+        self.peek_mapping(self.mapping_for_mpr(bank), offset & (PAGE_SIZE - 1))
+    }
+
+    fn peek_mapping(&self, mapping: BankMapping, offset: usize) -> u8 {
+        match mapping {
+            BankMapping::Ram { base } => self.ram.get(base + offset).copied().unwrap_or(0),
+            BankMapping::Rom { base } => self.rom_byte(base + offset),
+            BankMapping::CartRam { base } => {
+                self.cart_ram.get(base + offset).copied().unwrap_or(0x00)
+            }
+            BankMapping::Bram => self.read_bram_byte(offset),
+            // Offsets $1800-$1FFF have no I/O device and fall through to
+            // the HuCard bus, as in `read`.
+            BankMapping::Hardware
+                if offset >= 0x1800 && offset != BRAM_LOCK_PORT && offset != BRAM_UNLOCK_PORT =>
+            {
+                let rom_pages = self.rom_pages();
+                if rom_pages == 0 {
+                    return 0xFF;
+                }
+                let rom_page = Self::mirror_rom_bank(0xFF, rom_pages);
+                self.rom_byte(rom_page * PAGE_SIZE + offset)
+            }
+            BankMapping::Hardware => self.peek_io(offset),
+        }
+    }
+
     pub(super) fn update_mpr(&mut self, bank: usize) {
-        let value = self.mpr[bank];
+        let mapping = if bank == 1 && Self::env_force_mpr1_hardware() {
+            BankMapping::Hardware
+        } else {
+            self.mapping_for_mpr(self.mpr[bank])
+        };
+        self.banks[bank] = mapping;
+    }
+
+    /// What an MPR `value` selects, given the loaded ROM and RAM sizes.
+    pub(super) fn mapping_for_mpr(&self, value: u8) -> BankMapping {
         let rom_pages = self.rom_pages();
         let cart_pages = self.cart_ram_pages();
-        let mapping = match value {
+        match value {
             0xFF => BankMapping::Hardware,
             BRAM_PAGE => BankMapping::Bram,
             0xF8..=0xFD => {
@@ -37,13 +117,7 @@ impl Bus {
                     BankMapping::Ram { base: 0 }
                 }
             }
-        };
-        let mapping = if bank == 1 && Self::env_force_mpr1_hardware() {
-            BankMapping::Hardware
-        } else {
-            mapping
-        };
-        self.banks[bank] = mapping;
+        }
     }
 
     pub(super) fn total_ram_pages(&self) -> usize {
@@ -1,3 +1,5 @@
+use crate::cdl::CodeDataLog;
+use crate::cheat::{RomPatch, RomPatchTable};
 use crate::psg::Psg;
 use crate::vce::Vce;
 use crate::vdc::{
@@ -42,16 +44,28 @@ mod env;
 mod font;
 mod io;
 mod mapping;
+mod output;
 mod render;
 mod types;
+mod viewer;
 
 use self::types::TransientU64;
 use self::types::{
-    BankMapping, ControlRegister, IoPort, PaletteFlickerEvent, Timer, TransientBool, TransientBram,
-    TransientPaletteFlicker, TransientUsize, VdcPort,
+    ControlRegister, FrameOutput, IoPort, PaletteFlickerEvent, PaletteWriteLog, ScanlineFrame,
+    Timer, TransientAudioChannels, TransientBool, TransientBram, TransientPaletteFlicker,
+    TransientUsize, VdcPort,
 };
 use font::FONT;
 
+pub use self::types::{
+    AudioChannels, BankMapping, CropMargins, LayerToggles, MemoryRegion, OutputGeometry,
+    PaletteWriter,
+};
+pub use self::viewer::{
+    BatMap, MapRect, RgbaImage, SPRITE_SHEET_COLUMNS, SheetPalette, SpriteEntry, TILE_SHEET_COLUMNS,
+};
+pub use crate::vce::{DotClock, PixelFormat};
+
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct AudioDiagnostics {
     pub master_clock_hz: u32,
@@ -113,6 +127,16 @@ pub struct Bus {
     /// scene transitions is invisible due to phosphor response/blanking.
     /// Not serialized — transient render state, safe to default to false.
     burst_transition: TransientBool,
+    /// Rows drawn so far in the frame being rendered line by line.
+    scanline_frame: ScanlineFrame,
+    /// Host-chosen output geometry, crop and layer toggles.
+    frame_output: FrameOutput,
+    /// Host-chosen audio layout: interleaved stereo or a mono downmix.
+    audio_channels: TransientAudioChannels,
+    /// Who last wrote each palette entry, for the palette viewer.
+    palette_writes: PaletteWriteLog,
+    cdl: CodeDataLog,
+    rom_patches: RomPatchTable,
     #[cfg(feature = "trace_hw_writes")]
     last_pc_for_trace: Option<u16>,
     #[cfg(debug_assertions)]
@@ -208,6 +232,12 @@ impl Bus {
             bg_priority: vec![false; FRAME_WIDTH * FRAME_HEIGHT],
             sprite_line_counts: vec![0; FRAME_HEIGHT],
             burst_transition: TransientBool(false),
+            scanline_frame: ScanlineFrame::default(),
+            frame_output: FrameOutput::default(),
+            audio_channels: TransientAudioChannels::default(),
+            palette_writes: PaletteWriteLog::default(),
+            cdl: CodeDataLog::default(),
+            rom_patches: RomPatchTable::default(),
             #[cfg(feature = "trace_hw_writes")]
             last_pc_for_trace: None,
             #[cfg(debug_assertions)]
@@ -215,6 +245,7 @@ impl Bus {
             #[cfg(feature = "trace_hw_writes")]
             st0_lock_window: 0,
         };
+        bus.vdc.set_frame_lines(bus.vce.frame_lines());
 
         // Power-on mapping: expose internal RAM in bank 0 for ZP/stack and
         // keep all banks backed by RAM. The HuCARD loader remaps banks 4–7
@@ -300,7 +331,12 @@ impl Bus {
         }
         match mapping {
             BankMapping::Ram { base } => self.ram[base + offset],
-            BankMapping::Rom { base } => self.rom.get(base + offset).copied().unwrap_or(0xFF),
+            BankMapping::Rom { base } => {
+                if self.cdl.is_enabled() {
+                    self.cdl.mark(base + offset);
+                }
+                self.rom_byte(base + offset)
+            }
             BankMapping::CartRam { base } => {
                 self.cart_ram.get(base + offset).copied().unwrap_or(0x00)
             }
@@ -320,7 +356,10 @@ impl Bus {
                     if rom_pages > 0 {
                         let rom_page = Self::mirror_rom_bank(0xFF, rom_pages);
                         let rom_addr = rom_page * PAGE_SIZE + io_offset;
-                        return self.rom.get(rom_addr).copied().unwrap_or(0xFF);
+                        if self.cdl.is_enabled() {
+                            self.cdl.mark(rom_addr);
+                        }
+                        return self.rom_byte(rom_addr);
                     }
                     return 0xFF;
                 }
@@ -482,6 +521,8 @@ impl Bus {
         self.vdc.reset();
         self.psg.reset();
         self.vce.reset();
+        self.vdc.set_frame_lines(self.vce.frame_lines());
+        self.palette_writes.writers.fill(None);
         self.audio_phi_accumulator = 0;
         self.audio_psg_accumulator = TransientU64(0);
         self.audio_buffer.clear();
@@ -501,6 +542,7 @@ impl Bus {
         self.bg_opaque.fill(false);
         self.bg_priority.fill(false);
         self.sprite_line_counts.fill(0);
+        self.scanline_frame = ScanlineFrame::default();
         self.vdc.clear_sprite_overflow();
         #[cfg(debug_assertions)]
         {
@@ -521,6 +563,67 @@ impl Bus {
         }
     }
 
+    /// ROM image byte as the CPU sees it, after ROM cheat substitution.
+    #[inline]
+    pub(super) fn rom_byte(&self, rom_offset: usize) -> u8 {
+        let value = self.rom.get(rom_offset).copied().unwrap_or(0xFF);
+        if self.rom_patches.is_empty() {
+            value
+        } else {
+            self.rom_patches.substitute(rom_offset, value)
+        }
+    }
+
+    /// Replace the ROM cheat substitutions (see [`crate::cheat::RomPatch`]).
+    pub fn set_rom_patches(&mut self, patches: Vec<RomPatch>) {
+        self.rom_patches.set(patches);
+    }
+
+    pub fn rom_patches(&self) -> &[RomPatch] {
+        self.rom_patches.patches()
+    }
+
+    pub(crate) fn take_rom_patches(&mut self) -> RomPatchTable {
+        std::mem::take(&mut self.rom_patches)
+    }
+
+    pub(crate) fn restore_rom_patches(&mut self, patches: RomPatchTable) {
+        self.rom_patches = patches;
+    }
+
+    pub fn code_data_log(&self) -> &CodeDataLog {
+        &self.cdl
+    }
+
+    pub fn code_data_log_mut(&mut self) -> &mut CodeDataLog {
+        &mut self.cdl
+    }
+
+    /// Start or stop flagging ROM bytes as they are read; see [`crate::cdl`].
+    pub fn set_code_data_log_enabled(&mut self, enabled: bool) {
+        self.cdl.set_enabled(enabled, self.rom.len());
+    }
+
+    #[inline]
+    pub(crate) fn cdl_enabled(&self) -> bool {
+        self.cdl.is_enabled()
+    }
+
+    /// Read with the code/data logger tagging any ROM byte as `access`.
+    pub(crate) fn read_logged(&mut self, addr: u16, access: u8) -> u8 {
+        let previous = self.cdl.set_access(access);
+        let value = self.read(addr);
+        self.cdl.set_access(previous);
+        value
+    }
+
+    /// Whether `addr` currently decodes to the VDC data register
+    /// ($0002/$0003, mirrored through the VDC's 1 KiB window).
+    pub(crate) fn is_vdc_data_port(&self, addr: u16) -> bool {
+        let (mapping, offset) = self.resolve(addr);
+        matches!(mapping, BankMapping::Hardware) && offset < 0x0400 && offset & 0x02 != 0
+    }
+
     pub fn map_bank_to_ram(&mut self, bank: usize, page: usize) {
         if bank < NUM_BANKS {
             let pages = self.total_ram_pages();
@@ -582,8 +685,11 @@ impl Bus {
         self.bg_opaque.fill(false);
         self.bg_priority.fill(false);
         self.sprite_line_counts.fill(0);
+        self.scanline_frame = ScanlineFrame::default();
         self.psg.post_load_fixup();
         self.vdc.post_load_fixup();
+        self.vdc.set_frame_lines(self.vce.frame_lines());
+        self.vce.refresh_colours();
         self.refresh_vdc_irq();
     }
 
@@ -794,10 +900,25 @@ impl Bus {
         self.vce.palette_word(index)
     }
 
+    /// Palette entry as `0x00RRGGBB`, whatever the output pixel format.
     pub fn vce_palette_rgb(&self, index: usize) -> u32 {
         self.vce.palette_rgb(index)
     }
 
+    /// What last stored palette entry `index`, or `None` if nothing has
+    /// since reset or the last state load.
+    pub fn vce_palette_writer(&self, index: usize) -> Option<PaletteWriter> {
+        self.palette_writes.writers.get(index).copied().flatten()
+    }
+
+    /// Store the 9-bit GRB `word` in palette entry `index` from the host,
+    /// e.g. a palette editor.  Later pixels use the new colour.
+    pub fn set_vce_palette_word(&mut self, index: usize, word: u16) {
+        let index = index & 0x1FF;
+        self.vce.set_palette_word(index, word & 0x1FF);
+        self.palette_writes.writers[index] = Some(PaletteWriter::Host);
+    }
+
     #[cfg(test)]
     pub fn vdc_set_status_for_test(&mut self, mask: u8) {
         self.vdc.raise_status(mask);
@@ -815,6 +936,7 @@ impl Bus {
         if !enabled {
             self.frame_ready = false;
             self.vdc.clear_frame_trigger();
+            self.scanline_frame = ScanlineFrame::default();
         }
     }
 
@@ -836,7 +958,21 @@ impl Bus {
             self.interrupt_request |= IRQ_REQUEST_TIMER;
         }
 
-        if self.vdc.tick(phi_cycles) {
+        // Render each scanline as soon as the VDC has displayed it, so
+        // VRAM, SATB and palette writes made mid-frame only show up on
+        // the lines after them.
+        self.vdc.tick(phi_cycles);
+        let mut irq_recalc = false;
+        while let Some(line) = self.vdc.take_completed_line() {
+            if *self.video_output_enabled {
+                self.render_scanline(line);
+                if self.vdc.frame_ready() {
+                    self.finish_frame();
+                }
+            }
+            irq_recalc |= self.vdc.start_next_scanline();
+        }
+        if irq_recalc {
             self.refresh_vdc_irq();
         }
 
@@ -845,13 +981,9 @@ impl Bus {
             self.refresh_vdc_irq();
         }
 
-        if self.vdc.frame_ready() {
-            if !*self.video_output_enabled {
-                self.vdc.clear_frame_trigger();
-                self.frame_ready = false;
-            } else {
-                self.render_frame_from_vram();
-            }
+        if self.vdc.frame_ready() && !*self.video_output_enabled {
+            self.vdc.clear_frame_trigger();
+            self.frame_ready = false;
         }
 
         if self.timer.tick(cycles, high_speed) {
@@ -869,16 +1001,43 @@ impl Bus {
         self.irq_pending()
     }
 
+    /// Record the PC of the instruction about to run, so VCE data port
+    /// writes can be credited to it.
+    pub(crate) fn note_instruction_pc(&mut self, pc: u16) {
+        self.palette_writes.pc = pc;
+    }
+
     #[cfg(feature = "trace_hw_writes")]
     pub fn set_last_pc_for_trace(&mut self, pc: u16) {
         self.last_pc_for_trace = Some(pc);
     }
 
+    /// Render one sample outside the normal audio queue, downmixed to mono.
     pub fn psg_sample(&mut self) -> i16 {
+        let (left, right) = self.psg_stereo_sample();
+        downmix(left, right)
+    }
+
+    /// Render one `(left, right)` sample frame outside the normal audio queue.
+    pub fn psg_stereo_sample(&mut self) -> (i16, i16) {
         let psg_cycles = self.psg_cycles_for_host_sample();
         self.psg.render_host_sample(psg_cycles)
     }
 
+    pub fn audio_channels(&self) -> AudioChannels {
+        self.audio_channels.0
+    }
+
+    /// Choose a mono downmix (the default) or interleaved stereo for
+    /// [`take_audio_samples`](Self::take_audio_samples).  Samples already
+    /// queued in the old layout are dropped.
+    pub fn set_audio_channels(&mut self, channels: AudioChannels) {
+        if self.audio_channels.0 != channels {
+            self.audio_channels = TransientAudioChannels(channels);
+            self.audio_buffer.clear();
+        }
+    }
+
     /// Returns per-channel PSG state: (frequency, control, balance, noise_control)
     pub fn psg_channel_info(&self, ch: usize) -> (u16, u8, u8, u8) {
         if ch < 6 {
@@ -943,7 +1102,7 @@ impl Bus {
             generated_samples: *self.audio_total_generated_samples,
             drained_samples: *self.audio_total_drained_samples,
             drain_calls: *self.audio_total_drain_calls,
-            pending_bus_samples: self.audio_buffer.len(),
+            pending_bus_samples: self.audio_buffer.len() / self.audio_channels.0.count(),
             phi_remainder: self.audio_phi_accumulator,
         }
     }
@@ -994,8 +1153,10 @@ impl Bus {
             .push(PaletteFlickerEvent { row, x, len });
     }
 
+    /// Everything queued since the last call, laid out as
+    /// [`audio_channels`](Self::audio_channels) says.
     pub fn take_audio_samples(&mut self) -> Vec<i16> {
-        let drained = self.audio_buffer.len() as u64;
+        let drained = (self.audio_buffer.len() / self.audio_channels.0.count()) as u64;
         if drained != 0 {
             self.audio_total_drained_samples.0 =
                 self.audio_total_drained_samples.0.saturating_add(drained);
@@ -1019,21 +1180,7 @@ impl Bus {
             *buf = Self::synth_title_frame();
             return true;
         }
-        let w = self.current_display_width;
-        let h = self.current_display_height;
-        let x_off = *self.current_display_x_offset;
-        let y_off = self.current_display_y_offset;
-        let needed = w * h;
-        buf.resize(needed, 0);
-        for y in 0..h {
-            let src_y = y + y_off;
-            if src_y >= FRAME_HEIGHT {
-                break;
-            }
-            let src = src_y * FRAME_WIDTH + x_off;
-            let dst = y * w;
-            buf[dst..dst + w].copy_from_slice(&self.framebuffer[src..src + w]);
-        }
+        self.copy_output_frame(buf);
         true
     }
 
@@ -1050,20 +1197,8 @@ impl Bus {
         if Self::env_force_title_now() || Self::env_force_title_scene() {
             return Some(Self::synth_title_frame());
         }
-        let w = self.current_display_width;
-        let h = self.current_display_height;
-        let x_off = *self.current_display_x_offset;
-        let y_off = self.current_display_y_offset;
-        let mut out = vec![0u32; w * h];
-        for y in 0..h {
-            let src_y = y + y_off;
-            if src_y >= FRAME_HEIGHT {
-                break;
-            }
-            let src = src_y * FRAME_WIDTH + x_off;
-            let dst = y * w;
-            out[dst..dst + w].copy_from_slice(&self.framebuffer[src..src + w]);
-        }
+        let mut out = Vec::new();
+        self.copy_output_frame(&mut out);
         Some(out)
     }
 
@@ -1122,6 +1257,7 @@ impl Bus {
         for (i, slot) in self.vce.palette.iter_mut().enumerate() {
             *slot = ((i as u16 & 0x0F) << 8) | (((i as u16 >> 4) & 0x0F) << 4) | (i as u16 & 0x0F);
         }
+        self.vce.refresh_colours();
         // Tiles: simple 8x8 patterns
         for tile in 0..0x200 {
             for row in 0..8 {
@@ -1151,12 +1287,14 @@ impl Bus {
         self.frame_ready = true;
     }
 
+    /// The whole 512×240 render target, in the output pixel format.
     pub fn framebuffer(&self) -> &[u32] {
         &self.framebuffer
     }
 
+    /// Width of the frames `take_frame` returns.
     pub fn display_width(&self) -> usize {
-        self.current_display_width
+        self.output_size().0
     }
 
     fn compute_display_height(&self) -> (usize, usize) {
@@ -1185,10 +1323,37 @@ impl Bus {
         (active_count, first_active)
     }
 
+    /// Height of the frames `take_frame` returns.
     pub fn display_height(&self) -> usize {
-        self.current_display_height
+        self.output_size().1
+    }
+
+    /// Pixel clock currently selected on the VCE.
+    pub fn dot_clock(&self) -> DotClock {
+        self.vce.dot_clock()
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.vce.pixel_format()
     }
 
+    /// Pick the layout of framebuffer and frame pixels.  Rows drawn from
+    /// now on use it.
+    pub fn set_pixel_format(&mut self, format: PixelFormat) {
+        self.vce.set_pixel_format(format);
+    }
+
+    /// Scanlines per field (262 or 263) currently selected on the VCE.
+    pub fn frame_lines(&self) -> u16 {
+        self.vce.frame_lines()
+    }
+
+    /// Whether the VCE strips the colour burst, i.e. output is grayscale.
+    pub fn grayscale(&self) -> bool {
+        self.vce.grayscale()
+    }
+
+    /// Framebuffer row the active display starts on.
     pub fn display_y_offset(&self) -> usize {
         self.current_display_y_offset
     }
@@ -1325,6 +1490,12 @@ impl Bus {
         &mut self.ram[base..base + PAGE_SIZE]
     }
 
+    /// Work RAM page and cart RAM (empty when absent), borrowed together.
+    pub fn work_and_cart_ram_mut(&mut self) -> (&mut [u8], &mut [u8]) {
+        let base = self.mpr1_ram_base();
+        (&mut self.ram[base..base + PAGE_SIZE], &mut self.cart_ram)
+    }
+
     fn mpr1_ram_base(&self) -> usize {
         let mpr1 = self.mpr[1];
         if (0xF8..=0xFD).contains(&mpr1) {
@@ -1387,8 +1558,11 @@ impl Bus {
         while self.audio_phi_accumulator >= MASTER_CLOCK_HZ as u64 {
             self.audio_phi_accumulator -= MASTER_CLOCK_HZ as u64;
             let psg_cycles = self.psg_cycles_for_host_sample();
-            let sample = self.psg.render_host_sample(psg_cycles);
-            self.audio_buffer.push(sample);
+            let (left, right) = self.psg.render_host_sample(psg_cycles);
+            match self.audio_channels.0 {
+                AudioChannels::Mono => self.audio_buffer.push(downmix(left, right)),
+                AudioChannels::Stereo => self.audio_buffer.extend_from_slice(&[left, right]),
+            }
             self.audio_total_generated_samples.0 =
                 self.audio_total_generated_samples.0.saturating_add(1);
         }
@@ -1516,9 +1690,8 @@ impl Bus {
 
         for _ in 0..words {
             let word = *self.vdc.vram.get(src as usize).unwrap_or(&0);
-            if let Some(slot) = self.vce.palette.get_mut(index) {
-                *slot = word;
-            }
+            self.vce.set_palette_word(index, word);
+            self.palette_writes.writers[index] = Some(PaletteWriter::CramDma);
             index = (index + 1) & 0x01FF;
             src = Vdc::advance_vram_addr(src, false);
         }
@@ -1578,9 +1751,7 @@ impl Bus {
         if Self::env_force_cram_from_vram() {
             for i in 0..0x200 {
                 let word = self.vdc.vram.get(i).copied().unwrap_or(0);
-                if let Some(slot) = self.vce.palette.get_mut(i) {
-                    *slot = word;
-                }
+                self.vce.set_palette_word(i, word);
             }
             #[cfg(any(debug_assertions, feature = "trace_hw_writes"))]
             eprintln!("  DEBUG PCE_FORCE_CRAM_FROM_VRAM applied (first 512 words)");
@@ -1602,6 +1773,11 @@ impl Bus {
     }
 }
 
+/// Average of a stereo pair.
+fn downmix(left: i16, right: i16) -> i16 {
+    ((left as i32 + right as i32) / 2) as i16
+}
+
 impl From<CompatBusStateV1> for Bus {
     fn from(value: CompatBusStateV1) -> Self {
         Self {
@@ -1642,6 +1818,12 @@ impl From<CompatBusStateV1> for Bus {
             bg_priority: value.bg_priority,
             sprite_line_counts: value.sprite_line_counts,
             burst_transition: value.burst_transition,
+            scanline_frame: ScanlineFrame::default(),
+            frame_output: FrameOutput::default(),
+            audio_channels: TransientAudioChannels::default(),
+            palette_writes: PaletteWriteLog::default(),
+            cdl: CodeDataLog::default(),
+            rom_patches: RomPatchTable::default(),
             #[cfg(feature = "trace_hw_writes")]
             last_pc_for_trace: value.last_pc_for_trace,
             #[cfg(debug_assertions)]
@@ -0,0 +1,127 @@
+use crate::vdc::{FRAME_HEIGHT, FRAME_WIDTH};
+
+use super::Bus;
+use super::types::{CropMargins, LayerToggles, OutputGeometry};
+
+/// VCE clock, counted from a display start of HDS=0, that sits in the
+/// middle of a TV picture.  A 256-dot display at HDS=2 or a 336-dot one at
+/// HDS=3 are both centred on it.
+const TV_CENTRE_CLOCK: usize = 576;
+/// VCE clocks per fixed-canvas dot (5.37 MHz).
+const CANVAS_DOT_CLOCKS: usize = 4;
+
+impl Bus {
+    pub fn output_geometry(&self) -> OutputGeometry {
+        self.frame_output.geometry
+    }
+
+    /// Choose how later frames are sized; see [`OutputGeometry`].
+    pub fn set_output_geometry(&mut self, geometry: OutputGeometry) {
+        self.frame_output.geometry = geometry;
+    }
+
+    pub fn crop_margins(&self) -> CropMargins {
+        self.frame_output.crop
+    }
+
+    /// Trim later frames by `crop` on each edge.  Margins that would leave
+    /// nothing keep a single pixel.
+    pub fn set_crop_margins(&mut self, crop: CropMargins) {
+        self.frame_output.crop = crop;
+    }
+
+    pub fn layer_toggles(&self) -> LayerToggles {
+        self.frame_output.layers
+    }
+
+    /// Choose what later rows draw; see [`LayerToggles`].
+    pub fn set_layer_toggles(&mut self, layers: LayerToggles) {
+        self.frame_output.layers = layers;
+    }
+
+    pub(crate) fn update_layer_toggles(&mut self, update: impl FnOnce(&mut LayerToggles)) {
+        update(&mut self.frame_output.layers);
+    }
+
+    /// Take over the host's output settings (geometry, crop, layers, pixel
+    /// format and audio channels) from the bus this one replaces.
+    pub(crate) fn inherit_host_settings(&mut self, previous: &Bus) {
+        let settings = previous.frame_output;
+        self.frame_output.geometry = settings.geometry;
+        self.frame_output.crop = settings.crop;
+        self.frame_output.layers = settings.layers;
+        self.set_pixel_format(previous.pixel_format());
+        self.audio_channels = previous.audio_channels;
+    }
+
+    /// Remember the dot clock of the frame just finished, so a fixed canvas
+    /// can resample it.
+    pub(super) fn record_frame_placement(&mut self) {
+        self.frame_output.dot_divider = self.vce.dot_clock().divider() as usize;
+    }
+
+    /// Size of the frames handed to the front-end, after cropping.
+    pub(super) fn output_size(&self) -> (usize, usize) {
+        let (width, height) = match self.frame_output.geometry {
+            OutputGeometry::Native => (self.current_display_width, self.current_display_height),
+            OutputGeometry::Fixed { width, height } => (width, height),
+        };
+        let crop = self.frame_output.crop;
+        (
+            width.saturating_sub(crop.left + crop.right).max(1),
+            height.saturating_sub(crop.top + crop.bottom).max(1),
+        )
+    }
+
+    /// Copy the finished frame into `buf` at the output geometry.  Pixels
+    /// with no framebuffer source show the overscan colour.
+    pub(super) fn copy_output_frame(&self, buf: &mut Vec<u32>) {
+        let (width, height) = self.output_size();
+        let crop = self.frame_output.crop;
+        let overscan = self.vce.colour(0x100);
+        buf.resize(width * height, 0);
+        for (y, dst) in buf.chunks_exact_mut(width).enumerate() {
+            let Some(src_y) = self.output_source_row(y + crop.top) else {
+                dst.fill(overscan);
+                continue;
+            };
+            let src = &self.framebuffer[src_y * FRAME_WIDTH..(src_y + 1) * FRAME_WIDTH];
+            for (x, pixel) in dst.iter_mut().enumerate() {
+                *pixel = self
+                    .output_source_column(x + crop.left)
+                    .map_or(overscan, |src_x| src[src_x]);
+            }
+        }
+    }
+
+    /// Framebuffer row shown on uncropped output row `y`.  A fixed canvas
+    /// centres the active display by height alone, so VDS and VSW never
+    /// move the picture.
+    fn output_source_row(&self, y: usize) -> Option<usize> {
+        let row = match self.frame_output.geometry {
+            OutputGeometry::Native => y,
+            OutputGeometry::Fixed { height, .. } => {
+                let row = (y + self.current_display_height / 2).checked_sub(height / 2)?;
+                if row >= self.current_display_height {
+                    return None;
+                }
+                row
+            }
+        } + self.current_display_y_offset;
+        (row < FRAME_HEIGHT).then_some(row)
+    }
+
+    /// Framebuffer column shown on uncropped output column `x`.  A fixed
+    /// canvas samples the middle of each canvas dot.
+    fn output_source_column(&self, x: usize) -> Option<usize> {
+        let column = match self.frame_output.geometry {
+            OutputGeometry::Native => x + *self.current_display_x_offset,
+            OutputGeometry::Fixed { width, .. } => {
+                let clock = (TV_CENTRE_CLOCK + x * CANVAS_DOT_CLOCKS + CANVAS_DOT_CLOCKS / 2)
+                    .checked_sub(width * CANVAS_DOT_CLOCKS / 2)?;
+                clock / self.frame_output.dot_divider.max(1)
+            }
+        };
+        (column < FRAME_WIDTH).then_some(column)
+    }
+}
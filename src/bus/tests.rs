@@ -3,8 +3,8 @@ use crate::psg::PSG_WAVE_SIZE;
 use crate::psg::*;
 use crate::vdc::{
     DMA_CTRL_IRQ_SATB, DMA_CTRL_IRQ_VRAM, DMA_CTRL_SATB_AUTO, FRAME_WIDTH, LINES_PER_FRAME,
-    SPRITE_PATTERN_HEIGHT, SPRITE_PATTERN_WORDS, TILE_HEIGHT, TILE_WIDTH, VDC_BUSY_ACCESS_CYCLES,
-    VDC_VBLANK_INTERVAL, VDC_VISIBLE_LINES, Vdc,
+    SPRITE_PATTERN_HEIGHT, SPRITE_PATTERN_WIDTH, SPRITE_PATTERN_WORDS, SatbEntry, TILE_HEIGHT,
+    TILE_WIDTH, VDC_BUSY_ACCESS_CYCLES, VDC_VBLANK_INTERVAL, VDC_VISIBLE_LINES, Vdc,
 };
 
 const PHI_CYCLES_PER_SAMPLE: u32 = MASTER_CLOCK_HZ / AUDIO_SAMPLE_RATE;
@@ -57,7 +57,7 @@ fn prepare_bus_for_zoom() -> Bus {
 
     for bank in 0..16 {
         let colour = (bank as u16) * 0x041;
-        bus.vce.palette[(bank << 4) | 1] = colour;
+        bus.vce.set_palette_word((bank << 4) | 1, colour);
     }
 
     bus
@@ -100,7 +100,7 @@ fn prepare_bus_for_vertical_zoom() -> Bus {
 
     for bank in 0..16 {
         let colour = 0x0100 | ((bank as u16) * 0x021);
-        bus.vce.palette[(bank << 4) | 1] = colour;
+        bus.vce.set_palette_word((bank << 4) | 1, colour);
     }
 
     bus
@@ -133,6 +133,46 @@ fn load_and_bank_switch_rom() {
     assert_eq!(bus.read(0x8000), 0x12);
 }
 
+#[test]
+fn describe_address_reports_slot_bank_and_physical_offset() {
+    let mut bus = Bus::new();
+    bus.load_rom_image(vec![0; PAGE_SIZE * 4]);
+    bus.configure_cart_ram(PAGE_SIZE);
+    bus.set_mpr(0, 0xFF);
+    bus.set_mpr(1, 0xF8);
+    bus.set_mpr(2, 0xF7);
+    bus.set_mpr(3, 0x80);
+    bus.set_mpr(4, 0x06);
+
+    let rom = bus.describe_address(0x8123);
+    assert_eq!(rom.slot, 4);
+    assert_eq!(rom.mpr, 0x06);
+    // Bank 6 mirrors onto page 2 of a four-page ROM.
+    assert_eq!(
+        rom.mapping,
+        BankMapping::Rom {
+            base: 2 * PAGE_SIZE
+        }
+    );
+    assert_eq!(rom.offset, 2 * PAGE_SIZE + 0x0123);
+    assert_eq!(rom.to_string(), "$8123 MPR4=$06 ROM+$04123");
+
+    let io = bus.describe_address(0x0402);
+    assert_eq!((io.mapping, io.offset), (BankMapping::Hardware, 0x0402));
+    assert_eq!(bus.describe_address(0x2010).offset, 0x0010);
+    assert_eq!(bus.describe_address(0x4000).mapping, BankMapping::Bram);
+    assert_eq!(
+        bus.describe_address(0x6001).mapping,
+        BankMapping::CartRam { base: 0 }
+    );
+
+    let map = bus.memory_map();
+    assert_eq!(map.map(|region| region.slot), [0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(map.map(|region| region.mpr), bus.mpr_array());
+    assert_eq!(map[4].addr, 0x8000);
+    assert_eq!(map[4].offset, 2 * PAGE_SIZE);
+}
+
 #[test]
 fn mpr_mirrors_apply_across_high_page() {
     let mut bus = Bus::new();
@@ -413,8 +453,8 @@ fn sprite_priority_respects_background_mask() {
 
     write_constant_sprite_tile(&mut bus, SPRITE_PATTERN_ID, 0x01);
 
-    bus.vce.palette[0x1F] = 0x001F;
-    bus.vce.palette[0x121] = 0x03E0;
+    bus.vce.set_palette_word(0x1F, 0x001F);
+    bus.vce.set_palette_word(0x121, 0x03E0);
 
     bus.render_frame_from_vram();
     let bg_colour = bus.framebuffer[0];
@@ -458,9 +498,9 @@ fn sprites_render_when_background_disabled() {
     let mut bus = Bus::new();
     bus.set_mpr(0, 0xFF);
 
-    bus.vce.palette[0x00] = 0x0000;
-    bus.vce.palette[0x100] = 0x0000;
-    bus.vce.palette[0x101] = 0x7C00;
+    bus.vce.set_palette_word(0x00, 0x0000);
+    bus.vce.set_palette_word(0x100, 0x0000);
+    bus.vce.set_palette_word(0x101, 0x7C00);
 
     write_constant_sprite_tile(&mut bus, 0, 0x01);
 
@@ -498,8 +538,8 @@ fn sprite_double_width_draws_all_columns() {
     set_vdc_control(&mut bus, VDC_CTRL_DISPLAY_FULL);
 
     const BASE_PATTERN: usize = 0x200;
-    bus.vce.palette[0x101] = 0x0111;
-    bus.vce.palette[0x102] = 0x0222;
+    bus.vce.set_palette_word(0x101, 0x0111);
+    bus.vce.set_palette_word(0x102, 0x0222);
     write_constant_sprite_tile(&mut bus, BASE_PATTERN, 0x01);
     write_constant_sprite_tile(&mut bus, BASE_PATTERN + 1, 0x02);
 
@@ -579,7 +619,7 @@ fn sprite_size_scaling_plots_full_extent() {
     }
 
     let sprite_colour = 0x7C00;
-    bus.vce.palette[0x12F] = sprite_colour;
+    bus.vce.set_palette_word(0x12F, sprite_colour);
 
     let x_pos = 40;
     let y_pos = 32;
@@ -615,7 +655,7 @@ fn sprite_quad_height_plots_bottom_row() {
     }
 
     let sprite_colour = 0x03FF;
-    bus.vce.palette[0x10F] = sprite_colour;
+    bus.vce.set_palette_word(0x10F, sprite_colour);
 
     let x_pos = 24;
     let y_pos = 40;
@@ -886,7 +926,7 @@ fn bg_disabled_when_cr_bit7_clear() {
         bus.vdc.vram[0x0010 + row] = if row == 0 { 0x0080 } else { 0x0000 };
         bus.vdc.vram[0x0018 + row] = 0x0000;
     }
-    bus.vce.palette[0x001] = 0x01FF;
+    bus.vce.set_palette_word(0x001, 0x01FF);
 
     // BG bit (CR bit7) is clear, while increment bits 11-12 are set.
     set_vdc_control(&mut bus, VDC_CTRL_ENABLE_SPRITES_LEGACY | (0b11 << 11));
@@ -907,7 +947,7 @@ fn tile_entry_zero_uses_tile_zero_pattern_data() {
     // Tile 0 row 0 plane data (overlaps BAT area by hardware design).
     bus.vdc.vram[0x0000] = 0x0080;
     bus.vdc.vram[0x0008] = 0x0000;
-    bus.vce.palette[0x001] = 0x01FF;
+    bus.vce.set_palette_word(0x001, 0x01FF);
 
     set_vdc_control(&mut bus, VDC_CTRL_ENABLE_BACKGROUND_LEGACY);
     bus.render_frame_from_vram();
@@ -966,6 +1006,75 @@ fn renderer_honours_vertical_window_overscan_rows() {
     assert_eq!(overscan_pixel, 0x000000, "overscan lines should be black");
 }
 
+#[test]
+fn vce_dot_clock_limits_pixels_per_line() {
+    let mut bus = Bus::new();
+    // HDW = 63 asks for 512 pixels.
+    bus.vdc.registers[0x0B] = 0x003F;
+
+    bus.vce.write_control_low(0x00);
+    bus.render_frame_from_vram();
+    assert_eq!(bus.dot_clock(), DotClock::Mhz5);
+    assert_eq!(bus.display_width(), 341);
+
+    bus.vce.write_control_low(0x01);
+    bus.render_frame_from_vram();
+    assert_eq!(bus.dot_clock(), DotClock::Mhz7);
+    assert_eq!(bus.display_width(), 455);
+
+    bus.vce.write_control_low(0x02);
+    bus.render_frame_from_vram();
+    assert_eq!(bus.dot_clock(), DotClock::Mhz10);
+    assert_eq!(bus.display_width(), 512);
+}
+
+#[test]
+fn vce_colorburst_strip_bit_renders_grayscale() {
+    let mut bus = Bus::new();
+    bus.vce.set_palette_word(0x001, 0x0038); // pure red
+    let colour = bus.vce_palette_rgb(0x001);
+    assert_eq!(colour, 0x00FF_0000);
+
+    bus.write_io(0x0400, 0x80);
+    assert!(bus.grayscale());
+    let grey = bus.vce_palette_rgb(0x001);
+    let luma = grey & 0xFF;
+    assert_eq!(grey, (luma << 16) | (luma << 8) | luma);
+    assert_eq!(luma, 76);
+
+    bus.write_io(0x0400, 0x00);
+    assert_eq!(bus.vce_palette_rgb(0x001), colour);
+}
+
+#[test]
+fn vce_line_count_bit_selects_262_or_263_line_fields() {
+    let mut bus = Bus::new();
+    assert_eq!(bus.frame_lines(), 262);
+
+    let count_field = |bus: &mut Bus| {
+        let line_cycles = VDC_VBLANK_INTERVAL.div_ceil(LINES_PER_FRAME as u32);
+        while bus.vdc.scanline != 0 {
+            bus.tick(line_cycles, true);
+        }
+        let mut lines = 0;
+        loop {
+            bus.tick(line_cycles, true);
+            lines += 1;
+            if bus.vdc.scanline == 0 {
+                return lines;
+            }
+        }
+    };
+    assert_eq!(count_field(&mut bus), 262);
+
+    bus.write_io(0x0400, 0x04);
+    assert_eq!(bus.frame_lines(), 263);
+    assert_eq!(count_field(&mut bus), 263);
+
+    bus.write_io(0x0400, 0x00);
+    assert_eq!(count_field(&mut bus), 262);
+}
+
 #[test]
 fn vce_palette_access_during_active_display_smears_previous_pixel_colour() {
     let mut bus = Bus::new();
@@ -987,8 +1096,8 @@ fn vce_palette_access_during_active_display_smears_previous_pixel_colour() {
         bus.vdc.vram[TILE_ID * 16 + row] = 0x00FF;
         bus.vdc.vram[TILE_ID * 16 + row + 8] = 0x0000;
     }
-    bus.vce.palette[0x001] = 0x0007;
-    bus.vce.palette[0x011] = 0x01C0;
+    bus.vce.set_palette_word(0x001, 0x0007);
+    bus.vce.set_palette_word(0x011, 0x01C0);
 
     bus.vdc.in_vblank = false;
     bus.vdc.scanline = 0;
@@ -1050,8 +1159,8 @@ fn vdc_horizontal_display_end_margin_is_latched_per_line() {
 #[test]
 fn render_frame_uses_maximum_latched_horizontal_width_and_preserves_overscan() {
     let mut bus = Bus::new();
-    bus.vce.palette[0x000] = 0x0000;
-    bus.vce.palette[0x100] = 0x01C0;
+    bus.vce.set_palette_word(0x000, 0x0000);
+    bus.vce.set_palette_word(0x100, 0x01C0);
 
     bus.vdc.registers[0x0B] = 0x001F;
     bus.vdc.latch_line_state(0);
@@ -1076,9 +1185,9 @@ fn take_frame_includes_left_border_when_latched_horizontal_start_increases() {
         &mut bus,
         VDC_CTRL_ENABLE_BACKGROUND | VDC_CTRL_ENABLE_BACKGROUND_LEGACY,
     );
-    bus.vce.palette[0x000] = 0x0000;
-    bus.vce.palette[0x100] = 0x01C0;
-    bus.vce.palette[0x001] = 0x0007;
+    bus.vce.set_palette_word(0x000, 0x0000);
+    bus.vce.set_palette_word(0x100, 0x01C0);
+    bus.vce.set_palette_word(0x001, 0x0007);
 
     const TILE_ID: usize = 0x40;
     bus.vdc.vram[0] = TILE_ID as u16;
@@ -1109,9 +1218,9 @@ fn sprites_follow_horizontal_display_start_offset() {
     let mut bus = Bus::new();
     bus.set_mpr(0, 0xFF);
 
-    bus.vce.palette[0x00] = 0x0000;
-    bus.vce.palette[0x100] = 0x0000;
-    bus.vce.palette[0x101] = 0x7C00;
+    bus.vce.set_palette_word(0x00, 0x0000);
+    bus.vce.set_palette_word(0x100, 0x0000);
+    bus.vce.set_palette_word(0x101, 0x7C00);
     write_constant_sprite_tile(&mut bus, 0, 0x01);
 
     set_vdc_control(
@@ -1145,9 +1254,9 @@ fn take_frame_excludes_right_timing_margin_when_latched_horizontal_end_increases
         &mut bus,
         VDC_CTRL_ENABLE_BACKGROUND | VDC_CTRL_ENABLE_BACKGROUND_LEGACY,
     );
-    bus.vce.palette[0x000] = 0x0000;
-    bus.vce.palette[0x100] = 0x01C0;
-    bus.vce.palette[0x001] = 0x0007;
+    bus.vce.set_palette_word(0x000, 0x0000);
+    bus.vce.set_palette_word(0x100, 0x01C0);
+    bus.vce.set_palette_word(0x001, 0x0007);
 
     const TILE_ID: usize = 0x40;
     bus.vdc.vram[0] = TILE_ID as u16;
@@ -1235,7 +1344,7 @@ fn background_priority_bit_sets_bg_priority_mask() {
         bus.vdc.vram[(tile_base + row + 8) & 0x7FFF] = 0;
     }
 
-    bus.vce.palette[0x01] = 0x7C00;
+    bus.vce.set_palette_word(0x01, 0x7C00);
 
     bus.render_frame_from_vram();
     let colour = bus.vce.palette_rgb(0x01);
@@ -1409,6 +1518,112 @@ fn hardware_page_status_read_clears_irq() {
     assert_eq!(bus.read_io(0x00) & VDC_STATUS_VBL, 0);
 }
 
+#[test]
+fn peek_leaves_vdc_vce_and_bram_state_untouched() {
+    let mut bus = Bus::new();
+    bus.set_mpr(0, 0xFF);
+    bus.set_mpr(2, 0xF7);
+
+    // VDC status: peeking neither acknowledges VBlank nor drops the IRQ.
+    bus.write_st_port(0, 0x05);
+    bus.write_st_port(1, 0x08);
+    bus.write_st_port(2, 0x00);
+    bus.vdc_set_status_for_test(VDC_STATUS_VBL);
+    assert_ne!(bus.peek(0x0000) & VDC_STATUS_VBL, 0);
+    assert_ne!(bus.peek(0x0000) & VDC_STATUS_VBL, 0);
+    assert_ne!(bus.pending_interrupts() & IRQ_REQUEST_IRQ1, 0);
+    let status = bus.peek(0x0000);
+    assert_eq!(bus.read(0x0000), status);
+    assert_eq!(bus.peek(0x0000) & VDC_STATUS_VBL, 0);
+
+    // VDC data port: peeking VRAM does not advance MARR.
+    bus.write_st_port(0, 0x00);
+    bus.write_st_port(1, 0x00);
+    bus.write_st_port(2, 0x01);
+    bus.write_st_port(0, 0x02);
+    bus.write_st_port(1, 0xEF);
+    bus.write_st_port(2, 0xBE);
+    bus.write_st_port(0, 0x01);
+    bus.write_st_port(1, 0x00);
+    bus.write_st_port(2, 0x01);
+    bus.write_st_port(0, 0x02);
+    let peeked = (bus.peek(0x0002), bus.peek(0x0003));
+    assert_eq!((bus.peek(0x0002), bus.peek(0x0003)), peeked);
+    assert_eq!((bus.read(0x0002), bus.read(0x0003)), (0xEF, 0xBE));
+    assert_eq!(peeked, (0xEF, 0xBE));
+
+    // VCE data port: peeking does not auto-increment the palette index.
+    bus.write(VCE_ADDRESS_ADDR, 0x10);
+    bus.write(VCE_ADDRESS_HIGH_ADDR, 0x00);
+    bus.write(VCE_DATA_ADDR, 0x34);
+    bus.write(VCE_DATA_HIGH_ADDR, 0x01);
+    bus.write(VCE_ADDRESS_ADDR, 0x10);
+    for _ in 0..2 {
+        assert_eq!(bus.peek(VCE_DATA_ADDR), 0x34);
+        assert_eq!(bus.peek(VCE_DATA_HIGH_ADDR), 0xFF);
+    }
+    assert_eq!(bus.read(VCE_DATA_ADDR), 0x34);
+    assert_eq!(bus.read(VCE_DATA_HIGH_ADDR), 0xFF);
+
+    // BRAM lock port: peeking $1803 does not relock.
+    bus.write(0x1807, 0x80);
+    bus.write(0x4000, 0x5A);
+    assert_eq!(bus.peek(0x1803), 0xFF);
+    assert!(bus.bram_unlocked());
+    assert_eq!(bus.peek(0x4000), 0x5A);
+    assert_eq!(bus.peek_physical(0xF7, 0x0000), 0x5A);
+}
+
+#[test]
+fn peek_physical_reads_unmapped_banks() {
+    let mut bus = Bus::new();
+    let mut rom = vec![0u8; PAGE_SIZE * 4];
+    rom[PAGE_SIZE * 3 + 0x123] = 0x9C;
+    bus.load_rom_image(rom);
+    bus.map_bank_to_rom(4, 0);
+    bus.set_mpr(1, 0xF8);
+    bus.write(0x2010, 0x42);
+    let cdl_before = bus.code_data_log().flags().to_vec();
+
+    assert_eq!(bus.peek_physical(0x03, 0x0123), 0x9C);
+    // Mirrors like a CPU access would.
+    assert_eq!(bus.peek_physical(0x07, 0x0123), 0x9C);
+    assert_eq!(bus.peek_physical(0xF8, 0x0010), 0x42);
+    assert_eq!(bus.peek(0x8000), bus.peek_physical(0x00, 0));
+    assert_eq!(bus.code_data_log().flags(), cdl_before.as_slice());
+}
+
+#[test]
+fn rom_patches_substitute_reads_with_optional_compare() {
+    let mut bus = Bus::new();
+    let mut rom = vec![0u8; PAGE_SIZE * 2];
+    rom[0x0100] = 0xA9;
+    rom[0x0101] = 0x03;
+    bus.load_rom_image(rom);
+    bus.map_bank_to_rom(4, 0);
+    bus.set_rom_patches(vec![
+        RomPatch {
+            offset: 0x0101,
+            value: 0x09,
+            compare: Some(0x03),
+        },
+        RomPatch {
+            offset: 0x0100,
+            value: 0xEA,
+            compare: Some(0x00),
+        },
+    ]);
+
+    assert_eq!(bus.read(0x8101), 0x09);
+    assert_eq!(bus.peek(0x8101), 0x09);
+    // Compare byte does not match the original, so the ROM shows through.
+    assert_eq!(bus.read(0x8100), 0xA9);
+    assert_eq!(bus.rom_patches()[0].offset, 0x0100);
+
+    bus.set_rom_patches(Vec::new());
+    assert_eq!(bus.read(0x8101), 0x03);
+}
+
 #[test]
 fn vce_palette_write_and_read_round_trip() {
     let mut bus = Bus::new();
@@ -2009,34 +2224,111 @@ fn vdc_vblank_flag_returns_after_display() {
 }
 
 #[test]
-fn vdc_tick_holds_on_first_frame_trigger_for_large_cycle_chunk() {
+fn vdc_tick_pauses_after_each_completed_scanline() {
     let mut vdc = Vdc::new();
     vdc.scanline = 0;
     vdc.in_vblank = false;
     vdc.frame_trigger = false;
     vdc.scroll_line_valid.fill(false);
-    let frame_cycles = VDC_VBLANK_INTERVAL;
-
-    // One large chunk can cover more than a full frame worth of scanline steps.
-    // We should stop at the first VBlank/frame trigger and preserve latched line state.
-    let _ = vdc.tick(frame_cycles);
-    assert!(vdc.frame_ready(), "expected frame trigger after large tick");
-    // Frame trigger now fires at the last active scanline (one before VBlank),
-    // so that mid-frame VRAM writes are captured by the batch renderer.
-    assert_eq!(
-        vdc.scanline,
-        VDC_VISIBLE_LINES - 1,
-        "scanline should stop one line before VBlank"
-    );
-    assert!(vdc.scroll_line_valid[1], "line 1 should be latched");
+
+    // One large chunk covers a full frame worth of scanlines, but timing
+    // stops after the first completed line so it can be rendered before
+    // the next line is latched.
+    vdc.tick(VDC_VBLANK_INTERVAL);
+    assert_eq!(vdc.take_completed_line(), Some(0));
+    assert_eq!(vdc.take_completed_line(), None);
+    assert_eq!(vdc.scanline, 0);
     assert!(
-        vdc.scroll_line_valid[(VDC_VISIBLE_LINES - 1) as usize],
-        "last active line should be latched"
+        !vdc.scroll_line_valid[1],
+        "line 1 not latched until resumed"
     );
-    assert!(
-        !vdc.scroll_line_valid[VDC_VISIBLE_LINES as usize],
-        "VBlank line should remain unlatched until frame is consumed"
+
+    let mut completed = vec![0];
+    loop {
+        let _ = vdc.start_next_scanline();
+        match vdc.take_completed_line() {
+            Some(line) => completed.push(line),
+            None => break,
+        }
+    }
+    // The remaining cycles run up to VBlank start, which hands control
+    // back to the CPU; the last active line raised the frame trigger.
+    assert_eq!(completed, (0..VDC_VISIBLE_LINES).collect::<Vec<_>>());
+    assert_eq!(vdc.scanline, VDC_VISIBLE_LINES);
+    assert!(vdc.in_vblank);
+    assert!(vdc.frame_ready());
+}
+
+#[test]
+fn scroll_writes_mid_line_move_bxr_at_once_and_byr_from_the_next_line() {
+    let mut bus = Bus::new();
+    let line_cycles = VDC_VBLANK_INTERVAL.div_ceil(LINES_PER_FRAME as u32);
+    // Wrap into the frame, then stop part-way through line 100, as an RCR
+    // handler would.
+    while bus.vdc.scanline != 100 {
+        bus.tick(line_cycles, true);
+    }
+    bus.vdc.write_select(0x07);
+    bus.vdc.write_data_low(0x08);
+    bus.vdc.write_data_high(0x00);
+    bus.vdc.write_select(0x08);
+    bus.vdc.write_data_low(0x40);
+    bus.vdc.write_data_high(0x00);
+    while bus.vdc.scanline != 102 {
+        bus.tick(line_cycles, true);
+    }
+
+    assert_eq!(bus.vdc.scroll_line(99), (0, 0));
+    assert_eq!(bus.vdc.scroll_line(100), (8, 0));
+    assert_eq!(
+        bus.vdc_scroll_line_y_offset(100),
+        bus.vdc_scroll_line_y_offset(99) + 1
     );
+    assert_eq!(bus.vdc.scroll_line(101), (8, 0x40));
+    assert_eq!(bus.vdc_scroll_line_y_offset(101), 0);
+}
+
+#[test]
+fn mid_frame_palette_write_only_affects_later_lines() {
+    let mut bus = Bus::new();
+    bus.set_mpr(0, 0xFF);
+    set_vdc_control(
+        &mut bus,
+        VDC_CTRL_ENABLE_BACKGROUND | VDC_CTRL_ENABLE_BACKGROUND_LEGACY,
+    );
+    let write_colour = |bus: &mut Bus, raw: u16| {
+        bus.write(VCE_ADDRESS_ADDR, 0x00);
+        bus.write(VCE_ADDRESS_HIGH_ADDR, 0x00);
+        bus.write(VCE_DATA_ADDR, (raw & 0x00FF) as u8);
+        bus.write(VCE_DATA_HIGH_ADDR, (raw >> 8) as u8);
+    };
+    // Blank BG: every pixel shows palette entry 0.
+    write_colour(&mut bus, 0x0038);
+
+    let line_cycles = VDC_VBLANK_INTERVAL.div_ceil(LINES_PER_FRAME as u32);
+    // Wrap into the frame, then run through the first 100 lines.
+    while bus.vdc.scanline != 100 {
+        bus.tick(line_cycles, true);
+    }
+    let red = bus.vce_palette_rgb(0);
+    write_colour(&mut bus, 0x01C0);
+    let green = bus.vce_palette_rgb(0);
+    assert_ne!(red, green);
+
+    let mut frame = None;
+    for _ in 0..LINES_PER_FRAME {
+        bus.tick(line_cycles, true);
+        frame = bus.take_frame();
+        if frame.is_some() {
+            break;
+        }
+    }
+    let frame = frame.expect("expected frame");
+    let width = bus.display_width();
+    assert_eq!(frame[50 * width], red);
+    assert_eq!(frame[99 * width], red);
+    assert_eq!(frame[101 * width], green);
+    assert_eq!(frame[200 * width], green);
 }
 
 #[test]
@@ -2852,6 +3144,50 @@ fn audio_diagnostics_track_generation_and_reset() {
     assert_eq!(reset.pending_bus_samples, 0);
 }
 
+/// Bus with channel 0 keyed on in DDA mode at full volume, panned by
+/// `balance`.
+fn bus_with_dda_channel(balance: u8) -> Bus {
+    let mut bus = Bus::new();
+    bus.write_io(0x1C60, PSG_REG_CH_SELECT as u8);
+    bus.write_io(0x1C61, 0x00);
+    bus.write_io(0x1C60, PSG_REG_CH_BALANCE as u8);
+    bus.write_io(0x1C61, balance);
+    bus.write_io(0x1C60, PSG_REG_CH_CONTROL as u8);
+    bus.write_io(0x1C61, PSG_CH_CTRL_KEY_ON | PSG_CH_CTRL_DDA | 0x1F);
+    bus.write_io(0x1C60, PSG_REG_WAVE_DATA as u8);
+    bus.write_io(0x1C61, 0x1F);
+    bus
+}
+
+#[test]
+fn audio_samples_interleave_left_and_right() {
+    let mut bus = bus_with_dda_channel(0xF0);
+    bus.set_audio_channels(AudioChannels::Stereo);
+
+    bus.tick(PHI_CYCLES_PER_SAMPLE * 4, true);
+    let samples = bus.take_audio_samples();
+    assert_eq!(samples.len() % 2, 0);
+    assert!(samples.chunks_exact(2).all(|frame| frame[1] == 0));
+    assert!(samples.chunks_exact(2).any(|frame| frame[0] > 0));
+}
+
+#[test]
+fn mono_audio_downmixes_both_sides() {
+    let mut stereo = bus_with_dda_channel(0xF0);
+    let mut mono = bus_with_dda_channel(0xF0);
+    stereo.set_audio_channels(AudioChannels::Stereo);
+    assert_eq!(mono.audio_channels(), AudioChannels::Mono);
+
+    stereo.tick(PHI_CYCLES_PER_SAMPLE * 4, true);
+    mono.tick(PHI_CYCLES_PER_SAMPLE * 4, true);
+    let stereo = stereo.take_audio_samples();
+    let mono = mono.take_audio_samples();
+    assert_eq!(mono.len() * 2, stereo.len());
+    for (sample, frame) in mono.iter().zip(stereo.chunks_exact(2)) {
+        assert_eq!(*sample, ((frame[0] as i32 + frame[1] as i32) / 2) as i16);
+    }
+}
+
 #[test]
 fn disabling_video_output_clears_pending_frame_trigger() {
     let mut bus = Bus::new();
@@ -3145,3 +3481,457 @@ fn psg_dc_blocker_keeps_centered_wave_mean_near_zero() {
         "DC blocker should keep centered waveform mean near zero (mean={mean})"
     );
 }
+
+fn bus_with_standard_timing() -> Bus {
+    let mut bus = Bus::new();
+    set_vdc_control(
+        &mut bus,
+        VDC_CTRL_ENABLE_BACKGROUND | VDC_CTRL_ENABLE_BACKGROUND_LEGACY,
+    );
+    bus.vce.set_palette_word(0x000, 0x0007);
+    bus.vce.set_palette_word(0x100, 0x01C0);
+    bus.vdc.registers[0x0A] = 0x0202;
+    bus.vdc.registers[0x0B] = 0x041F;
+    bus.vdc.registers[0x0C] = 0x0F02;
+    bus.vdc.registers[0x0D] = 0x00EF;
+    bus.vdc.registers[0x0E] = 0x0003;
+    bus
+}
+
+#[test]
+fn fixed_geometry_places_standard_display_on_canvas() {
+    let mut bus = bus_with_standard_timing();
+    let backdrop = bus.vce.palette_rgb(0x000);
+    let overscan = bus.vce.palette_rgb(0x100);
+
+    bus.set_output_geometry(OutputGeometry::STANDARD);
+    bus.render_frame_from_vram();
+    let frame = bus.take_frame().expect("expected frame");
+    assert_eq!((bus.display_width(), bus.display_height()), (256, 240));
+    assert!(frame.iter().all(|&pixel| pixel == backdrop));
+
+    bus.set_output_geometry(OutputGeometry::FULL_OVERSCAN);
+    bus.render_frame_from_vram();
+    let frame = bus.take_frame().expect("expected frame");
+    assert_eq!(frame.len(), 342 * 242);
+    // 43 columns and one line of border on the leading edges.
+    assert_eq!(frame[0], overscan);
+    assert_eq!(frame[342 + 42], overscan);
+    assert_eq!(frame[342 + 43], backdrop);
+    assert_eq!(frame[342 + 43 + 255], backdrop);
+    assert_eq!(frame[342 + 43 + 256], overscan);
+    assert_eq!(frame[241 * 342 + 100], overscan);
+}
+
+#[test]
+fn fixed_geometry_keeps_size_when_display_shrinks() {
+    let mut bus = bus_with_standard_timing();
+    bus.set_output_geometry(OutputGeometry::STANDARD);
+    // 224 lines, 8 lines later: still centred on the same canvas.
+    bus.vdc.registers[0x0C] = 0x1702;
+    bus.vdc.registers[0x0D] = 0x00DF;
+    bus.render_frame_from_vram();
+    let frame = bus.take_frame().expect("expected frame");
+
+    assert_eq!((bus.display_width(), bus.display_height()), (256, 240));
+    let overscan = bus.vce.palette_rgb(0x100);
+    let backdrop = bus.vce.palette_rgb(0x000);
+    assert_eq!(frame[7 * 256], overscan);
+    assert_eq!(frame[8 * 256], backdrop);
+    assert_eq!(frame[231 * 256], backdrop);
+
+    bus.set_output_geometry(OutputGeometry::Native);
+    bus.render_frame_from_vram();
+    assert_eq!(bus.take_frame().map(|frame| frame.len()), Some(256 * 224));
+}
+
+#[test]
+fn fixed_geometry_ignores_vertical_display_start() {
+    let mut bus = bus_with_standard_timing();
+    bus.set_output_geometry(OutputGeometry::FULL_OVERSCAN);
+    bus.render_frame_from_vram();
+    let centred = bus.take_frame().expect("expected frame");
+
+    // Same 240 lines, started 8 lines earlier.
+    bus.vdc.registers[0x0C] = 0x0702;
+    bus.render_frame_from_vram();
+    let moved = bus.take_frame().expect("expected frame");
+    assert!(moved == centred, "VDS moved the fixed canvas picture");
+}
+
+#[test]
+fn crop_margins_trim_each_edge() {
+    let mut bus = bus_with_standard_timing();
+    bus.set_output_geometry(OutputGeometry::FULL_OVERSCAN);
+    bus.set_crop_margins(CropMargins {
+        left: 43,
+        right: 43,
+        top: 1,
+        bottom: 1,
+    });
+    bus.render_frame_from_vram();
+    let frame = bus.take_frame().expect("expected frame");
+
+    assert_eq!((bus.display_width(), bus.display_height()), (256, 240));
+    let backdrop = bus.vce.palette_rgb(0x000);
+    assert!(frame.iter().all(|&pixel| pixel == backdrop));
+}
+
+#[test]
+fn pixel_formats_encode_palette_entries() {
+    let mut bus = bus_with_standard_timing();
+    bus.set_mpr(0, 0xFF);
+    bus.write(VCE_ADDRESS_ADDR, 0x00);
+    bus.write(VCE_ADDRESS_HIGH_ADDR, 0x00);
+    // Entry 0: full red.
+    bus.write(VCE_DATA_ADDR, 0x38);
+    bus.write(VCE_DATA_HIGH_ADDR, 0x00);
+
+    let mut pixels = Vec::new();
+    for format in [
+        PixelFormat::Xrgb8888,
+        PixelFormat::Abgr8888,
+        PixelFormat::Rgb565,
+        PixelFormat::Index9,
+    ] {
+        bus.set_pixel_format(format);
+        bus.render_frame_from_vram();
+        pixels.push(bus.take_frame().expect("expected frame")[0]);
+    }
+    assert_eq!(pixels, vec![0x00FF_0000, 0xFF00_00FF, 0xF800, 0x000]);
+}
+
+#[test]
+fn raw_index_output_ignores_palette_contents() {
+    let mut bus = bus_with_standard_timing();
+    bus.set_pixel_format(PixelFormat::Index9);
+    bus.set_output_geometry(OutputGeometry::FULL_OVERSCAN);
+    bus.render_frame_from_vram();
+    let before = bus.take_frame().expect("expected frame");
+
+    bus.vce.set_palette_word(0x000, 0x01FF);
+    bus.vce.set_palette_word(0x100, 0x0123);
+    bus.render_frame_from_vram();
+    let after = bus.take_frame().expect("expected frame");
+
+    assert_eq!(before, after);
+    assert_eq!(after[0], 0x100);
+    assert_eq!(after[342 + 43], 0x000);
+}
+
+#[test]
+fn palette_cache_follows_port_writes_and_colour_burst() {
+    let mut bus = Bus::new();
+    bus.set_mpr(0, 0xFF);
+    bus.set_pixel_format(PixelFormat::Rgb565);
+    bus.write(VCE_ADDRESS_ADDR, 0x05);
+    bus.write(VCE_ADDRESS_HIGH_ADDR, 0x00);
+    bus.write(VCE_DATA_ADDR, 0x07);
+    bus.write(VCE_DATA_HIGH_ADDR, 0x00);
+    assert_eq!(bus.vce.colour(0x005), 0x001F);
+
+    bus.write_io(0x0400, 0x80);
+    let grey = bus.vce_palette_rgb(0x005);
+    assert_eq!(
+        bus.vce.colour(0x005),
+        PixelFormat::Rgb565.encode(grey, 0x005)
+    );
+}
+
+fn bus_with_background_under_sprite() -> Bus {
+    let mut bus = Bus::new();
+    bus.set_mpr(0, 0xFF);
+    set_vdc_control(&mut bus, VDC_CTRL_DISPLAY_FULL);
+
+    const BG_TILE_ID: usize = 200;
+    const SPRITE_PATTERN_ID: usize = 201;
+    for entry in bus.vdc.vram.iter_mut().take(32 * 32) {
+        *entry = BG_TILE_ID as u16 | (1 << 12);
+    }
+    for row in 0..8 {
+        bus.vdc.vram[BG_TILE_ID * 16 + row] = 0xFFFF;
+        bus.vdc.vram[BG_TILE_ID * 16 + 8 + row] = 0xFFFF;
+    }
+    write_constant_sprite_tile(&mut bus, SPRITE_PATTERN_ID, 0x01);
+    bus.vce.set_palette_word(0x000, 0x0007);
+    bus.vce.set_palette_word(0x01F, 0x001F);
+    bus.vce.set_palette_word(0x121, 0x03E0);
+
+    // Low-priority sprite 0 over the top-left corner.
+    bus.vdc.satb[0] = 64;
+    bus.vdc.satb[1] = 32;
+    bus.vdc.satb[2] = (SPRITE_PATTERN_ID as u16) << 1;
+    bus.vdc.satb[3] = 0x0002;
+    bus
+}
+
+#[test]
+fn layer_toggles_change_only_what_is_drawn() {
+    let mut bus = bus_with_background_under_sprite();
+    let bg_colour = bus.vce.palette_rgb(0x01F);
+    let sprite_colour = bus.vce.palette_rgb(0x121);
+    let backdrop = bus.vce.palette_rgb(0x000);
+    let top_left = |bus: &mut Bus, update: &dyn Fn(&mut LayerToggles)| {
+        let mut layers = bus.layer_toggles();
+        update(&mut layers);
+        bus.set_layer_toggles(layers);
+        bus.render_frame_from_vram();
+        bus.framebuffer[0]
+    };
+
+    assert_eq!(top_left(&mut bus, &|_| {}), bg_colour);
+    assert_eq!(
+        top_left(&mut bus, &|layers| layers.sprite_priority = false),
+        sprite_colour
+    );
+    assert_eq!(
+        top_left(&mut bus, &|layers| {
+            layers.sprite_priority = true;
+            layers.background = false;
+        }),
+        sprite_colour
+    );
+    assert_eq!(
+        top_left(&mut bus, &|layers| layers.set_sprite_visible(0, false)),
+        backdrop
+    );
+    assert_eq!(
+        top_left(&mut bus, &|layers| {
+            layers.set_sprite_visible(0, true);
+            layers.sprites = false;
+        }),
+        backdrop
+    );
+    assert_eq!(
+        top_left(&mut bus, &|layers| *layers = LayerToggles::default()),
+        bg_colour
+    );
+}
+
+#[test]
+fn disabling_sprite_line_limit_draws_every_sprite_and_keeps_overflow() {
+    let mut bus = Bus::new();
+    bus.set_mpr(0, 0xFF);
+    const TILE_ID: usize = 0x400;
+    write_constant_sprite_tile(&mut bus, TILE_ID, 0x01);
+    set_vdc_control(&mut bus, VDC_CTRL_DISPLAY_FULL);
+    for sprite in 0..17 {
+        let base = sprite * 4;
+        bus.vdc.satb[base] = 64;
+        let x = if sprite < 16 { sprite * 4 } else { 100 };
+        bus.vdc.satb[base + 1] = (x + 32) as u16;
+        bus.vdc.satb[base + 2] = (TILE_ID as u16) << 1;
+        bus.vdc.satb[base + 3] = 0x0080 | (sprite / 16) as u16;
+    }
+    bus.vce.set_palette_word(0x111, 0x01FF);
+    let sprite_colour = bus.vce.palette_rgb(0x111);
+
+    // Only the 17th sprite, past the 16 the VDC fetches, covers x=110.
+    bus.render_frame_from_vram();
+    assert_ne!(bus.framebuffer[110], sprite_colour);
+
+    let mut layers = bus.layer_toggles();
+    layers.sprite_line_limit = false;
+    bus.set_layer_toggles(layers);
+    bus.render_frame_from_vram();
+    assert_eq!(bus.framebuffer[110], sprite_colour);
+    assert_ne!(bus.vdc.status_bits() & VDC_STATUS_OR, 0);
+}
+
+#[test]
+fn bat_map_draws_every_entry_with_its_palette() {
+    let mut bus = Bus::new();
+    bus.vdc.registers[0x09] = 0x0010; // 64x32 map
+    const TILE: u16 = 0x100;
+    let entry = bus.vdc.map_entry_address(1, 40);
+    bus.vdc.vram[entry] = 0x2000 | TILE;
+    // Row 2 of the tile: colour 1 at x=0, colour 0x0F at x=7.
+    bus.vdc.vram[TILE as usize * 16 + 2] = 0x0181;
+    bus.vdc.vram[TILE as usize * 16 + 8 + 2] = 0x0101;
+    bus.vce.set_palette_word(0x000, 0x0007);
+    bus.vce.set_palette_word(0x021, 0x01C0);
+    bus.vce.set_palette_word(0x02F, 0x0038);
+
+    let map = bus.render_bat_map();
+    assert_eq!((map.columns, map.rows), (64, 32));
+    assert_eq!((map.image.width, map.image.height), (512, 256));
+    let (x, y) = (40 * TILE_WIDTH, TILE_HEIGHT + 2);
+    assert_eq!(map.image.rgb(x, y), bus.vce.palette_rgb(0x021));
+    assert_eq!(map.image.rgb(x + 7, y), bus.vce.palette_rgb(0x02F));
+    assert_eq!(map.image.rgb(x + 3, y), bus.vce.palette_rgb(0x000));
+    assert!(map.image.pixels.chunks_exact(4).all(|p| p[3] == 0xFF));
+}
+
+#[test]
+fn bat_map_viewport_follows_scroll_and_wraps() {
+    let mut bus = Bus::new();
+    bus.vdc.registers[0x09] = 0x0010; // 64x32 map, 512x256 pixels
+    bus.vdc.scroll_x = 0x01F4;
+    bus.vdc.scroll_y = 0x01F0;
+
+    let map = bus.render_bat_map();
+    assert_eq!(
+        map.viewport,
+        MapRect {
+            x: 500,
+            y: 240,
+            width: 256,
+            height: 224,
+        }
+    );
+    let rects = map.viewport_rects();
+    assert_eq!(rects.len(), 4);
+    assert_eq!(
+        rects[0],
+        MapRect {
+            x: 500,
+            y: 240,
+            width: 12,
+            height: 16,
+        }
+    );
+    assert_eq!(
+        rects[3],
+        MapRect {
+            x: 0,
+            y: 0,
+            width: 244,
+            height: 208,
+        }
+    );
+}
+
+#[test]
+fn bat_map_viewport_uses_the_scroll_the_top_line_was_drawn_with() {
+    let mut bus = Bus::new();
+    bus.vdc.registers[0x09] = 0x0010;
+    let write_scroll = |bus: &mut Bus, x: u16, y: u16| {
+        for (register, value) in [(0x07, x), (0x08, y)] {
+            bus.vdc.write_select(register);
+            bus.vdc.write_data_low(value as u8);
+            bus.vdc.write_data_high((value >> 8) as u8);
+        }
+    };
+    write_scroll(&mut bus, 0x0010, 0x0020);
+    let line_cycles = VDC_VBLANK_INTERVAL.div_ceil(LINES_PER_FRAME as u32);
+    while bus.take_frame().is_none() {
+        bus.tick(line_cycles, true);
+    }
+    // Not on screen until the next frame.
+    write_scroll(&mut bus, 0x0100, 0x0100);
+
+    let map = bus.render_bat_map();
+    assert_eq!((map.viewport.x, map.viewport.y), (0x10, 0x20));
+}
+
+#[test]
+fn satb_entries_mask_pattern_bits_per_size_bit() {
+    let mut satb = [0u16; 8];
+    satb[2] = 0x03FF << 1;
+    satb[3] = 0x2000; // CGY 2: 4 cells high
+    satb[6] = 0x03FF << 1;
+    satb[7] = 0x3100; // CGY 3, 2 cells wide
+    let tall = SatbEntry::decode(&satb, 0);
+    assert_eq!(
+        (tall.width(), tall.height(), tall.pattern),
+        (16, 64, 0x03FB)
+    );
+    let big = SatbEntry::decode(&satb, 1);
+    assert_eq!((big.width(), big.height(), big.pattern), (32, 64, 0x03F8));
+    assert_eq!(SatbEntry::decode(&satb, 2), SatbEntry::decode(&[0; 4], 0));
+}
+
+#[test]
+fn sprite_entries_decode_satb_fields_and_visibility() {
+    let mut bus = Bus::new();
+    set_vdc_control(&mut bus, VDC_CTRL_DISPLAY_FULL);
+    bus.vdc.satb[12] = 64 + 10;
+    bus.vdc.satb[13] = 32 + 20;
+    bus.vdc.satb[14] = 0x0205 << 1;
+    bus.vdc.satb[15] = 0x1000 | 0x0800 | 0x0100 | 0x0080 | 0x0005;
+    bus.vdc.satb[17] = 0x0000; // x=-32: fully left of the display
+
+    let sprites = bus.sprite_entries();
+    assert_eq!(sprites.len(), 64);
+    assert_eq!(
+        sprites[3],
+        SpriteEntry {
+            index: 3,
+            x: 20,
+            y: 10,
+            pattern: 0x0204,
+            width: 32,
+            height: 32,
+            palette: 5,
+            h_flip: true,
+            v_flip: false,
+            high_priority: true,
+            visible: true,
+        }
+    );
+    assert!(!sprites[4].visible);
+
+    set_vdc_control(&mut bus, VDC_CTRL_ENABLE_BACKGROUND_LEGACY);
+    assert!(!bus.sprite_entries()[3].visible);
+}
+
+#[test]
+fn rendered_sprite_is_flipped_with_transparent_colour_zero() {
+    let mut bus = Bus::new();
+    const PATTERN: usize = 0x180;
+    bus.vdc.vram[PATTERN * SPRITE_PATTERN_WORDS] = 0x8000; // colour 1 at (0, 0)
+    bus.vdc.satb[2] = (PATTERN as u16) << 1;
+    bus.vdc.satb[3] = 0x8800 | 0x0002;
+    bus.vce.set_palette_word(0x121, 0x01FF);
+
+    let image = bus.render_sprite(0);
+    assert_eq!((image.width, image.height), (16, 16));
+    assert_eq!(image.rgb(15, 15), bus.vce.palette_rgb(0x121));
+    assert_eq!(image.pixels[(15 * 16 + 15) * 4 + 3], 0xFF);
+    assert_eq!(image.pixels[3], 0x00);
+}
+
+#[test]
+fn tile_sheet_uses_the_bank_each_tile_is_mapped_with() {
+    let mut bus = Bus::new();
+    const TILE: usize = 0x123;
+    bus.vdc.vram[TILE * 16] = 0x0080; // colour 1 at (0, 0)
+    bus.vdc.vram[TILE * 16 + 1] = 0x0080;
+    let entry = bus.vdc.map_entry_address(3, 5);
+    bus.vdc.vram[entry] = 0x7000 | TILE as u16;
+    let later = bus.vdc.map_entry_address(4, 5);
+    bus.vdc.vram[later] = 0x9000 | TILE as u16;
+    bus.vce.set_palette_word(0x011, 0x0038);
+    bus.vce.set_palette_word(0x071, 0x01C0);
+
+    let x = (TILE % TILE_SHEET_COLUMNS) * TILE_WIDTH;
+    let y = (TILE / TILE_SHEET_COLUMNS) * TILE_HEIGHT;
+    let sheet = bus.render_tile_sheet(SheetPalette::Referenced);
+    assert_eq!(sheet.width, TILE_SHEET_COLUMNS * TILE_WIDTH);
+    assert_eq!(sheet.height, 2048 / TILE_SHEET_COLUMNS * TILE_HEIGHT);
+    assert_eq!(sheet.rgb(x, y), bus.vce.palette_rgb(0x071));
+    assert_eq!(sheet.rgb(x, y + 1), bus.vce.palette_rgb(0x071));
+    assert_eq!(sheet.pixels[((y + 2) * sheet.width + x) * 4 + 3], 0);
+
+    let sheet = bus.render_tile_sheet(SheetPalette::Bank(1));
+    assert_eq!(sheet.rgb(x, y), bus.vce.palette_rgb(0x011));
+}
+
+#[test]
+fn sprite_sheet_colours_every_cell_of_a_referencing_sprite() {
+    let mut bus = Bus::new();
+    const PATTERN: usize = 0x40;
+    write_constant_sprite_tile(&mut bus, PATTERN, 0x01);
+    write_constant_sprite_tile(&mut bus, PATTERN + 1, 0x01);
+    bus.vdc.satb[2] = (PATTERN as u16) << 1;
+    bus.vdc.satb[3] = 0x0100 | 0x0006; // 32x16, sprite palette 6
+    bus.vce.set_palette_word(0x161, 0x0038);
+
+    let sheet = bus.render_sprite_sheet(SheetPalette::Referenced);
+    assert_eq!(sheet.width, SPRITE_SHEET_COLUMNS * SPRITE_PATTERN_WIDTH);
+    for pattern in [PATTERN, PATTERN + 1] {
+        let x = (pattern % SPRITE_SHEET_COLUMNS) * SPRITE_PATTERN_WIDTH;
+        let y = (pattern / SPRITE_SHEET_COLUMNS) * SPRITE_PATTERN_HEIGHT;
+        assert_eq!(sheet.rgb(x + 5, y + 5), bus.vce.palette_rgb(0x161));
+    }
+}
@@ -1,4 +1,6 @@
 use super::{BRAM_FORMAT_HEADER, BRAM_SIZE, TIMER_CONTROL_START};
+use crate::vdc::FRAME_HEIGHT;
+use std::fmt;
 
 /// A `bool` wrapper that is invisible to bincode serialization.
 /// Encodes as zero bytes; decodes as `false`.  Used for transient render
@@ -176,6 +178,294 @@ impl core::ops::DerefMut for TransientPaletteFlicker {
     }
 }
 
+/// Per-frame bookkeeping for the scanline renderer: which framebuffer rows
+/// have been drawn and the display window each used, plus whether any line
+/// enabled BG or sprites.  Encodes as zero bytes; a loaded state starts a
+/// fresh frame.
+#[derive(Clone)]
+pub(super) struct ScanlineFrame {
+    pub(super) rows_drawn: [bool; FRAME_HEIGHT],
+    pub(super) line_display_starts: [usize; FRAME_HEIGHT],
+    pub(super) line_display_widths: [usize; FRAME_HEIGHT],
+    pub(super) any_background: bool,
+    pub(super) any_sprites: bool,
+}
+
+impl Default for ScanlineFrame {
+    fn default() -> Self {
+        Self {
+            rows_drawn: [false; FRAME_HEIGHT],
+            line_display_starts: [0; FRAME_HEIGHT],
+            line_display_widths: [0; FRAME_HEIGHT],
+            any_background: false,
+            any_sprites: false,
+        }
+    }
+}
+
+impl ScanlineFrame {
+    pub(super) fn started(&self) -> bool {
+        self.rows_drawn.iter().any(|&drawn| drawn)
+    }
+}
+
+impl bincode::Encode for ScanlineFrame {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for ScanlineFrame {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for ScanlineFrame {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+/// How frames handed to the front-end are sized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputGeometry {
+    /// Just the active display, so the size follows HDR/VDW and the dot
+    /// clock from frame to frame.
+    #[default]
+    Native,
+    /// A canvas that never changes size: `width` 5.37 MHz dots by `height`
+    /// scanlines.  The active display is centred vertically and lands
+    /// horizontally wherever HDS puts it, as on a TV; the 7.16 and
+    /// 10.74 MHz modes are resampled to the canvas dot.
+    Fixed { width: usize, height: usize },
+}
+
+impl OutputGeometry {
+    /// 256×240, the common "safe area" capture size.
+    pub const STANDARD: Self = Self::Fixed {
+        width: 256,
+        height: 240,
+    };
+    /// 342×242, a whole 5.37 MHz line and every line a TV can show.
+    pub const FULL_OVERSCAN: Self = Self::Fixed {
+        width: 342,
+        height: 242,
+    };
+}
+
+/// Pixels trimmed from each edge of the output after the geometry is
+/// applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CropMargins {
+    pub left: usize,
+    pub right: usize,
+    pub top: usize,
+    pub bottom: usize,
+}
+
+/// Which parts of the picture get drawn.  Hiding something only changes
+/// the output: sprites still take line slots and set the overflow flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayerToggles {
+    pub background: bool,
+    pub sprites: bool,
+    /// Bit `n` hides SATB entry `n`.
+    pub hidden_sprites: u64,
+    /// Let opaque BG cover low-priority sprites.  Off draws every sprite in
+    /// front.
+    pub sprite_priority: bool,
+    /// Drop sprites past the VDC's 16 per line.
+    pub sprite_line_limit: bool,
+}
+
+impl Default for LayerToggles {
+    fn default() -> Self {
+        Self {
+            background: true,
+            sprites: true,
+            hidden_sprites: 0,
+            sprite_priority: true,
+            sprite_line_limit: true,
+        }
+    }
+}
+
+impl LayerToggles {
+    pub fn sprite_visible(&self, index: usize) -> bool {
+        index < 64 && self.hidden_sprites & (1 << index) == 0
+    }
+
+    pub fn set_sprite_visible(&mut self, index: usize, visible: bool) {
+        if index < 64 {
+            if visible {
+                self.hidden_sprites &= !(1 << index);
+            } else {
+                self.hidden_sprites |= 1 << index;
+            }
+        }
+    }
+}
+
+/// Layout of the samples handed out by `take_audio_samples`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AudioChannels {
+    /// One value per sample frame: the average of left and right.
+    #[default]
+    Mono,
+    /// Interleaved left, right pairs.
+    Stereo,
+}
+
+impl AudioChannels {
+    /// Values per sample frame.
+    pub fn count(self) -> usize {
+        match self {
+            Self::Mono => 1,
+            Self::Stereo => 2,
+        }
+    }
+}
+
+/// The host's [`AudioChannels`] choice.  Encodes as zero bytes; it belongs
+/// to the front-end, not the save state.
+#[derive(Clone, Copy, Default)]
+pub(super) struct TransientAudioChannels(pub(super) AudioChannels);
+
+impl bincode::Encode for TransientAudioChannels {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for TransientAudioChannels {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for TransientAudioChannels {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+/// Front-end output settings plus the dot clock of the last finished
+/// frame.  Encodes as zero bytes: the settings belong to the host,
+/// not the save state.
+#[derive(Clone, Copy)]
+pub(super) struct FrameOutput {
+    pub(super) geometry: OutputGeometry,
+    pub(super) crop: CropMargins,
+    pub(super) layers: LayerToggles,
+    /// VCE clocks per framebuffer pixel.
+    pub(super) dot_divider: usize,
+}
+
+impl Default for FrameOutput {
+    fn default() -> Self {
+        Self {
+            geometry: OutputGeometry::default(),
+            crop: CropMargins::default(),
+            layers: super::Bus::env_layer_toggles(),
+            dot_divider: 4,
+        }
+    }
+}
+
+impl bincode::Encode for FrameOutput {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for FrameOutput {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for FrameOutput {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+/// What last stored a VCE palette entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteWriter {
+    /// A write to the VCE data port by the instruction at this PC.
+    Cpu(u16),
+    /// A VRAM-to-CRAM DMA.
+    CramDma,
+    /// The front-end, e.g. a palette editor.
+    Host,
+}
+
+/// Last writer of each palette entry, plus the PC of the instruction the
+/// CPU is executing so port writes can be attributed.  Debug bookkeeping;
+/// encodes as zero bytes.
+#[derive(Clone)]
+pub(super) struct PaletteWriteLog {
+    pub(super) pc: u16,
+    pub(super) writers: [Option<PaletteWriter>; 0x200],
+}
+
+impl Default for PaletteWriteLog {
+    fn default() -> Self {
+        Self {
+            pc: 0,
+            writers: [None; 0x200],
+        }
+    }
+}
+
+impl bincode::Encode for PaletteWriteLog {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for PaletteWriteLog {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for PaletteWriteLog {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
 /// A BRAM wrapper that is intentionally excluded from save-state encoding.
 /// Old save states (before BRAM support) remain decodable because this field
 /// consumes zero bytes on decode.
@@ -234,8 +524,10 @@ pub(super) enum VdcPort {
     Data,
 }
 
-#[derive(Clone, Copy, Debug, bincode::Encode, bincode::Decode)]
-pub(super) enum BankMapping {
+/// What an MPR value selects. `base` is the byte offset of the 8 KiB page
+/// in work RAM, the ROM image (header stripped) or cartridge RAM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub enum BankMapping {
     Ram { base: usize },
     Rom { base: usize },
     CartRam { base: usize },
@@ -243,6 +535,55 @@ pub(super) enum BankMapping {
     Bram,
 }
 
+impl BankMapping {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ram { .. } => "RAM",
+            Self::Rom { .. } => "ROM",
+            Self::CartRam { .. } => "CartRAM",
+            Self::Hardware => "I/O",
+            Self::Bram => "BRAM",
+        }
+    }
+
+    /// Offset of the page in its backing store; zero for the hardware page
+    /// and BRAM, which are a single page each.
+    pub fn base(self) -> usize {
+        match self {
+            Self::Ram { base } | Self::Rom { base } | Self::CartRam { base } => base,
+            Self::Hardware | Self::Bram => 0,
+        }
+    }
+}
+
+/// Where a logical CPU address currently lands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub addr: u16,
+    /// MPR slot (`addr >> 13`).
+    pub slot: usize,
+    /// Raw MPR value for the slot.
+    pub mpr: u8,
+    pub mapping: BankMapping,
+    /// Byte offset in the backing store: `mapping.base()` plus the offset
+    /// within the page. For the hardware page this is the I/O offset.
+    pub offset: usize,
+}
+
+impl fmt::Display for MemoryRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "${:04X} MPR{}=${:02X} {}+${:05X}",
+            self.addr,
+            self.slot,
+            self.mpr,
+            self.mapping.label(),
+            self.offset
+        )
+    }
+}
+
 #[derive(Clone, Copy, bincode::Encode, bincode::Decode)]
 pub(super) enum ControlRegister {
     TimerCounter,
@@ -0,0 +1,378 @@
+use crate::vdc::{
+    SPRITE_COUNT, SPRITE_PATTERN_HEIGHT, SPRITE_PATTERN_WIDTH, SPRITE_PATTERN_WORDS, SatbEntry,
+    TILE_HEIGHT, TILE_WIDTH, VDC_CTRL_ENABLE_SPRITES_LEGACY,
+};
+
+use super::Bus;
+
+/// An RGBA8 picture built by the graphics viewers, rows top to bottom.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    /// Colour at (`x`, `y`) as `0x00RRGGBB`, ignoring alpha.
+    pub fn rgb(&self, x: usize, y: usize) -> u32 {
+        let i = (y * self.width + x) * 4;
+        let p = &self.pixels[i..i + 4];
+        ((p[0] as u32) << 16) | ((p[1] as u32) << 8) | p[2] as u32
+    }
+
+    /// Store the opaque `0x00RRGGBB` colour `rgb` at (`x`, `y`).
+    pub fn put_rgb(&mut self, x: usize, y: usize, rgb: u32) {
+        let i = (y * self.width + x) * 4;
+        self.pixels[i..i + 4].copy_from_slice(&[
+            (rgb >> 16) as u8,
+            (rgb >> 8) as u8,
+            rgb as u8,
+            0xFF,
+        ]);
+    }
+}
+
+/// A rectangle in map pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The whole virtual background map, drawn with the current tiles and
+/// palette, and the part of it the scroll registers put on screen.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatMap {
+    pub image: RgbaImage,
+    /// Map size in tiles, from MWR.
+    pub columns: usize,
+    pub rows: usize,
+    /// Displayed area at the current BXR/BYR.  It may run past the right or
+    /// bottom edge, in which case the screen wraps to the other side.
+    pub viewport: MapRect,
+}
+
+impl BatMap {
+    /// The viewport split at the map edges into up to four rectangles that
+    /// each lie inside the image.
+    pub fn viewport_rects(&self) -> Vec<MapRect> {
+        let (map_width, map_height) = (self.image.width, self.image.height);
+        if map_width == 0 || map_height == 0 {
+            return Vec::new();
+        }
+        let split = |start: usize, len: usize, size: usize| {
+            let len = len.min(size);
+            let first = len.min(size - start);
+            let mut spans = vec![(start, first)];
+            if first < len {
+                spans.push((0, len - first));
+            }
+            spans
+        };
+        let view = self.viewport;
+        let mut rects = Vec::new();
+        for (y, height) in split(view.y % map_height, view.height, map_height) {
+            for (x, width) in split(view.x % map_width, view.width, map_width) {
+                rects.push(MapRect {
+                    x,
+                    y,
+                    width,
+                    height,
+                });
+            }
+        }
+        rects
+    }
+}
+
+/// One SATB entry, decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpriteEntry {
+    /// SATB slot, 0-63; lower slots are drawn in front.
+    pub index: usize,
+    /// Top-left corner relative to the first active dot and line.
+    pub x: i32,
+    pub y: i32,
+    /// First 16x16 pattern, with the bits the size ignores cleared.
+    pub pattern: usize,
+    /// Size in pixels: 16 or 32 wide, 16, 32 or 64 tall.
+    pub width: usize,
+    pub height: usize,
+    /// Sprite palette bank, 0-15 (VCE entries 0x100 + 16 * bank).
+    pub palette: u8,
+    pub h_flip: bool,
+    pub v_flip: bool,
+    /// Drawn in front of background pixels.
+    pub high_priority: bool,
+    /// Sprites are switched on and the entry overlaps the display.  Line
+    /// limits and host layer toggles are not considered.
+    pub visible: bool,
+}
+
+/// Palette the tile and pattern sheets are coloured with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SheetPalette {
+    /// One bank (0-15) for everything: BG palette `n` for tiles, sprite
+    /// palette `n` for patterns.
+    Bank(u8),
+    /// The bank each tile is used with in the BAT, or each pattern in the
+    /// SATB; the first use wins and unused graphics get bank 0.
+    #[default]
+    Referenced,
+}
+
+/// Tiles per row of [`Bus::render_tile_sheet`].
+pub const TILE_SHEET_COLUMNS: usize = 32;
+/// Patterns per row of [`Bus::render_sprite_sheet`].
+pub const SPRITE_SHEET_COLUMNS: usize = 16;
+
+impl Bus {
+    /// Decode all 64 SATB entries from the VDC's internal copy.
+    pub fn sprite_entries(&self) -> Vec<SpriteEntry> {
+        (0..SPRITE_COUNT)
+            .map(|index| self.decode_sprite(index).0)
+            .collect()
+    }
+
+    /// Draw SATB entry `index` as it appears on screen, flips included.
+    /// Colour 0 is left transparent.
+    pub fn render_sprite(&self, index: usize) -> RgbaImage {
+        let (sprite, upper_cg_pair) = self.decode_sprite(index % SPRITE_COUNT);
+        let mut image = RgbaImage::new(sprite.width, sprite.height);
+        let palette_base = 0x100 | ((sprite.palette as usize) << 4);
+        for y in 0..sprite.height {
+            let src_y = if sprite.v_flip {
+                sprite.height - 1 - y
+            } else {
+                y
+            };
+            for x in 0..sprite.width {
+                let src_x = if sprite.h_flip {
+                    sprite.width - 1 - x
+                } else {
+                    x
+                };
+                let pattern = sprite.pattern
+                    + (src_y / SPRITE_PATTERN_HEIGHT) * 2
+                    + src_x / SPRITE_PATTERN_WIDTH;
+                let pixel = self.sprite_pattern_pixel(
+                    pattern,
+                    src_x % SPRITE_PATTERN_WIDTH,
+                    src_y % SPRITE_PATTERN_HEIGHT,
+                    upper_cg_pair,
+                ) as usize;
+                if pixel != 0 {
+                    image.put_rgb(x, y, self.vce.palette_rgb(palette_base | pixel));
+                }
+            }
+        }
+        image
+    }
+
+    /// Decode SATB entry `index`, along with the CG pair bit of its
+    /// pattern word.
+    fn decode_sprite(&self, index: usize) -> (SpriteEntry, bool) {
+        let entry = SatbEntry::decode(&self.vdc.satb, index);
+        let (width, height) = (entry.width(), entry.height());
+        let sprites_on = self.vdc.control_for_render() & VDC_CTRL_ENABLE_SPRITES_LEGACY != 0;
+        let on_screen = entry.x < self.current_display_width as i32
+            && entry.x + width as i32 > 0
+            && entry.y < self.current_display_height as i32
+            && entry.y + height as i32 > 0;
+        let sprite = SpriteEntry {
+            index,
+            x: entry.x,
+            y: entry.y,
+            pattern: entry.pattern,
+            width,
+            height,
+            palette: entry.palette,
+            h_flip: entry.h_flip,
+            v_flip: entry.v_flip,
+            high_priority: entry.high_priority,
+            visible: sprites_on && on_screen,
+        };
+        (sprite, entry.upper_cg_pair)
+    }
+
+    /// Colour number (0-15) of pixel (`x`, `y`) in 16x16 sprite pattern
+    /// `pattern`.  In the 2-bit CG mode only one plane pair is used.
+    fn sprite_pattern_pixel(&self, pattern: usize, x: usize, y: usize, upper_cg_pair: bool) -> u8 {
+        let vram = &self.vdc.vram;
+        let mask = vram.len() - 1;
+        let base = pattern * SPRITE_PATTERN_WORDS + y;
+        let shift = 15 - x;
+        let mut planes =
+            [0, 16, 32, 48].map(|offset| ((vram[(base + offset) & mask] >> shift) & 0x01) as u8);
+        if (self.vdc.registers[0x09] >> 2) & 0x03 == 0x01 {
+            if upper_cg_pair {
+                planes = [planes[2], planes[3], 0, 0];
+            } else {
+                planes[2] = 0;
+                planes[3] = 0;
+            }
+        }
+        planes[0] | (planes[1] << 1) | (planes[2] << 2) | (planes[3] << 3)
+    }
+
+    /// Colour number (0-15) of pixel (`x`, `y`) in 8x8 background tile
+    /// `tile`, honouring the CG plane select of the 4-clock VRAM mode.
+    pub(super) fn background_tile_pixel(&self, tile: usize, x: usize, y: usize) -> u8 {
+        let vram = &self.vdc.vram;
+        let mask = vram.len() - 1;
+        let base = tile * 16 + y;
+        let mut chr0 = vram[base & mask];
+        let mut chr1 = vram[(base + 8) & mask];
+        let mwr = self.vdc.registers[0x09];
+        if mwr & 0x03 == 0x03 {
+            if mwr & 0x80 == 0 {
+                chr1 = 0;
+            } else {
+                chr0 = 0;
+            }
+        }
+        let shift = 7 - x;
+        let bit = |word: u16, offset: usize| ((word >> (shift + offset)) & 0x01) as u8;
+        bit(chr0, 0) | (bit(chr0, 8) << 1) | (bit(chr1, 0) << 2) | (bit(chr1, 8) << 3)
+    }
+
+    /// Draw the whole BAT, as sized by MWR, the way the background layer
+    /// would show it.  Colour 0 of every tile shows palette entry 0.
+    pub fn render_bat_map(&self) -> BatMap {
+        let (columns, rows) = self.vdc.map_dimensions();
+        let mut image = RgbaImage::new(columns * TILE_WIDTH, rows * TILE_HEIGHT);
+        for row in 0..rows {
+            for column in 0..columns {
+                let address = self.vdc.map_entry_address(row, column);
+                let entry = self.vdc.vram[address & (self.vdc.vram.len() - 1)];
+                let tile = (entry & 0x07FF) as usize;
+                let palette_base = ((entry >> 12) as usize) << 4;
+                for y in 0..TILE_HEIGHT {
+                    for x in 0..TILE_WIDTH {
+                        let pixel = self.background_tile_pixel(tile, x, y) as usize;
+                        let index = if pixel == 0 { 0 } else { palette_base | pixel };
+                        image.put_rgb(
+                            column * TILE_WIDTH + x,
+                            row * TILE_HEIGHT + y,
+                            self.vce.palette_rgb(index),
+                        );
+                    }
+                }
+            }
+        }
+        let top_line = self
+            .vdc
+            .line_state_index_for_frame_row(self.current_display_y_offset);
+        let (origin_x, origin_y) = self.vdc.bg_origin_for_line(top_line);
+        let viewport = MapRect {
+            x: origin_x % image.width,
+            y: origin_y % image.height,
+            width: self.current_display_width,
+            height: self.current_display_height,
+        };
+        BatMap {
+            image,
+            columns,
+            rows,
+            viewport,
+        }
+    }
+
+    /// Every 8x8 tile in VRAM, [`TILE_SHEET_COLUMNS`] to a row, with
+    /// colour 0 transparent.
+    pub fn render_tile_sheet(&self, palette: SheetPalette) -> RgbaImage {
+        let tiles = self.vdc.vram.len() / 16;
+        let mut banks = vec![None; tiles];
+        match palette {
+            SheetPalette::Bank(bank) => banks.fill(Some(bank & 0x0F)),
+            SheetPalette::Referenced => {
+                let (columns, rows) = self.vdc.map_dimensions();
+                for row in 0..rows {
+                    for column in 0..columns {
+                        let address = self.vdc.map_entry_address(row, column);
+                        let entry = self.vdc.vram[address & (self.vdc.vram.len() - 1)];
+                        let slot = &mut banks[(entry & 0x07FF) as usize % tiles];
+                        slot.get_or_insert((entry >> 12) as u8);
+                    }
+                }
+            }
+        }
+        let sheet_rows = tiles.div_ceil(TILE_SHEET_COLUMNS);
+        let mut image = RgbaImage::new(TILE_SHEET_COLUMNS * TILE_WIDTH, sheet_rows * TILE_HEIGHT);
+        for (tile, bank) in banks.into_iter().enumerate() {
+            let palette_base = (bank.unwrap_or(0) as usize) << 4;
+            let left = (tile % TILE_SHEET_COLUMNS) * TILE_WIDTH;
+            let top = (tile / TILE_SHEET_COLUMNS) * TILE_HEIGHT;
+            for y in 0..TILE_HEIGHT {
+                for x in 0..TILE_WIDTH {
+                    let pixel = self.background_tile_pixel(tile, x, y) as usize;
+                    if pixel != 0 {
+                        image.put_rgb(
+                            left + x,
+                            top + y,
+                            self.vce.palette_rgb(palette_base | pixel),
+                        );
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    /// Every 16x16 sprite pattern in VRAM, [`SPRITE_SHEET_COLUMNS`] to a
+    /// row, with colour 0 transparent.
+    pub fn render_sprite_sheet(&self, palette: SheetPalette) -> RgbaImage {
+        let patterns = self.vdc.vram.len() / SPRITE_PATTERN_WORDS;
+        // (bank, CG pair) per pattern.
+        let mut uses: Vec<Option<(u8, bool)>> = vec![None; patterns];
+        match palette {
+            SheetPalette::Bank(bank) => uses.fill(Some((bank & 0x0F, false))),
+            SheetPalette::Referenced => {
+                for index in 0..SPRITE_COUNT {
+                    let (sprite, upper_cg_pair) = self.decode_sprite(index);
+                    for cell_y in 0..sprite.height / SPRITE_PATTERN_HEIGHT {
+                        for cell_x in 0..sprite.width / SPRITE_PATTERN_WIDTH {
+                            let pattern = (sprite.pattern + cell_y * 2 + cell_x) % patterns;
+                            uses[pattern].get_or_insert((sprite.palette, upper_cg_pair));
+                        }
+                    }
+                }
+            }
+        }
+        let sheet_rows = patterns.div_ceil(SPRITE_SHEET_COLUMNS);
+        let mut image = RgbaImage::new(
+            SPRITE_SHEET_COLUMNS * SPRITE_PATTERN_WIDTH,
+            sheet_rows * SPRITE_PATTERN_HEIGHT,
+        );
+        for (pattern, used) in uses.into_iter().enumerate() {
+            let (bank, upper_cg_pair) = used.unwrap_or((0, false));
+            let palette_base = 0x100 | ((bank as usize) << 4);
+            let left = (pattern % SPRITE_SHEET_COLUMNS) * SPRITE_PATTERN_WIDTH;
+            let top = (pattern / SPRITE_SHEET_COLUMNS) * SPRITE_PATTERN_HEIGHT;
+            for y in 0..SPRITE_PATTERN_HEIGHT {
+                for x in 0..SPRITE_PATTERN_WIDTH {
+                    let pixel = self.sprite_pattern_pixel(pattern, x, y, upper_cg_pair) as usize;
+                    if pixel != 0 {
+                        image.put_rgb(
+                            left + x,
+                            top + y,
+                            self.vce.palette_rgb(palette_base | pixel),
+                        );
+                    }
+                }
+            }
+        }
+        image
+    }
+}
@@ -0,0 +1,154 @@
+//! Code/Data Logger: per-byte ROM usage flags.
+//!
+//! While enabled, every ROM byte the CPU touches is tagged with how it was
+//! used. The `.cdl` file is one flag byte per ROM byte (header stripped), in
+//! the same order as the ROM image, so it can be lined up against the
+//! `.pce` file directly. Logs merge: loading an existing file and playing
+//! further only ever adds flags.
+
+use std::path::{Path, PathBuf};
+
+/// First byte of an instruction.
+pub const CDL_OPCODE: u8 = 0x01;
+/// Operand byte of an instruction.
+pub const CDL_OPERAND: u8 = 0x02;
+/// Read as data by a load, compare, etc.
+pub const CDL_DATA: u8 = 0x04;
+/// Source of a TII/TDD/TIN/TIA/TAI block transfer.
+pub const CDL_BLOCK_SOURCE: u8 = 0x08;
+/// Block-transfer source copied into the VDC data port, i.e. VRAM
+/// contents (usually graphics or tile maps).
+pub const CDL_VRAM_SOURCE: u8 = 0x10;
+
+/// Path of the `.cdl` file that sits next to `rom_path`.
+pub fn cdl_path_for(rom_path: &Path) -> PathBuf {
+    rom_path.with_extension("cdl")
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CdlSummary {
+    pub total: usize,
+    pub opcode: usize,
+    pub operand: usize,
+    pub data: usize,
+    pub block_source: usize,
+    pub vram_source: usize,
+    pub unused: usize,
+}
+
+/// ROM usage log owned by the bus.
+///
+/// Like other debugging aids it is not machine state: it encodes as zero
+/// bytes and decodes as an empty, disabled log.
+#[derive(Clone, Debug)]
+pub struct CodeDataLog {
+    enabled: bool,
+    flags: Vec<u8>,
+    /// Flag applied to the next ROM reads; set by the CPU around fetches
+    /// and block transfers.
+    access: u8,
+}
+
+impl Default for CodeDataLog {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            flags: Vec::new(),
+            access: CDL_DATA,
+        }
+    }
+}
+
+impl CodeDataLog {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start or stop logging for a ROM of `rom_len` bytes. Existing flags
+    /// are kept when the size matches.
+    pub(crate) fn set_enabled(&mut self, enabled: bool, rom_len: usize) {
+        self.enabled = enabled;
+        if self.flags.len() != rom_len {
+            self.flags = vec![0; rom_len];
+        }
+    }
+
+    pub fn flags(&self) -> &[u8] {
+        &self.flags
+    }
+
+    pub fn clear(&mut self) {
+        self.flags.fill(0);
+    }
+
+    /// OR previously saved flags into the log. Fails if the file was made
+    /// for a ROM of a different size.
+    pub fn merge(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.len() != self.flags.len() {
+            return Err(format!(
+                "CDL size {} does not match ROM size {}",
+                data.len(),
+                self.flags.len()
+            ));
+        }
+        for (flag, &saved) in self.flags.iter_mut().zip(data) {
+            *flag |= saved;
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> CdlSummary {
+        let mut summary = CdlSummary {
+            total: self.flags.len(),
+            ..CdlSummary::default()
+        };
+        for &flag in &self.flags {
+            let count = |mask: u8| (flag & mask != 0) as usize;
+            summary.opcode += count(CDL_OPCODE);
+            summary.operand += count(CDL_OPERAND);
+            summary.data += count(CDL_DATA);
+            summary.block_source += count(CDL_BLOCK_SOURCE);
+            summary.vram_source += count(CDL_VRAM_SOURCE);
+            summary.unused += (flag == 0) as usize;
+        }
+        summary
+    }
+
+    /// Set the flag applied to subsequent ROM reads, returning the old one.
+    #[inline]
+    pub(crate) fn set_access(&mut self, access: u8) -> u8 {
+        std::mem::replace(&mut self.access, access)
+    }
+
+    #[inline]
+    pub(crate) fn mark(&mut self, rom_offset: usize) {
+        if let Some(flag) = self.flags.get_mut(rom_offset) {
+            *flag |= self.access;
+        }
+    }
+}
+
+impl bincode::Encode for CodeDataLog {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for CodeDataLog {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for CodeDataLog {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
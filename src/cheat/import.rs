@@ -0,0 +1,238 @@
+//! Importers for cheat lists kept in other emulators' formats.
+//!
+//! Raw PC Engine codes are `LOCATION:VALUE` with hex fields:
+//!
+//! - `BBAAAA:VV` — MPR bank `BB` and an address whose low 13 bits are the
+//!   offset in that bank (`F80044:03`, `F82044:03`)
+//! - `xxxxxx:VV` — 21-bit physical address, bank `xxxxxx >> 13`
+//!   (`1F0044:03`); any 6-digit location below `$200000` is read this way
+//! - `AAAA:VV` — work RAM index or `$2000-$3FFF` CPU address, as the cheat
+//!   panel accepts
+//!
+//! Bank `$F8` is work RAM and banks `$80-$F7` cart RAM, both translated to
+//! the work RAM ++ cart RAM index space of [`CheatManager::apply`]. Banks
+//! `$00-$7F` become ROM-patch cheats. A 4-, 6- or 8-digit value is
+//! written little-endian as a 16-, 24- or 32-bit cheat.
+
+use super::{CheatEntry, CheatManager, CheatTarget, CheatWidth, WORK_RAM_SIZE};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+const BANK_SIZE: u32 = 0x2000;
+const WORK_RAM_BANK: u8 = 0xF8;
+const PHYSICAL_LIMIT: u32 = 0x20_0000;
+
+/// Where `offset` in MPR bank `bank` lives for a cheat, if anywhere.
+fn bank_location(bank: u8, offset: u32, cart_ram_size: usize) -> Option<(CheatTarget, u32)> {
+    let offset = offset & (BANK_SIZE - 1);
+    match bank {
+        WORK_RAM_BANK if (offset as usize) < WORK_RAM_SIZE => Some((CheatTarget::Ram, offset)),
+        0x80..=0xF7 => {
+            let cart_offset = (u32::from(bank) - 0x80) * BANK_SIZE + offset;
+            ((cart_offset as usize) < cart_ram_size)
+                .then(|| (CheatTarget::Ram, WORK_RAM_SIZE as u32 + cart_offset))
+        }
+        0x00..=0x7F => Some((CheatTarget::Rom, u32::from(bank) * BANK_SIZE + offset)),
+        _ => None,
+    }
+}
+
+/// Translate a numeric cheat address to a work RAM ++ cart RAM index:
+/// `F8xxxx` / `80xxxx-F7xxxx` bank form, a direct index, or a CPU address
+/// in `$2000-$3FFF`.
+pub fn ram_address(raw: u32, cart_ram_size: usize) -> Option<u32> {
+    if raw > 0xFFFF {
+        match bank_location((raw >> 16) as u8, raw, cart_ram_size)? {
+            (CheatTarget::Ram, index) if raw < 0x100_0000 => Some(index),
+            _ => None,
+        }
+    } else if (raw as usize) < WORK_RAM_SIZE + cart_ram_size {
+        Some(raw)
+    } else if (0x2000..0x4000).contains(&raw) && ((raw - 0x2000) as usize) < WORK_RAM_SIZE {
+        Some(raw - 0x2000)
+    } else {
+        None
+    }
+}
+
+/// Trim `text` and drop one leading `$`, `0x` or `0X`.
+fn strip_hex_prefix(text: &str) -> &str {
+    let text = text.trim();
+    ["$", "0x", "0X"]
+        .iter()
+        .find_map(|prefix| text.strip_prefix(prefix))
+        .unwrap_or(text)
+}
+
+fn parse_hex_field(text: &str) -> Option<u32> {
+    let digits = strip_hex_prefix(text);
+    if digits.is_empty() || digits.len() > 8 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// Parse one `LOCATION:VALUE` code (see the module docs) into an enabled
+/// entry labelled with the code itself.
+pub fn parse_raw_code(code: &str, cart_ram_size: usize) -> Result<CheatEntry, String> {
+    let code = code.trim();
+    let (location, value) = code
+        .split_once(':')
+        .ok_or_else(|| format!("'{code}': expected LOCATION:VALUE"))?;
+    let bad = || format!("'{code}': not a hex code");
+    let location_digits = strip_hex_prefix(location);
+    let value_digits = strip_hex_prefix(value);
+    let raw = parse_hex_field(location_digits).ok_or_else(bad)?;
+    let width = match value_digits.len() {
+        1 | 2 => CheatWidth::U8,
+        4 => CheatWidth::U16Le,
+        6 => CheatWidth::U24Le,
+        8 => CheatWidth::U32Le,
+        _ => return Err(format!("'{code}': value must be 2, 4, 6 or 8 hex digits")),
+    };
+    let value = parse_hex_field(value_digits).ok_or_else(bad)?;
+
+    let (target, address) = if location_digits.len() <= 4 {
+        let index = ram_address(raw, cart_ram_size)
+            .ok_or_else(|| format!("'{code}': address outside work/cart RAM"))?;
+        (CheatTarget::Ram, index)
+    } else {
+        let (bank, offset) = if raw < PHYSICAL_LIMIT {
+            ((raw / BANK_SIZE) as u8, raw)
+        } else if raw <= 0xFF_FFFF {
+            ((raw >> 16) as u8, raw)
+        } else {
+            return Err(format!("'{code}': location has more than 6 digits"));
+        };
+        bank_location(bank, offset, cart_ram_size)
+            .ok_or_else(|| format!("'{code}': bank ${bank:02X} is not work RAM, cart RAM or ROM"))?
+    };
+    Ok(CheatEntry::new(address, value, code.to_string())
+        .with_width(width)
+        .with_target(target))
+}
+
+/// Parse a plain code list: one or more `+`-joined codes per line, an
+/// optional label after whitespace, `#`/`;` comments.
+pub fn parse_raw_codes(text: &str, cart_ram_size: usize) -> Result<Vec<CheatEntry>, String> {
+    let mut entries = Vec::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.split(['#', ';']).next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (codes, label) = match line.split_once(char::is_whitespace) {
+            Some((codes, label)) => (codes, Some(label.trim())),
+            None => (line, None),
+        };
+        for code in codes.split('+') {
+            let mut entry = parse_raw_code(code, cart_ram_size)
+                .map_err(|err| format!("line {}: {err}", index + 1))?;
+            if let Some(label) = label {
+                entry.label = label.to_string();
+            }
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Parse a libretro `.cht` file. Code cheats (`cheatN_code`, `+`-joined
+/// raw codes) and RetroArch cheat-search entries (`cheatN_handler = 1`
+/// with `cheatN_address` into work RAM) are supported; the latter only
+/// with `cheatN_cheat_type = 1` (set value).
+pub fn parse_cht(text: &str, cart_ram_size: usize) -> Result<Vec<CheatEntry>, String> {
+    let mut fields = HashMap::new();
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", index + 1))?;
+        fields.insert(key.trim(), value.trim().trim_matches('"'));
+    }
+    let count: usize = fields
+        .get("cheats")
+        .ok_or("missing 'cheats = N'")?
+        .parse()
+        .map_err(|_| "invalid cheat count".to_string())?;
+
+    let mut entries = Vec::new();
+    for n in 0..count {
+        let field = |name: &str| fields.get(format!("cheat{n}_{name}").as_str()).copied();
+        let context = |err: String| format!("cheat {n}: {err}");
+        let label = field("desc").unwrap_or_default().to_string();
+        let enabled = field("enable") == Some("true");
+        let code = field("code").unwrap_or_default().trim();
+
+        if field("handler") == Some("1") || (code.is_empty() && field("address").is_some()) {
+            let number = |name: &str| -> Result<u32, String> {
+                field(name)
+                    .ok_or_else(|| format!("missing {name}"))?
+                    .parse()
+                    .map_err(|_| format!("invalid {name}"))
+            };
+            if field("cheat_type").is_some_and(|t| t != "1") {
+                return Err(context("only cheat_type 1 (set value) is supported".into()));
+            }
+            let big_endian = field("big_endian") == Some("true");
+            let size = match field("memory_search_size") {
+                Some(_) => number("memory_search_size").map_err(context)?,
+                None => 3,
+            };
+            let width = match (size, big_endian) {
+                (3, _) => CheatWidth::U8,
+                (4, false) => CheatWidth::U16Le,
+                (4, true) => CheatWidth::U16Be,
+                (5, false) => CheatWidth::U32Le,
+                (5, true) => CheatWidth::U32Be,
+                (size, _) => {
+                    return Err(context(format!("unsupported memory_search_size {size}")));
+                }
+            };
+            let address = number("address").map_err(context)?;
+            if address as usize + width.byte_len() > WORK_RAM_SIZE + cart_ram_size {
+                return Err(context(format!("address {address:#X} outside RAM")));
+            }
+            let mut entry = CheatEntry::new(address, number("value").map_err(context)?, label)
+                .with_width(width);
+            entry.enabled = enabled;
+            entries.push(entry);
+            continue;
+        }
+
+        for part in code.split('+').filter(|part| !part.trim().is_empty()) {
+            let mut entry = parse_raw_code(part, cart_ram_size).map_err(context)?;
+            entry.label = label.clone();
+            entry.enabled = enabled;
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+impl CheatManager {
+    /// Append cheats from a libretro `.cht` file or a raw code list (any
+    /// other extension). Returns how many entries were added.
+    pub fn import_file(
+        &mut self,
+        path: &Path,
+        cart_ram_size: usize,
+    ) -> Result<usize, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let is_cht = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("cht"));
+        let entries = if is_cht {
+            parse_cht(&text, cart_ram_size)?
+        } else {
+            parse_raw_codes(&text, cart_ram_size)?
+        };
+        let count = entries.len();
+        self.entries.extend(entries);
+        Ok(count)
+    }
+}
@@ -0,0 +1,605 @@
+mod import;
+
+#[cfg(test)]
+mod tests;
+
+pub use import::{parse_cht, parse_raw_code, parse_raw_codes, ram_address};
+
+pub const WORK_RAM_SIZE: usize = 0x2000;
+
+#[derive(Clone)]
+pub struct RamSnapshot {
+    data: Vec<u8>,
+}
+
+impl RamSnapshot {
+    pub fn capture(ram: &[u8]) -> Self {
+        Self { data: ram.to_vec() }
+    }
+
+    pub fn get(&self, addr: u32) -> u8 {
+        self.data.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchFilter {
+    Equal(i64),
+    NotEqual(i64),
+    GreaterThan(i64),
+    LessThan(i64),
+    /// Inclusive range `lo..=hi`.
+    InRange(i64, i64),
+    Increased,
+    Decreased,
+    Changed,
+    Unchanged,
+    IncreasedBy(i64),
+    DecreasedBy(i64),
+    /// Search for a decimal value stored as little-endian BCD (1 digit per byte).
+    /// E.g. BcdEqual(13) matches consecutive bytes [0x03, 0x01].
+    /// Returns the address of the lowest digit (ones place).
+    BcdEqual(u16),
+}
+
+impl SearchFilter {
+    pub fn needs_snapshot(&self) -> bool {
+        matches!(
+            self,
+            Self::Increased
+                | Self::Decreased
+                | Self::Changed
+                | Self::Unchanged
+                | Self::IncreasedBy(_)
+                | Self::DecreasedBy(_)
+        )
+    }
+
+    /// Convert a decimal value to BCD digit array (ones first).
+    /// E.g. 130 → [0, 3, 1], 13 → [3, 1], 0 → [0].
+    pub fn bcd_digits(mut value: u16) -> Vec<u8> {
+        if value == 0 {
+            return vec![0];
+        }
+        let mut digits = Vec::new();
+        while value > 0 {
+            digits.push((value % 10) as u8);
+            value /= 10;
+        }
+        digits
+    }
+}
+
+/// How the bytes of a search value are interpreted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValueEncoding {
+    #[default]
+    Unsigned,
+    /// Two's complement.
+    Signed,
+    /// Two decimal digits per byte, high digit in the high nibble; the
+    /// width's byte order decides which byte holds the ones and tens.
+    PackedBcd,
+}
+
+impl ValueEncoding {
+    pub const ALL: [ValueEncoding; 3] = [Self::Unsigned, Self::Signed, Self::PackedBcd];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Unsigned => "Unsigned",
+            Self::Signed => "Signed",
+            Self::PackedBcd => "Packed BCD",
+        }
+    }
+
+    /// Value of the `width`-sized `raw` bits, or `None` for a byte that is
+    /// not valid packed BCD.
+    pub fn decode(self, width: CheatWidth, raw: u32) -> Option<i64> {
+        let bits = 8 * width.byte_len() as u32;
+        match self {
+            Self::Unsigned => Some(i64::from(raw)),
+            Self::Signed => Some(i64::from(raw) << (64 - bits) >> (64 - bits)),
+            Self::PackedBcd => (0..bits / 4).rev().try_fold(0i64, |value, nibble| {
+                let digit = i64::from((raw >> (4 * nibble)) & 0xF);
+                (digit <= 9).then_some(value * 10 + digit)
+            }),
+        }
+    }
+}
+
+/// Value at `addr` in `ram`, if it fits and decodes.
+fn decode_value(ram: &[u8], addr: u32, width: CheatWidth, encoding: ValueEncoding) -> Option<i64> {
+    let start = addr as usize;
+    let bytes = ram.get(start..start + width.byte_len())?;
+    encoding.decode(width, width.read(|i| bytes[i]))
+}
+
+pub struct CheatSearch {
+    snapshot: Option<RamSnapshot>,
+    candidates: Vec<u32>,
+    ram_size: usize,
+    width: CheatWidth,
+    encoding: ValueEncoding,
+}
+
+impl CheatSearch {
+    pub fn new() -> Self {
+        Self {
+            snapshot: None,
+            candidates: (0..WORK_RAM_SIZE as u32).collect(),
+            ram_size: WORK_RAM_SIZE,
+            width: CheatWidth::U8,
+            encoding: ValueEncoding::Unsigned,
+        }
+    }
+
+    /// Re-initialize candidates for the given RAM size.
+    pub fn resize(&mut self, size: usize) {
+        if size != self.ram_size {
+            self.ram_size = size;
+            self.snapshot = None;
+            self.candidates = (0..size as u32).collect();
+        }
+    }
+
+    /// Compare `width`-sized values decoded with `encoding` from now on.
+    /// Candidates are kept, so a search can be narrowed under a new view.
+    pub fn set_value_type(&mut self, width: CheatWidth, encoding: ValueEncoding) {
+        self.width = width;
+        self.encoding = encoding;
+    }
+
+    pub fn width(&self) -> CheatWidth {
+        self.width
+    }
+
+    pub fn encoding(&self) -> ValueEncoding {
+        self.encoding
+    }
+
+    /// Value starting at `addr` under the current width and encoding.
+    pub fn value_at(&self, ram: &[u8], addr: u32) -> Option<i64> {
+        decode_value(ram, addr, self.width, self.encoding)
+    }
+
+    /// Value starting at `addr` in the last snapshot.
+    pub fn previous_value(&self, addr: u32) -> Option<i64> {
+        let snap = self.snapshot.as_ref()?;
+        decode_value(&snap.data, addr, self.width, self.encoding)
+    }
+
+    pub fn snapshot(&mut self, ram: &[u8]) {
+        if ram.len() != self.ram_size {
+            self.resize(ram.len());
+        }
+        self.snapshot = Some(RamSnapshot::capture(ram));
+    }
+
+    pub fn has_snapshot(&self) -> bool {
+        self.snapshot.is_some()
+    }
+
+    pub fn previous_snapshot(&self) -> Option<&RamSnapshot> {
+        self.snapshot.as_ref()
+    }
+
+    pub fn apply_filter(&mut self, filter: SearchFilter, current_ram: &[u8]) {
+        // BCD search: replace candidates with matching start addresses
+        if let SearchFilter::BcdEqual(value) = filter {
+            let digits = SearchFilter::bcd_digits(value);
+            let num_digits = digits.len();
+            let mut matches = Vec::new();
+            let ram_len = current_ram.len();
+            // Check every address in current candidates
+            let candidates = std::mem::take(&mut self.candidates);
+            for &addr in &candidates {
+                let a = addr as usize;
+                if a + num_digits > ram_len {
+                    continue;
+                }
+                let ok = digits
+                    .iter()
+                    .enumerate()
+                    .all(|(i, &d)| current_ram[a + i] == d);
+                if ok {
+                    matches.push(addr);
+                }
+            }
+            self.candidates = matches;
+            self.snapshot = Some(RamSnapshot::capture(current_ram));
+            return;
+        }
+
+        if filter.needs_snapshot() && self.snapshot.is_none() {
+            return;
+        }
+        let (width, encoding) = (self.width, self.encoding);
+        // Binary values wrap at the width; packed BCD counters are compared
+        // as plain decimal differences.
+        let modulus = 1i64 << (8 * width.byte_len());
+        let same_step = |delta: i64, step: i64| match encoding {
+            ValueEncoding::PackedBcd => delta == step,
+            _ => delta.rem_euclid(modulus) == step.rem_euclid(modulus),
+        };
+        let snap = self.snapshot.take();
+        self.candidates.retain(|&addr| {
+            let Some(cur) = decode_value(current_ram, addr, width, encoding) else {
+                return false;
+            };
+            let prev = snap
+                .as_ref()
+                .and_then(|s| decode_value(&s.data, addr, width, encoding));
+            match (filter, prev) {
+                (SearchFilter::Equal(v), _) => cur == v,
+                (SearchFilter::NotEqual(v), _) => cur != v,
+                (SearchFilter::GreaterThan(v), _) => cur > v,
+                (SearchFilter::LessThan(v), _) => cur < v,
+                (SearchFilter::InRange(lo, hi), _) => (lo..=hi).contains(&cur),
+                (SearchFilter::Increased, Some(prev)) => cur > prev,
+                (SearchFilter::Decreased, Some(prev)) => cur < prev,
+                (SearchFilter::Changed, Some(prev)) => cur != prev,
+                (SearchFilter::Unchanged, Some(prev)) => cur == prev,
+                (SearchFilter::IncreasedBy(d), Some(prev)) => same_step(cur - prev, d),
+                (SearchFilter::DecreasedBy(d), Some(prev)) => same_step(prev - cur, d),
+                _ => false,
+            }
+        });
+        self.snapshot = Some(RamSnapshot::capture(current_ram));
+    }
+
+    pub fn reset(&mut self) {
+        self.snapshot = None;
+        self.candidates = (0..self.ram_size as u32).collect();
+    }
+
+    pub fn candidates(&self) -> &[u32] {
+        &self.candidates
+    }
+
+    pub fn candidate_count(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn ram_size(&self) -> usize {
+        self.ram_size
+    }
+}
+
+/// Size and byte order of a cheat value. Multi-byte values occupy
+/// consecutive addresses starting at `CheatEntry::address`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum CheatWidth {
+    #[default]
+    U8,
+    U16Le,
+    U16Be,
+    U24Le,
+    U24Be,
+    U32Le,
+    U32Be,
+}
+
+impl CheatWidth {
+    pub const ALL: [CheatWidth; 7] = [
+        Self::U8,
+        Self::U16Le,
+        Self::U16Be,
+        Self::U24Le,
+        Self::U24Be,
+        Self::U32Le,
+        Self::U32Be,
+    ];
+
+    pub fn byte_len(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16Le | Self::U16Be => 2,
+            Self::U24Le | Self::U24Be => 3,
+            Self::U32Le | Self::U32Be => 4,
+        }
+    }
+
+    pub fn mask(self) -> u32 {
+        match self.byte_len() {
+            4 => u32::MAX,
+            n => (1 << (8 * n)) - 1,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::U8 => "8",
+            Self::U16Le => "16LE",
+            Self::U16Be => "16BE",
+            Self::U24Le => "24LE",
+            Self::U24Be => "24BE",
+            Self::U32Le => "32LE",
+            Self::U32Be => "32BE",
+        }
+    }
+
+    fn shift(self, index: usize) -> usize {
+        match self {
+            Self::U8 | Self::U16Le | Self::U24Le | Self::U32Le => 8 * index,
+            Self::U16Be | Self::U24Be | Self::U32Be => 8 * (self.byte_len() - 1 - index),
+        }
+    }
+
+    /// Byte of `value` stored at `address + index`.
+    pub fn byte(self, value: u32, index: usize) -> u8 {
+        (value >> self.shift(index)) as u8
+    }
+
+    /// Assemble a value from the bytes at `address + 0 .. address + byte_len()`.
+    pub fn read(self, byte_at: impl Fn(usize) -> u8) -> u32 {
+        (0..self.byte_len()).fold(0, |value, index| {
+            value | u32::from(byte_at(index)) << self.shift(index)
+        })
+    }
+}
+
+/// Where a cheat writes. RAM addresses index work RAM followed by cart RAM
+/// (the buffer `CheatSearch` works on); ROM addresses are byte offsets into
+/// the HuCard image with any 512-byte header stripped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum CheatTarget {
+    #[default]
+    Ram,
+    Rom,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CheatEntry {
+    pub address: u32,
+    pub value: u32,
+    pub enabled: bool,
+    pub label: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub width: CheatWidth,
+    /// Only write while the current value equals this (RAM), or only
+    /// substitute bytes that originally hold it (ROM).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub compare: Option<u32>,
+    /// Write once, then disable the entry. Ignored for ROM cheats.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub one_shot: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub target: CheatTarget,
+}
+
+impl CheatEntry {
+    /// An enabled, unconditional single-byte RAM cheat.
+    pub fn new(address: u32, value: u32, label: String) -> Self {
+        Self {
+            address,
+            value,
+            enabled: true,
+            label,
+            width: CheatWidth::U8,
+            compare: None,
+            one_shot: false,
+            target: CheatTarget::Ram,
+        }
+    }
+
+    pub fn with_width(mut self, width: CheatWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_compare(mut self, compare: u32) -> Self {
+        self.compare = Some(compare);
+        self
+    }
+
+    pub fn with_one_shot(mut self) -> Self {
+        self.one_shot = true;
+        self
+    }
+
+    pub fn with_target(mut self, target: CheatTarget) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+/// One substituted ROM byte, produced from ROM-target cheats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomPatch {
+    pub offset: usize,
+    pub value: u8,
+    /// Substitute only when the original byte equals this.
+    pub compare: Option<u8>,
+}
+
+/// ROM patches consulted on every ROM read, sorted by offset. Not part of
+/// save states: cheats belong to the front-end, which re-applies them.
+#[derive(Clone, Debug, Default)]
+pub struct RomPatchTable {
+    patches: Vec<RomPatch>,
+}
+
+impl RomPatchTable {
+    /// Replace the table; returns false when `patches` is already active.
+    pub fn set(&mut self, mut patches: Vec<RomPatch>) -> bool {
+        patches.sort_by_key(|patch| patch.offset);
+        if patches == self.patches {
+            return false;
+        }
+        self.patches = patches;
+        true
+    }
+
+    pub fn patches(&self) -> &[RomPatch] {
+        &self.patches
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    /// The byte the CPU sees at `offset` when the image holds `original`.
+    pub fn substitute(&self, offset: usize, original: u8) -> u8 {
+        let Ok(index) = self
+            .patches
+            .binary_search_by_key(&offset, |patch| patch.offset)
+        else {
+            return original;
+        };
+        let patch = self.patches[index];
+        match patch.compare {
+            Some(expected) if expected != original => original,
+            _ => patch.value,
+        }
+    }
+}
+
+impl bincode::Encode for RomPatchTable {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for RomPatchTable {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for RomPatchTable {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+pub struct CheatManager {
+    pub entries: Vec<CheatEntry>,
+}
+
+impl CheatManager {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, address: u32, value: u8, label: String) {
+        self.entries
+            .push(CheatEntry::new(address, value.into(), label));
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+        }
+    }
+
+    /// Apply cheats to a combined RAM buffer (work_ram ++ cart_ram).
+    pub fn apply(&mut self, ram: &mut [u8]) {
+        self.apply_split(ram, &mut []);
+    }
+
+    /// Apply RAM cheats with work RAM and cart RAM held separately; addresses
+    /// past the end of `work_ram` land in `cart_ram`. One-shot entries that
+    /// fire are disabled.
+    pub fn apply_split(&mut self, work_ram: &mut [u8], cart_ram: &mut [u8]) {
+        let wram_len = work_ram.len();
+        let total = wram_len + cart_ram.len();
+        let byte_at = |work_ram: &[u8], cart_ram: &[u8], addr: usize| {
+            if addr < wram_len {
+                work_ram[addr]
+            } else {
+                cart_ram[addr - wram_len]
+            }
+        };
+        for entry in &mut self.entries {
+            if !entry.enabled || entry.target != CheatTarget::Ram {
+                continue;
+            }
+            let start = entry.address as usize;
+            let len = entry.width.byte_len();
+            if start + len > total {
+                continue;
+            }
+            if let Some(expected) = entry.compare {
+                let current = entry.width.read(|i| byte_at(work_ram, cart_ram, start + i));
+                if current != expected & entry.width.mask() {
+                    continue;
+                }
+            }
+            for i in 0..len {
+                let addr = start + i;
+                let value = entry.width.byte(entry.value, i);
+                if addr < wram_len {
+                    work_ram[addr] = value;
+                } else {
+                    cart_ram[addr - wram_len] = value;
+                }
+            }
+            if entry.one_shot {
+                entry.enabled = false;
+            }
+        }
+    }
+
+    /// Byte substitutions for the enabled ROM cheats.
+    pub fn rom_patches(&self) -> Vec<RomPatch> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled && entry.target == CheatTarget::Rom)
+            .flat_map(|entry| {
+                (0..entry.width.byte_len()).map(move |i| RomPatch {
+                    offset: entry.address as usize + i,
+                    value: entry.width.byte(entry.value, i),
+                    compare: entry.compare.map(|c| entry.width.byte(c, i)),
+                })
+            })
+            .collect()
+    }
+
+    /// Save cheat entries to a JSON file.
+    #[cfg(feature = "serde")]
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&self.entries).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Load cheat entries from a JSON file.
+    #[cfg(feature = "serde")]
+    pub fn load_from_file(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let entries: Vec<CheatEntry> = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+        self.entries = entries;
+        Ok(())
+    }
+}
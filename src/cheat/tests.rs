@@ -0,0 +1,429 @@
+use super::*;
+
+#[test]
+fn test_snapshot_and_filter_equal() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x10] = 42;
+    ram[0x20] = 42;
+    ram[0x30] = 99;
+
+    let mut search = CheatSearch::new();
+    assert_eq!(search.candidate_count(), WORK_RAM_SIZE);
+
+    search.apply_filter(SearchFilter::Equal(42), &ram);
+    assert_eq!(search.candidate_count(), 2);
+    assert!(search.candidates().contains(&0x10));
+    assert!(search.candidates().contains(&0x20));
+}
+
+#[test]
+fn test_snapshot_and_filter_increased() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x10] = 5;
+    ram[0x20] = 10;
+    ram[0x30] = 3;
+
+    let mut search = CheatSearch::new();
+    search.snapshot(&ram);
+
+    ram[0x10] = 8; // increased
+    ram[0x20] = 10; // unchanged
+    ram[0x30] = 1; // decreased
+
+    search.apply_filter(SearchFilter::Increased, &ram);
+    assert_eq!(search.candidate_count(), 1);
+    assert_eq!(search.candidates()[0], 0x10);
+}
+
+#[test]
+fn test_snapshot_and_filter_decreased() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x10] = 10;
+    ram[0x20] = 5;
+
+    let mut search = CheatSearch::new();
+    search.snapshot(&ram);
+
+    ram[0x10] = 7;
+    ram[0x20] = 5;
+
+    search.apply_filter(SearchFilter::Decreased, &ram);
+    assert_eq!(search.candidate_count(), 1);
+    assert_eq!(search.candidates()[0], 0x10);
+}
+
+#[test]
+fn test_filter_unchanged() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x10] = 5;
+    ram[0x20] = 10;
+
+    let mut search = CheatSearch::new();
+    search.snapshot(&ram);
+
+    ram[0x10] = 5;
+    ram[0x20] = 99;
+
+    search.apply_filter(SearchFilter::Unchanged, &ram);
+    let count = search.candidate_count();
+    assert!(search.candidates().contains(&0x10));
+    assert!(!search.candidates().contains(&0x20));
+    assert_eq!(count, WORK_RAM_SIZE - 1);
+}
+
+#[test]
+fn test_filter_increased_by() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x10] = 5;
+    ram[0x20] = 10;
+
+    let mut search = CheatSearch::new();
+    search.snapshot(&ram);
+
+    ram[0x10] = 8;
+    ram[0x20] = 13;
+
+    search.apply_filter(SearchFilter::IncreasedBy(3), &ram);
+    assert_eq!(search.candidate_count(), 2);
+}
+
+#[test]
+fn test_reset() {
+    let ram = vec![0u8; WORK_RAM_SIZE];
+    let mut search = CheatSearch::new();
+    search.snapshot(&ram);
+    search.apply_filter(SearchFilter::Equal(99), &ram);
+    assert_eq!(search.candidate_count(), 0);
+
+    search.reset();
+    assert_eq!(search.candidate_count(), WORK_RAM_SIZE);
+    assert!(!search.has_snapshot());
+}
+
+#[test]
+fn test_search_with_extended_ram() {
+    // Simulate work_ram (8KB) + cart_ram (2KB)
+    let size = WORK_RAM_SIZE + 0x800;
+    let mut ram = vec![0u8; size];
+    ram[0x2100] = 42; // In cart_ram region
+
+    let mut search = CheatSearch::new();
+    search.resize(size);
+    assert_eq!(search.candidate_count(), size);
+
+    search.apply_filter(SearchFilter::Equal(42), &ram);
+    assert_eq!(search.candidate_count(), 1);
+    assert_eq!(search.candidates()[0], 0x2100);
+}
+
+#[test]
+fn test_cheat_manager_apply() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    let mut mgr = CheatManager::new();
+    mgr.add(0x100, 99, "Lives".into());
+    mgr.add(0x200, 50, "Health".into());
+
+    mgr.apply(&mut ram);
+    assert_eq!(ram[0x100], 99);
+    assert_eq!(ram[0x200], 50);
+}
+
+#[test]
+fn test_cheat_manager_disabled() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    let mut mgr = CheatManager::new();
+    mgr.add(0x100, 99, "Lives".into());
+    mgr.entries[0].enabled = false;
+
+    mgr.apply(&mut ram);
+    assert_eq!(ram[0x100], 0);
+}
+
+#[test]
+fn test_cheat_manager_remove() {
+    let mut mgr = CheatManager::new();
+    mgr.add(0x100, 99, "Lives".into());
+    mgr.add(0x200, 50, "Health".into());
+    assert_eq!(mgr.entries.len(), 2);
+
+    mgr.remove(0);
+    assert_eq!(mgr.entries.len(), 1);
+    assert_eq!(mgr.entries[0].address, 0x200);
+}
+
+#[test]
+fn test_multi_byte_cheats_write_in_byte_order() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    let mut mgr = CheatManager::new();
+    mgr.entries
+        .push(CheatEntry::new(0x10, 0x1234, "Gold".into()).with_width(CheatWidth::U16Le));
+    mgr.entries
+        .push(CheatEntry::new(0x20, 0x1234_5678, "Score".into()).with_width(CheatWidth::U32Be));
+
+    mgr.apply(&mut ram);
+    assert_eq!(&ram[0x10..0x12], &[0x34, 0x12]);
+    assert_eq!(&ram[0x20..0x24], &[0x12, 0x34, 0x56, 0x78]);
+    assert_eq!(CheatWidth::U16Be.read(|i| ram[0x20 + i]), 0x1234);
+}
+
+#[test]
+fn test_compare_and_one_shot_cheats() {
+    let mut work = vec![0u8; WORK_RAM_SIZE];
+    let mut cart = vec![0u8; 0x800];
+    let mut mgr = CheatManager::new();
+    mgr.entries
+        .push(CheatEntry::new(0x40, 9, "Lives".into()).with_compare(1));
+    mgr.entries.push(
+        CheatEntry::new(WORK_RAM_SIZE as u32 + 0x10, 0x0300, "Saved gold".into())
+            .with_width(CheatWidth::U16Be)
+            .with_one_shot(),
+    );
+
+    mgr.apply_split(&mut work, &mut cart);
+    assert_eq!(work[0x40], 0, "condition not met yet");
+    assert_eq!(&cart[0x10..0x12], &[0x03, 0x00]);
+    assert!(!mgr.entries[1].enabled);
+
+    work[0x40] = 1;
+    cart[0x10] = 0;
+    mgr.apply_split(&mut work, &mut cart);
+    assert_eq!(work[0x40], 9);
+    assert_eq!(cart[0x10], 0, "one-shot cheat fired only once");
+}
+
+#[test]
+fn test_rom_cheats_become_patches_and_skip_ram() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    let mut mgr = CheatManager::new();
+    mgr.entries.push(
+        CheatEntry::new(0x1FFE, 0xBEEF, "Skip check".into())
+            .with_width(CheatWidth::U16Le)
+            .with_compare(0x1234)
+            .with_target(CheatTarget::Rom),
+    );
+
+    mgr.apply(&mut ram);
+    assert!(ram.iter().all(|&b| b == 0));
+    assert_eq!(
+        mgr.rom_patches(),
+        vec![
+            RomPatch {
+                offset: 0x1FFE,
+                value: 0xEF,
+                compare: Some(0x34),
+            },
+            RomPatch {
+                offset: 0x1FFF,
+                value: 0xBE,
+                compare: Some(0x12),
+            },
+        ]
+    );
+
+    let mut table = RomPatchTable::default();
+    assert!(table.set(mgr.rom_patches()));
+    assert!(!table.set(mgr.rom_patches()));
+    assert_eq!(table.substitute(0x1FFE, 0x34), 0xEF);
+    assert_eq!(table.substitute(0x1FFE, 0x35), 0x35);
+    assert_eq!(table.substitute(0x2000, 0x77), 0x77);
+}
+
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+#[test]
+fn test_legacy_json_entries_load_as_byte_ram_cheats() {
+    let json = r#"[{"address": 68, "value": 3, "enabled": true, "label": "LIFE"}]"#;
+    let entries: Vec<CheatEntry> = serde_json::from_str(json).unwrap();
+    assert_eq!(entries, vec![CheatEntry::new(68, 3, "LIFE".into())]);
+}
+
+#[test]
+fn test_raw_codes_translate_bank_and_physical_addresses() {
+    let cart = 0x2000;
+    let by_bank = parse_raw_code("F80044:03", cart).unwrap();
+    let by_cpu_addr = parse_raw_code("F82044:03", cart).unwrap();
+    let physical = parse_raw_code("1F0044:03", cart).unwrap();
+    assert_eq!(by_bank.address, 0x44);
+    assert_eq!(by_cpu_addr.address, 0x44);
+    assert_eq!(physical.address, 0x44);
+    assert_eq!(physical.target, CheatTarget::Ram);
+
+    let cart_code = parse_raw_code("$800010:09", cart).unwrap();
+    assert_eq!(cart_code.address, WORK_RAM_SIZE as u32 + 0x10);
+    assert!(parse_raw_code("810010:09", cart).is_err(), "past cart RAM");
+
+    let rom = parse_raw_code("01E123:EA", cart).unwrap();
+    assert_eq!(rom.target, CheatTarget::Rom);
+    assert_eq!(rom.address, 0x01E123);
+
+    let word = parse_raw_code("0100:E803", 0).unwrap();
+    assert_eq!((word.address, word.value), (0x100, 0xE803));
+    assert_eq!(word.width, CheatWidth::U16Le);
+    assert!(parse_raw_code("F80044", 0).is_err());
+    assert!(parse_raw_code("FF0000:01", 0).is_err());
+}
+
+#[test]
+fn test_raw_codes_accept_0x_prefixes() {
+    let plain = parse_raw_code("2044:03", 0).unwrap();
+    let prefixed = parse_raw_code("0x2044:0x03", 0).unwrap();
+    assert_eq!(prefixed.target, CheatTarget::Ram);
+    assert_eq!(prefixed.width, CheatWidth::U8);
+    assert_eq!(
+        (prefixed.address, prefixed.value),
+        (plain.address, plain.value)
+    );
+    assert_eq!(parse_raw_code("0X1F0044:03", 0).unwrap().address, 0x44);
+}
+
+#[test]
+fn test_raw_code_lists_carry_labels() {
+    let entries = parse_raw_codes(
+        "# Momotarou\n\
+         F80044:09+F80045:09  Max money\n\
+         1F0100:01\n",
+        0,
+    )
+    .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[1].label, "Max money");
+    assert_eq!(entries[2].label, "1F0100:01");
+
+    let err = parse_raw_codes("F80044:03\nbogus\n", 0).unwrap_err();
+    assert!(err.starts_with("line 2"), "{err}");
+}
+
+#[test]
+fn test_libretro_cht_code_and_address_cheats() {
+    let cht = r#"cheats = 3
+
+cheat0_desc = "Infinite Lives"
+cheat0_code = "F80044:03+F80045:03"
+cheat0_enable = true
+
+cheat1_desc = "Gold"
+cheat1_handler = "1"
+cheat1_address = "256"
+cheat1_value = "999"
+cheat1_memory_search_size = "4"
+cheat1_big_endian = "true"
+cheat1_cheat_type = "1"
+cheat1_enable = false
+
+cheat2_desc = "Skip intro"
+cheat2_code = "00E010:80"
+"#;
+    let entries = parse_cht(cht, 0).unwrap();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].label, "Infinite Lives");
+    assert!(entries[0].enabled && entries[1].enabled);
+    assert_eq!(entries[1].address, 0x45);
+    assert_eq!(
+        entries[2],
+        CheatEntry {
+            enabled: false,
+            ..CheatEntry::new(256, 999, "Gold".into()).with_width(CheatWidth::U16Be)
+        }
+    );
+    assert_eq!(entries[3].target, CheatTarget::Rom);
+    assert!(!entries[3].enabled);
+
+    let err = parse_cht(
+        "cheats = 1\ncheat0_address = 16\ncheat0_value = 1\ncheat0_cheat_type = 2\n",
+        0,
+    )
+    .unwrap_err();
+    assert!(err.starts_with("cheat 0"), "{err}");
+}
+
+#[test]
+fn test_word_search_in_both_byte_orders() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x10..0x12].copy_from_slice(&1000u16.to_le_bytes());
+    ram[0x20..0x22].copy_from_slice(&1000u16.to_be_bytes());
+
+    let mut search = CheatSearch::new();
+    search.set_value_type(CheatWidth::U16Le, ValueEncoding::Unsigned);
+    search.apply_filter(SearchFilter::Equal(1000), &ram);
+    assert_eq!(search.candidates(), &[0x10]);
+
+    search.reset();
+    search.set_value_type(CheatWidth::U16Be, ValueEncoding::Unsigned);
+    search.apply_filter(SearchFilter::Equal(1000), &ram);
+    assert_eq!(search.candidates(), &[0x20]);
+
+    ram[0x20..0x22].copy_from_slice(&1250u16.to_be_bytes());
+    search.apply_filter(SearchFilter::IncreasedBy(250), &ram);
+    assert_eq!(search.candidates(), &[0x20]);
+    assert_eq!(search.value_at(&ram, 0x20), Some(1250));
+}
+
+#[test]
+fn test_signed_and_range_filters() {
+    let mut ram = vec![0u8; WORK_RAM_SIZE];
+    ram[0x30] = 0xFE; // -2
+    ram[0x31] = 0x05;
+
+    let mut search = CheatSearch::new();
+    search.set_value_type(CheatWidth::U8, ValueEncoding::Signed);
+    search.apply_filter(SearchFilter::LessThan(0), &ram);
+    assert_eq!(search.candidates(), &[0x30]);
+
+    ram[0x30] = 0x01; // -2 -> 1 wraps through zero
+    search.apply_filter(SearchFilter::IncreasedBy(3), &ram);
+    assert_eq!(search.candidates(), &[0x30]);
+
+    search.reset();
+    search.set_value_type(CheatWidth::U24Le, ValueEncoding::Signed);
+    ram[0x40..0x43].copy_from_slice(&[0x00, 0x00, 0x80]);
+    search.apply_filter(SearchFilter::InRange(-0x80_0000, -0x7F_FFFF), &ram);
+    assert_eq!(search.candidates(), &[0x40]);
+}
+
+#[test]
+fn test_packed_bcd_search_spans_cart_ram() {
+    let size = WORK_RAM_SIZE + 0x800;
+    let mut ram = vec![0u8; size];
+    // Score 012345 stored ones-first: [0x45, 0x23, 0x01].
+    ram[0x2100..0x2103].copy_from_slice(&[0x45, 0x23, 0x01]);
+    ram[0x2200] = 0xAB; // not BCD
+
+    let mut search = CheatSearch::new();
+    search.resize(size);
+    search.set_value_type(CheatWidth::U24Le, ValueEncoding::PackedBcd);
+    search.apply_filter(SearchFilter::InRange(12_000, 13_000), &ram);
+    assert_eq!(search.candidates(), &[0x2100]);
+    assert_eq!(search.value_at(&ram, 0x2200), None);
+
+    ram[0x2100] = 0x95;
+    search.apply_filter(SearchFilter::IncreasedBy(50), &ram);
+    assert_eq!(search.candidates(), &[0x2100]);
+    assert_eq!(search.previous_value(0x2100), Some(12_395));
+    assert_eq!(
+        search.value_at(&ram, size as u32 - 2),
+        None,
+        "value would run past the end of RAM"
+    );
+}
+
+#[test]
+fn test_cheat_width_all_lists_every_variant() {
+    // The match is exhaustive, so a new variant fails to build here until
+    // it is added to ALL as well.
+    let index = |width: CheatWidth| match width {
+        CheatWidth::U8 => 0,
+        CheatWidth::U16Le => 1,
+        CheatWidth::U16Be => 2,
+        CheatWidth::U24Le => 3,
+        CheatWidth::U24Be => 4,
+        CheatWidth::U32Le => 5,
+        CheatWidth::U32Be => 6,
+    };
+    for (i, width) in CheatWidth::ALL.into_iter().enumerate() {
+        assert_eq!(index(width), i, "{width:?} out of place in ALL");
+    }
+    assert!(
+        CheatWidth::ALL
+            .windows(2)
+            .all(|pair| pair[0].byte_len() <= pair[1].byte_len())
+    );
+}
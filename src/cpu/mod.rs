@@ -1,4 +1,6 @@
 use crate::bus::{Bus, IRQ_REQUEST_IRQ1, IRQ_REQUEST_TIMER};
+use crate::cdl::{CDL_BLOCK_SOURCE, CDL_OPCODE, CDL_OPERAND, CDL_VRAM_SOURCE};
+use crate::debugger::{CallFrame, CallStack, FrameKind};
 
 pub const FLAG_CARRY: u8 = 0b0000_0001;
 pub const FLAG_ZERO: u8 = 0b0000_0010;
@@ -35,6 +37,7 @@ pub struct Cpu {
     nmi_pending: bool,
     last_opcode: u8,
     block_transfer_cycles: Option<u32>,
+    call_stack: CallStack,
 }
 
 impl Default for Cpu {
@@ -59,6 +62,7 @@ impl Cpu {
             nmi_pending: false,
             last_opcode: 0,
             block_transfer_cycles: None,
+            call_stack: CallStack::default(),
         }
     }
 
@@ -74,6 +78,7 @@ impl Cpu {
         self.nmi_pending = false;
         self.last_opcode = 0;
         self.block_transfer_cycles = None;
+        self.call_stack.clear();
     }
 
     pub fn request_irq(&mut self) {
@@ -152,6 +157,31 @@ impl Cpu {
         }
     }
 
+    /// The interrupt the next [`step`](Self::step) will dispatch instead of
+    /// executing an instruction, if any.
+    pub fn pending_interrupt(&self, bus: &Bus) -> Option<FrameKind> {
+        if self.halted {
+            return None;
+        }
+        if self.nmi_pending {
+            return Some(FrameKind::Nmi);
+        }
+        if self.get_flag(FLAG_INTERRUPT_DISABLE) && !self.waiting {
+            return None;
+        }
+        bus.next_irq().map(Self::irq_frame_kind)
+    }
+
+    fn irq_frame_kind(mask: u8) -> FrameKind {
+        if mask & IRQ_REQUEST_TIMER != 0 {
+            FrameKind::Timer
+        } else if mask & IRQ_REQUEST_IRQ1 != 0 {
+            FrameKind::Irq1
+        } else {
+            FrameKind::Irq2
+        }
+    }
+
     #[allow(unreachable_patterns)]
     pub fn step(&mut self, bus: &mut Bus) -> u32 {
         let _ = bus.take_cpu_vdc_vce_penalty();
@@ -167,7 +197,9 @@ impl Cpu {
         if self.nmi_pending {
             self.nmi_pending = false;
             let vector_slot = Self::vector_slot_with_fallback(bus, VECTOR_NMI, VECTOR_LEGACY_NMI);
+            let (caller_pc, sp) = (self.pc, self.sp);
             let cycles = self.handle_interrupt(bus, vector_slot, false) as u32;
+            self.note_call(bus, FrameKind::Nmi, caller_pc, sp);
             return Self::finish_step(bus, cycles);
         }
 
@@ -178,7 +210,9 @@ impl Cpu {
             if let Some(mask) = bus.next_irq() {
                 bus.acknowledge_irq(mask);
                 let vector_slot = Self::vector_slot_for_irq_source(bus, mask);
+                let (caller_pc, sp) = (self.pc, self.sp);
                 let cycles = self.handle_interrupt(bus, vector_slot, false) as u32;
+                self.note_call(bus, Self::irq_frame_kind(mask), caller_pc, sp);
                 return Self::finish_step(bus, cycles);
             }
             // No actual IRQ source on the bus — the latched irq_pending was
@@ -189,7 +223,15 @@ impl Cpu {
             return 0;
         }
 
-        let opcode = self.fetch_byte(bus);
+        let opcode_pc = self.pc;
+        let opcode_sp = self.sp;
+        let opcode = if bus.cdl_enabled() {
+            let opcode = bus.read_logged(self.pc, CDL_OPCODE);
+            self.pc = self.pc.wrapping_add(1);
+            opcode
+        } else {
+            self.fetch_byte(bus)
+        };
         self.last_opcode = opcode;
         // HuC6280 T-mode is consumed by the next fetched instruction.
         let t_mode_active = self.get_flag(FLAG_T);
@@ -982,10 +1024,38 @@ impl Cpu {
 
             _ => unreachable!("opcode dispatch table out of sync: {opcode:#04X}"),
         };
+        if self.call_stack.is_enabled() {
+            self.track_call_stack(bus, opcode, opcode_pc, opcode_sp);
+        }
         let cycles = self.block_transfer_cycles.take().unwrap_or(cycles as u32);
         Self::finish_step(bus, cycles)
     }
 
+    fn note_call(&mut self, bus: &Bus, kind: FrameKind, caller_pc: u16, sp: u8) {
+        if !self.call_stack.is_enabled() {
+            return;
+        }
+        self.call_stack.push(CallFrame {
+            kind,
+            caller_pc,
+            target: self.pc,
+            sp,
+            mpr: bus.mpr_array(),
+        });
+    }
+
+    fn track_call_stack(&mut self, bus: &Bus, opcode: u8, opcode_pc: u16, sp: u8) {
+        match opcode {
+            0x20 => self.note_call(bus, FrameKind::Jsr, opcode_pc, sp),
+            0x44 => self.note_call(bus, FrameKind::Bsr, opcode_pc, sp),
+            0x00 if !self.halted => self.note_call(bus, FrameKind::Brk, opcode_pc, sp),
+            // Unwind by stack depth rather than popping one frame, so
+            // routines that discard their return address still line up.
+            0x40 | 0x60 => self.call_stack.unwind_to(self.sp),
+            _ => {}
+        }
+    }
+
     #[inline]
     fn finish_step(bus: &mut Bus, cycles: u32) -> u32 {
         cycles.saturating_add(bus.take_cpu_vdc_vce_penalty() as u32)
@@ -1593,6 +1663,14 @@ impl Cpu {
         let mut dest_alt: u16 = 0;
         let mut src_alt: u16 = 0;
         let mut cycles: u32 = 17;
+        let logged_access = bus.cdl_enabled().then(|| {
+            let access = if bus.is_vdc_data_port(dest) {
+                CDL_BLOCK_SOURCE | CDL_VRAM_SOURCE
+            } else {
+                CDL_BLOCK_SOURCE
+            };
+            bus.code_data_log_mut().set_access(access)
+        });
 
         while remaining > 0 {
             match mode {
@@ -1632,6 +1710,9 @@ impl Cpu {
             remaining -= 1;
             cycles = cycles.saturating_add(6);
         }
+        if let Some(previous) = logged_access {
+            bus.code_data_log_mut().set_access(previous);
+        }
 
         self.waiting = false;
         cycles
@@ -1864,7 +1945,11 @@ impl Cpu {
     }
 
     fn fetch_byte(&mut self, bus: &mut Bus) -> u8 {
-        let value = bus.read(self.pc);
+        let value = if bus.cdl_enabled() {
+            bus.read_logged(self.pc, CDL_OPERAND)
+        } else {
+            bus.read(self.pc)
+        };
         self.pc = self.pc.wrapping_add(1);
         value
     }
@@ -1991,6 +2076,16 @@ impl Cpu {
     pub fn last_opcode(&self) -> u8 {
         self.last_opcode
     }
+
+    /// Shadow call stack; empty unless tracking has been enabled.
+    pub fn call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+
+    /// Start or stop maintaining the shadow call stack. Toggling clears it.
+    pub fn set_call_stack_enabled(&mut self, enabled: bool) {
+        self.call_stack.set_enabled(enabled);
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1,5 +1,6 @@
 use super::*;
 use crate::bus::{IRQ_REQUEST_IRQ1, IRQ_REQUEST_IRQ2, IRQ_REQUEST_TIMER, PAGE_SIZE};
+use crate::debugger::FrameKind;
 
 fn setup_cpu_with_program(program: &[u8]) -> (Cpu, Bus) {
     let mut bus = Bus::new();
@@ -1385,6 +1386,54 @@ fn irq_and_rti_restore_state() {
     assert!(cpu.flag(FLAG_CARRY));
 }
 
+#[test]
+fn call_stack_tracks_nested_calls_and_interrupts() {
+    // $8000: JSR $8010; NOP; BRK
+    // $8010: BSR +1; RTS; RTS
+    let mut program = vec![0xEA; 0x14];
+    program[..5].copy_from_slice(&[0x20, 0x10, 0x80, 0xEA, 0x00]);
+    program[0x10..].copy_from_slice(&[0x44, 0x01, 0x60, 0x60]);
+    let (mut cpu, mut bus) = setup_cpu_with_program(&program);
+    bus.write_u16(0xFFFA, 0x9000);
+    bus.load(0x9000, &[0x40]);
+    cpu.set_call_stack_enabled(true);
+
+    cpu.step(&mut bus); // JSR
+    cpu.step(&mut bus); // BSR
+    let frames = cpu.call_stack().frames();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].kind, FrameKind::Jsr);
+    assert_eq!((frames[0].caller_pc, frames[0].target), (0x8000, 0x8010));
+    assert_eq!(frames[0].sp, 0xFD);
+    assert_eq!(frames[0].mpr, bus.mpr_array());
+    assert_eq!(frames[1].kind, FrameKind::Bsr);
+    assert_eq!((frames[1].caller_pc, frames[1].target), (0x8010, 0x8013));
+
+    cpu.status = 0;
+    bus.raise_irq(IRQ_REQUEST_TIMER);
+    cpu.step(&mut bus);
+    let top = *cpu.call_stack().frames().last().unwrap();
+    assert_eq!(top.kind, FrameKind::Timer);
+    assert!(top.is_interrupt());
+    assert_eq!((top.caller_pc, top.target), (0x8013, 0x9000));
+
+    cpu.step(&mut bus); // RTI
+    assert_eq!(cpu.call_stack().depth(), 2);
+    cpu.step(&mut bus); // RTS from the BSR target
+    assert_eq!(cpu.call_stack().depth(), 1);
+    cpu.step(&mut bus); // RTS at $8012
+    assert_eq!(cpu.pc, 0x8003);
+    assert_eq!(cpu.call_stack().depth(), 0);
+}
+
+#[test]
+fn call_stack_stays_empty_when_disabled() {
+    let (mut cpu, mut bus) = setup_cpu_with_program(&[0x20, 0x10, 0x80]);
+    cpu.step(&mut bus);
+    assert_eq!(cpu.pc, 0x8010);
+    assert_eq!(cpu.call_stack().depth(), 0);
+}
+
 #[test]
 fn multiple_irq_sources_preserve_lower_priority() {
     let program = [0xEA, 0x00];
@@ -1623,3 +1672,30 @@ fn interrupt_clears_decimal_flag() {
         "Interrupt should clear D flag (65C02 behavior)"
     );
 }
+
+#[test]
+fn disassembler_lengths_match_execution() {
+    use crate::disasm::{AddrMode, opcode_info};
+
+    for opcode in 0..=255u8 {
+        let info = opcode_info(opcode);
+        // Branches use a zero offset, so taken or not they land on the next
+        // instruction.
+        let transfers_control = info.mode == AddrMode::BlockTransfer
+            || matches!(
+                info.mnemonic,
+                "BRK" | "JSR" | "JMP" | "RTS" | "RTI" | "WAI" | "STP"
+            );
+        if transfers_control {
+            continue;
+        }
+        let (mut cpu, mut bus) = setup_cpu_with_program(&[opcode, 0x00, 0x00, 0x00]);
+        cpu.step(&mut bus);
+        assert_eq!(
+            cpu.pc,
+            0x8000 + info.mode.size() as u16,
+            "opcode {opcode:02X} {}",
+            info.mnemonic
+        );
+    }
+}
@@ -0,0 +1,135 @@
+/// Maximum number of frames kept; the oldest are dropped beyond this so a
+/// game that resets the stack with TXS cannot grow the list forever.
+const MAX_FRAMES: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameKind {
+    Jsr,
+    Bsr,
+    Brk,
+    Irq1,
+    Irq2,
+    Timer,
+    Nmi,
+}
+
+impl FrameKind {
+    pub fn is_interrupt(self) -> bool {
+        !matches!(self, Self::Jsr | Self::Bsr)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Jsr => "JSR",
+            Self::Bsr => "BSR",
+            Self::Brk => "BRK",
+            Self::Irq1 => "IRQ1",
+            Self::Irq2 => "IRQ2",
+            Self::Timer => "TIMER",
+            Self::Nmi => "NMI",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    pub kind: FrameKind,
+    /// Address of the calling instruction, or the interrupted PC.
+    pub caller_pc: u16,
+    pub target: u16,
+    /// Stack pointer before the return address was pushed; the frame is
+    /// closed once an RTS/RTI brings SP back up to this value.
+    pub sp: u8,
+    /// MPR0–7 at the time of the call.
+    pub mpr: [u8; 8],
+}
+
+impl CallFrame {
+    pub fn is_interrupt(&self) -> bool {
+        self.kind.is_interrupt()
+    }
+
+    /// Bank the target address was mapped to when the frame was entered.
+    pub fn target_bank(&self) -> u8 {
+        self.mpr[(self.target >> 13) as usize]
+    }
+
+    pub fn caller_bank(&self) -> u8 {
+        self.mpr[(self.caller_pc >> 13) as usize]
+    }
+}
+
+/// Shadow call stack maintained by `Cpu::step` while enabled.
+///
+/// This is debugger state, not machine state: it encodes as zero bytes so
+/// save states are unaffected, and decodes as an empty, disabled stack.
+#[derive(Clone, Debug, Default)]
+pub struct CallStack {
+    enabled: bool,
+    frames: Vec<CallFrame>,
+}
+
+impl CallStack {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled != enabled {
+            self.enabled = enabled;
+            self.frames.clear();
+        }
+    }
+
+    /// Active frames, outermost first.
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub(crate) fn push(&mut self, frame: CallFrame) {
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+
+    /// Close every frame whose return address lies at or above `sp`.
+    pub(crate) fn unwind_to(&mut self, sp: u8) {
+        while self.frames.last().is_some_and(|frame| frame.sp <= sp) {
+            self.frames.pop();
+        }
+    }
+}
+
+impl bincode::Encode for CallStack {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for CallStack {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for CallStack {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
@@ -0,0 +1,365 @@
+//! Breakpoint condition expressions.
+//!
+//! The grammar is a small C-like language evaluated over the CPU registers
+//! and the current memory map:
+//!
+//! ```text
+//! A == $3F && [$2040] > 5 && MPR2 == $F8
+//! {$20} >= $C000 || (P & $02) != 0
+//! ```
+//!
+//! Operands are numbers (`$3F`, `0x3F`, `%0011_1111`, `63`), registers
+//! (`A X Y S P PC MPR0..MPR7 SCANLINE`), byte reads `[addr]` and
+//! little-endian word reads `{addr}`. Operators follow C precedence; every
+//! value is an unsigned 32-bit integer and comparisons yield `0` or `1`.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    P,
+    Pc,
+    Mpr(u8),
+    Scanline,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    BitNot,
+    Neg,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    /// Binding strength; higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::BitOr => 3,
+            Self::BitXor => 4,
+            Self::BitAnd => 5,
+            Self::Eq | Self::Ne => 6,
+            Self::Lt | Self::Le | Self::Gt | Self::Ge => 7,
+            Self::Shl | Self::Shr => 8,
+            Self::Add | Self::Sub => 9,
+            Self::Mul | Self::Div | Self::Rem => 10,
+        }
+    }
+
+    fn apply(self, lhs: u32, rhs: u32) -> u32 {
+        match self {
+            Self::Or => (lhs != 0 || rhs != 0) as u32,
+            Self::And => (lhs != 0 && rhs != 0) as u32,
+            Self::BitOr => lhs | rhs,
+            Self::BitXor => lhs ^ rhs,
+            Self::BitAnd => lhs & rhs,
+            Self::Eq => (lhs == rhs) as u32,
+            Self::Ne => (lhs != rhs) as u32,
+            Self::Lt => (lhs < rhs) as u32,
+            Self::Le => (lhs <= rhs) as u32,
+            Self::Gt => (lhs > rhs) as u32,
+            Self::Ge => (lhs >= rhs) as u32,
+            Self::Shl => lhs.checked_shl(rhs).unwrap_or(0),
+            Self::Shr => lhs.checked_shr(rhs).unwrap_or(0),
+            Self::Add => lhs.wrapping_add(rhs),
+            Self::Sub => lhs.wrapping_sub(rhs),
+            Self::Mul => lhs.wrapping_mul(rhs),
+            Self::Div => lhs.checked_div(rhs).unwrap_or(0),
+            Self::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Const(u32),
+    Reg(Register),
+    /// Byte read through the current MPR mapping.
+    Byte(Box<Expr>),
+    /// Little-endian 16-bit read through the current MPR mapping.
+    Word(Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// Machine state visible to condition expressions.
+pub trait ExprContext {
+    fn register(&self, reg: Register) -> u32;
+    /// Must not have side effects on the emulated hardware.
+    fn read_byte(&self, addr: u16) -> u8;
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expression(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some((col, tok)) => Err(format!("unexpected {tok} at column {}", col + 1)),
+        }
+    }
+
+    pub fn evaluate(&self, ctx: &dyn ExprContext) -> u32 {
+        match self {
+            Self::Const(value) => *value,
+            Self::Reg(reg) => ctx.register(*reg),
+            Self::Byte(addr) => ctx.read_byte(addr.evaluate(ctx) as u16) as u32,
+            Self::Word(addr) => {
+                let addr = addr.evaluate(ctx) as u16;
+                let lo = ctx.read_byte(addr) as u32;
+                let hi = ctx.read_byte(addr.wrapping_add(1)) as u32;
+                (hi << 8) | lo
+            }
+            Self::Unary(op, inner) => {
+                let value = inner.evaluate(ctx);
+                match op {
+                    UnaryOp::Not => (value == 0) as u32,
+                    UnaryOp::BitNot => !value,
+                    UnaryOp::Neg => value.wrapping_neg(),
+                }
+            }
+            Self::Binary(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(ctx);
+                // Short-circuit so `[addr]` on the right is skipped when
+                // the left side already decides the result.
+                match op {
+                    BinaryOp::And if lhs == 0 => 0,
+                    BinaryOp::Or if lhs != 0 => 1,
+                    _ => op.apply(lhs, rhs.evaluate(ctx)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Number(u32),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "number {value}"),
+            Self::Ident(name) => write!(f, "'{name}'"),
+            Self::Op(op) => write!(f, "'{op}'"),
+        }
+    }
+}
+
+const OPERATORS: [&str; 27] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "(", ")", "[", "]", "{", "}", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, String> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let ch = bytes[pos];
+        if ch.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        let radix = match ch {
+            b'$' => Some((16, 1)),
+            b'%' if bytes.get(pos + 1).is_some_and(|b| matches!(b, b'0' | b'1')) => Some((2, 1)),
+            b'0' if matches!(bytes.get(pos + 1), Some(b'x' | b'X')) => Some((16, 2)),
+            b'0'..=b'9' => Some((10, 0)),
+            _ => None,
+        };
+        if let Some((radix, prefix)) = radix {
+            pos += prefix;
+            let digits_start = pos;
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            let digits: String = source[digits_start..pos]
+                .chars()
+                .filter(|&c| c != '_')
+                .collect();
+            let value = u32::from_str_radix(&digits, radix)
+                .map_err(|_| format!("invalid number at column {}", start + 1))?;
+            tokens.push((start, Token::Number(value)));
+            continue;
+        }
+        if ch.is_ascii_alphabetic() || ch == b'_' {
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            tokens.push((start, Token::Ident(source[start..pos].to_ascii_uppercase())));
+            continue;
+        }
+        let Some(op) = OPERATORS.iter().find(|op| source[pos..].starts_with(**op)) else {
+            return Err(format!(
+                "unexpected character '{}' at column {}",
+                source[pos..].chars().next().unwrap_or('?'),
+                pos + 1
+            ));
+        };
+        pos += op.len();
+        // Accept a lone `=` as equality; it is what people type in a hurry.
+        let op = if *op == "=" { "==" } else { op };
+        tokens.push((start, Token::Op(op)));
+    }
+    Ok(tokens)
+}
+
+fn register_named(name: &str) -> Option<Register> {
+    let reg = match name {
+        "A" => Register::A,
+        "X" => Register::X,
+        "Y" => Register::Y,
+        "S" | "SP" => Register::Sp,
+        "P" => Register::P,
+        "PC" => Register::Pc,
+        "SCANLINE" | "LINE" => Register::Scanline,
+        _ => {
+            let index = name.strip_prefix("MPR")?.parse::<u8>().ok()?;
+            if index >= 8 {
+                return None;
+            }
+            Register::Mpr(index)
+        }
+    };
+    Some(reg)
+}
+
+fn binary_op(op: &str) -> Option<BinaryOp> {
+    let op = match op {
+        "||" => BinaryOp::Or,
+        "&&" => BinaryOp::And,
+        "|" => BinaryOp::BitOr,
+        "^" => BinaryOp::BitXor,
+        "&" => BinaryOp::BitAnd,
+        "==" => BinaryOp::Eq,
+        "!=" => BinaryOp::Ne,
+        "<" => BinaryOp::Lt,
+        "<=" => BinaryOp::Le,
+        ">" => BinaryOp::Gt,
+        ">=" => BinaryOp::Ge,
+        "<<" => BinaryOp::Shl,
+        ">>" => BinaryOp::Shr,
+        "+" => BinaryOp::Add,
+        "-" => BinaryOp::Sub,
+        "*" => BinaryOp::Mul,
+        "/" => BinaryOp::Div,
+        "%" => BinaryOp::Rem,
+        _ => return None,
+    };
+    Some(op)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), String> {
+        let tok = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "unexpected end of expression".to_string())?;
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<(), String> {
+        match self.next()? {
+            (_, Token::Op(found)) if found == op => Ok(()),
+            (col, tok) => Err(format!(
+                "expected '{op}' but found {tok} at column {}",
+                col + 1
+            )),
+        }
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some((_, Token::Op(op))) = self.peek() {
+            let Some(op) = binary_op(op) else {
+                break;
+            };
+            let precedence = op.precedence();
+            if precedence <= min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expression(precedence)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let (col, tok) = self.next()?;
+        match tok {
+            Token::Number(value) => Ok(Expr::Const(value)),
+            Token::Ident(name) => register_named(&name)
+                .map(Expr::Reg)
+                .ok_or_else(|| format!("unknown register '{name}' at column {}", col + 1)),
+            Token::Op("(") => {
+                let inner = self.expression(0)?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Token::Op("[") => {
+                let inner = self.expression(0)?;
+                self.expect("]")?;
+                Ok(Expr::Byte(Box::new(inner)))
+            }
+            Token::Op("{") => {
+                let inner = self.expression(0)?;
+                self.expect("}")?;
+                Ok(Expr::Word(Box::new(inner)))
+            }
+            Token::Op(op @ ("!" | "~" | "-")) => {
+                let op = match op {
+                    "!" => UnaryOp::Not,
+                    "~" => UnaryOp::BitNot,
+                    _ => UnaryOp::Neg,
+                };
+                Ok(Expr::Unary(op, Box::new(self.unary()?)))
+            }
+            tok => Err(format!("unexpected {tok} at column {}", col + 1)),
+        }
+    }
+}
@@ -0,0 +1,453 @@
+mod call_stack;
+mod expr;
+
+#[cfg(test)]
+mod tests;
+
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::symbols::SymbolTable;
+use std::collections::BTreeMap;
+
+pub use call_stack::{CallFrame, CallStack, FrameKind};
+pub use expr::{BinaryOp, Expr, ExprContext, Register, UnaryOp};
+
+const OPCODE_JSR: u8 = 0x20;
+const OPCODE_BSR: u8 = 0x44;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugBreak {
+    Breakpoint(u16),
+    Step(u16),
+    StepOver(u16),
+    StepOut(u16),
+    RunTo(u16),
+}
+
+/// Pending run request started by one of the `request_*` methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RunMode {
+    Step,
+    /// Step over, decided on the next fetched opcode.
+    StepOver,
+    /// Run until the subroutine called at `pc - len` returns to `pc` with
+    /// the stack pointer it was called with.
+    StepOverReturn {
+        pc: u16,
+        sp: u8,
+    },
+    /// Run until the shadow call stack is shallower than `depth`; `None`
+    /// until the first instruction records the current depth.  Counting
+    /// frames rather than comparing SP keeps an interrupt that fires and
+    /// returns meanwhile, or a PHA/PLA pair, from ending the step early.
+    StepOut {
+        depth: Option<usize>,
+    },
+    /// Run until `pc` is about to execute; `started` is set once the first
+    /// instruction has run, so a target equal to the current PC is only
+    /// reached by coming back to it.
+    RunTo {
+        pc: u16,
+        started: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugTick {
+    Ran(u32),
+    Paused,
+    Break(DebugBreak),
+}
+
+/// Parsed breakpoint condition, keeping the text it was built from so
+/// front-ends can show it back to the user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreakCondition {
+    source: String,
+    expr: Expr,
+}
+
+impl BreakCondition {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        Ok(Self {
+            source: source.to_string(),
+            expr: Expr::parse(source)?,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    pub fn evaluate(&self, ctx: &dyn ExprContext) -> bool {
+        self.expr.evaluate(ctx) != 0
+    }
+}
+
+/// When a breakpoint whose condition passed actually stops execution,
+/// based on how many times it has passed so far (including this one).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HitCondition {
+    #[default]
+    Always,
+    /// Break only on the Nth hit.
+    Equal(u32),
+    /// Break on the Nth hit and every one after it.
+    AtLeast(u32),
+    /// Break on every Nth hit.
+    Multiple(u32),
+}
+
+impl HitCondition {
+    pub fn matches(self, hits: u32) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Equal(n) => hits == n,
+            Self::AtLeast(n) => hits >= n,
+            Self::Multiple(n) => n != 0 && hits.is_multiple_of(n),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub enabled: bool,
+    pub condition: Option<BreakCondition>,
+    pub hit_condition: HitCondition,
+    /// Number of times execution reached this address with the condition
+    /// satisfied.
+    pub hits: u32,
+}
+
+impl Breakpoint {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_condition(condition: Option<BreakCondition>, hit_condition: HitCondition) -> Self {
+        Self {
+            enabled: true,
+            condition,
+            hit_condition,
+            hits: 0,
+        }
+    }
+
+    /// Count a hit if the condition holds and report whether to stop.
+    fn check(&mut self, ctx: &dyn ExprContext) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if let Some(condition) = &self.condition
+            && !condition.evaluate(ctx)
+        {
+            return false;
+        }
+        self.hits = self.hits.saturating_add(1);
+        self.hit_condition.matches(self.hits)
+    }
+}
+
+/// Read-only view of the machine used to evaluate breakpoint conditions.
+pub struct MachineView<'a> {
+    pub cpu: &'a Cpu,
+    pub bus: &'a Bus,
+}
+
+impl ExprContext for MachineView<'_> {
+    fn register(&self, reg: Register) -> u32 {
+        match reg {
+            Register::A => self.cpu.a as u32,
+            Register::X => self.cpu.x as u32,
+            Register::Y => self.cpu.y as u32,
+            Register::Sp => self.cpu.sp as u32,
+            Register::P => self.cpu.status as u32,
+            Register::Pc => self.cpu.pc as u32,
+            Register::Mpr(index) => self.bus.mpr(index as usize) as u32,
+            Register::Scanline => self.bus.vdc_current_scanline() as u32,
+        }
+    }
+
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    pub paused: bool,
+    pub breakpoints: BTreeMap<u16, Breakpoint>,
+    pub last_break: Option<DebugBreak>,
+    /// Maintain the CPU's shadow call stack while this debugger drives
+    /// execution.
+    pub track_calls: bool,
+    /// Labels used to resolve breakpoint locations by name and by
+    /// front-ends when showing addresses.
+    pub symbols: SymbolTable,
+    run_mode: Option<RunMode>,
+    /// PC of the breakpoint we last stopped on, so resuming does not
+    /// immediately trip it again.
+    resume_pc: Option<u16>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            paused: false,
+            breakpoints: BTreeMap::new(),
+            last_break: None,
+            track_calls: true,
+            symbols: SymbolTable::new(),
+            run_mode: None,
+            resume_pc: None,
+        }
+    }
+
+    pub fn request_step(&mut self) {
+        self.request(RunMode::Step);
+    }
+
+    /// Like [`request_step`](Self::request_step), but a JSR/BSR runs the
+    /// whole subroutine and stops at its return address.
+    pub fn request_step_over(&mut self) {
+        self.request(RunMode::StepOver);
+    }
+
+    /// Run until the current subroutine or interrupt handler returns.
+    /// Uses the shadow call stack, which is kept while this is pending; if
+    /// no frame is open (e.g. tracking was just switched on) it acts as a
+    /// single step.
+    pub fn request_step_out(&mut self) {
+        self.request(RunMode::StepOut { depth: None });
+    }
+
+    /// Run until `pc` is about to execute again.  At least one instruction
+    /// runs first, so asking for the current PC finds its next visit.
+    pub fn request_run_to(&mut self, pc: u16) {
+        self.request(RunMode::RunTo { pc, started: false });
+    }
+
+    /// Drop any pending step/run request without resuming.
+    pub fn cancel_step(&mut self) {
+        self.run_mode = None;
+    }
+
+    fn request(&mut self, mode: RunMode) {
+        self.run_mode = Some(mode);
+        self.paused = false;
+    }
+
+    pub fn clear_break(&mut self) {
+        self.last_break = None;
+    }
+
+    pub fn add_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.insert(pc, Breakpoint::new());
+    }
+
+    /// Add (or replace) a breakpoint guarded by a condition expression and
+    /// hit count. An empty condition string means "always".
+    pub fn add_conditional_breakpoint(
+        &mut self,
+        pc: u16,
+        condition: &str,
+        hit_condition: HitCondition,
+    ) -> Result<(), String> {
+        let condition = if condition.trim().is_empty() {
+            None
+        } else {
+            Some(BreakCondition::parse(condition)?)
+        };
+        self.breakpoints
+            .insert(pc, Breakpoint::with_condition(condition, hit_condition));
+        Ok(())
+    }
+
+    /// Add a breakpoint at `location`, given as a symbol name or a hex
+    /// address (`$E318`, `0xE318`, `E318`). A symbol that lives in a
+    /// specific bank gets an `MPRn == $bank` guard in front of `condition`
+    /// so it only fires while that bank is mapped. Returns the logical PC.
+    pub fn add_breakpoint_at(
+        &mut self,
+        location: &str,
+        condition: &str,
+        hit_condition: HitCondition,
+    ) -> Result<u16, String> {
+        let (pc, bank) = self.resolve_location(location)?;
+        let condition = condition.trim();
+        let guarded = match (bank, condition.is_empty()) {
+            (None, _) => condition.to_string(),
+            (Some(bank), true) => format!("MPR{} == ${bank:02X}", pc >> 13),
+            (Some(bank), false) => format!("MPR{} == ${bank:02X} && ({condition})", pc >> 13),
+        };
+        self.add_conditional_breakpoint(pc, &guarded, hit_condition)?;
+        Ok(pc)
+    }
+
+    /// Resolve a breakpoint location to a logical PC and, for banked
+    /// symbols, the bank it needs mapped. `$` or `0x` always means a number;
+    /// otherwise symbols win, so labels such as `fade` or `c0de` are not
+    /// taken for addresses, and bare hex is only used when no symbol
+    /// matches.
+    pub fn resolve_location(&self, location: &str) -> Result<(u16, Option<u8>), String> {
+        let location = location.trim();
+        let prefixed = location
+            .strip_prefix('$')
+            .or_else(|| location.strip_prefix("0x"))
+            .or_else(|| location.strip_prefix("0X"));
+        if let Some(hex) = prefixed {
+            return u16::from_str_radix(hex, 16)
+                .map(|pc| (pc, None))
+                .map_err(|_| format!("invalid address '{location}'"));
+        }
+
+        match self.symbols.find(location) {
+            [symbol] => Ok((symbol.addr, symbol.bank)),
+            [] => u16::from_str_radix(location, 16)
+                .map(|pc| (pc, None))
+                .map_err(|_| format!("unknown symbol '{location}'")),
+            several => {
+                let banks: Vec<String> = several
+                    .iter()
+                    .map(|s| match s.bank {
+                        Some(bank) => format!("{bank:02X}:{:04X}", s.addr),
+                        None => format!("{:04X}", s.addr),
+                    })
+                    .collect();
+                Err(format!(
+                    "symbol '{location}' is defined more than once ({}); use an address",
+                    banks.join(", ")
+                ))
+            }
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.remove(&pc);
+    }
+
+    pub fn toggle_breakpoint(&mut self, pc: u16) {
+        if self.breakpoints.remove(&pc).is_none() {
+            self.breakpoints.insert(pc, Breakpoint::new());
+        }
+    }
+
+    pub fn has_breakpoint(&self, pc: u16) -> bool {
+        self.breakpoints.contains_key(&pc)
+    }
+
+    pub fn reset_hit_counts(&mut self) {
+        for bp in self.breakpoints.values_mut() {
+            bp.hits = 0;
+        }
+    }
+
+    pub fn step_pending(&self) -> bool {
+        self.run_mode.is_some()
+    }
+
+    /// Whether the pending request needs the CPU's shadow call stack.
+    pub(crate) fn needs_call_stack(&self) -> bool {
+        matches!(self.run_mode, Some(RunMode::StepOut { .. }))
+    }
+
+    /// Called before the instruction at `pc` executes. Arms step-over/out
+    /// and reports run-to and step-over targets that have been reached.
+    pub(crate) fn before_instruction(
+        &mut self,
+        pc: u16,
+        opcode: u8,
+        sp: u8,
+        call_depth: usize,
+    ) -> Option<DebugBreak> {
+        let mode = self.run_mode?;
+        let (next, hit) = match mode {
+            RunMode::RunTo {
+                pc: target,
+                started: true,
+            } if pc == target => (None, Some(DebugBreak::RunTo(pc))),
+            RunMode::RunTo {
+                pc: target,
+                started: false,
+            } => (
+                Some(RunMode::RunTo {
+                    pc: target,
+                    started: true,
+                }),
+                None,
+            ),
+            RunMode::StepOverReturn {
+                pc: target,
+                sp: frame,
+            } if pc == target && sp == frame => (None, Some(DebugBreak::StepOver(pc))),
+            RunMode::StepOver => {
+                let next = match opcode {
+                    OPCODE_JSR => RunMode::StepOverReturn {
+                        pc: pc.wrapping_add(3),
+                        sp,
+                    },
+                    OPCODE_BSR => RunMode::StepOverReturn {
+                        pc: pc.wrapping_add(2),
+                        sp,
+                    },
+                    _ => RunMode::Step,
+                };
+                (Some(next), None)
+            }
+            RunMode::StepOut { depth: None } if call_depth == 0 => (Some(RunMode::Step), None),
+            RunMode::StepOut { depth: None } => (
+                Some(RunMode::StepOut {
+                    depth: Some(call_depth),
+                }),
+                None,
+            ),
+            other => (Some(other), None),
+        };
+        self.run_mode = next;
+        hit
+    }
+
+    /// Called after an instruction executed, with the PC and call stack
+    /// depth it left behind.
+    pub(crate) fn after_instruction(&mut self, pc: u16, call_depth: usize) -> Option<DebugBreak> {
+        let hit = match self.run_mode? {
+            RunMode::Step => DebugBreak::Step(pc),
+            RunMode::StepOut { depth: Some(depth) } if call_depth < depth => {
+                DebugBreak::StepOut(pc)
+            }
+            _ => return None,
+        };
+        self.run_mode = None;
+        Some(hit)
+    }
+
+    /// Evaluate the breakpoint at `pc` (if any) and record the break.
+    pub(crate) fn check_breakpoint(&mut self, pc: u16, ctx: &dyn ExprContext) -> bool {
+        if self.resume_pc.take() == Some(pc) {
+            return false;
+        }
+        let Some(bp) = self.breakpoints.get_mut(&pc) else {
+            return false;
+        };
+        if !bp.check(ctx) {
+            return false;
+        }
+        self.resume_pc = Some(pc);
+        self.run_mode = None;
+        true
+    }
+}
@@ -0,0 +1,385 @@
+use super::*;
+use crate::emulator::Emulator;
+
+struct FakeMachine {
+    a: u8,
+    mpr2: u8,
+    memory: [u8; 0x100],
+}
+
+impl ExprContext for FakeMachine {
+    fn register(&self, reg: Register) -> u32 {
+        match reg {
+            Register::A => self.a as u32,
+            Register::Mpr(2) => self.mpr2 as u32,
+            _ => 0,
+        }
+    }
+
+    fn read_byte(&self, addr: u16) -> u8 {
+        self.memory[(addr & 0xFF) as usize]
+    }
+}
+
+fn fake() -> FakeMachine {
+    FakeMachine {
+        a: 0x3F,
+        mpr2: 0xF8,
+        memory: [0; 0x100],
+    }
+}
+
+fn eval(source: &str, ctx: &FakeMachine) -> u32 {
+    Expr::parse(source).unwrap().evaluate(ctx)
+}
+
+#[test]
+fn expr_parses_numbers_in_all_radixes() {
+    let ctx = fake();
+    assert_eq!(eval("$3F", &ctx), 0x3F);
+    assert_eq!(eval("0x3f", &ctx), 0x3F);
+    assert_eq!(eval("%0011_1111", &ctx), 0x3F);
+    assert_eq!(eval("63", &ctx), 63);
+}
+
+#[test]
+fn expr_follows_c_precedence() {
+    let ctx = fake();
+    assert_eq!(eval("1 + 2 * 3", &ctx), 7);
+    assert_eq!(eval("(1 + 2) * 3", &ctx), 9);
+    assert_eq!(eval("1 | 2 == 2", &ctx), 1);
+    assert_eq!(eval("$F0 & $3C >> 2", &ctx), 0x00);
+    assert_eq!(eval("-1 == $FFFFFFFF", &ctx), 1);
+    assert_eq!(eval("!0 && ~0 != 0", &ctx), 1);
+    assert_eq!(eval("10 / 0", &ctx), 0);
+}
+
+#[test]
+fn expr_reads_registers_and_memory() {
+    let mut ctx = fake();
+    ctx.memory[0x40] = 6;
+    ctx.memory[0x41] = 0x12;
+    assert_eq!(eval("A == $3F && [$2040] > 5 && MPR2 == $F8", &ctx), 1);
+    assert_eq!(eval("a = $3f", &ctx), 1);
+    assert_eq!(eval("{$40}", &ctx), 0x1206);
+    assert_eq!(eval("[$40 + 1]", &ctx), 0x12);
+    ctx.memory[0x40] = 5;
+    assert_eq!(eval("A == $3F && [$2040] > 5", &ctx), 0);
+}
+
+#[test]
+fn expr_reports_errors_with_column() {
+    assert!(
+        Expr::parse("A ==")
+            .unwrap_err()
+            .contains("end of expression")
+    );
+    assert!(Expr::parse("Q == 1").unwrap_err().contains("column 1"));
+    assert!(Expr::parse("MPR8").is_err());
+    assert!(
+        Expr::parse("[$20")
+            .unwrap_err()
+            .contains("end of expression")
+    );
+    assert!(Expr::parse("A == 1 )").unwrap_err().contains("column 8"));
+    assert!(Expr::parse("A @ 1").unwrap_err().contains("'@'"));
+}
+
+#[test]
+fn hit_condition_matches_counts() {
+    assert!(HitCondition::Always.matches(1));
+    assert!(!HitCondition::Equal(3).matches(2));
+    assert!(HitCondition::Equal(3).matches(3));
+    assert!(!HitCondition::Equal(3).matches(4));
+    assert!(HitCondition::AtLeast(3).matches(4));
+    assert!(HitCondition::Multiple(4).matches(8));
+    assert!(!HitCondition::Multiple(4).matches(6));
+    assert!(!HitCondition::Multiple(0).matches(0));
+}
+
+// LDX #$00; loop: INX; CPX #$10; BNE loop; BRK
+const COUNT_LOOP: [u8; 8] = [0xA2, 0x00, 0xE8, 0xE0, 0x10, 0xD0, 0xFB, 0x00];
+const LOOP_PC: u16 = 0xC002;
+
+fn run_until_break(emu: &mut Emulator, debugger: &mut Debugger) -> Option<DebugBreak> {
+    for _ in 0..1000 {
+        if emu.cpu.halted {
+            return None;
+        }
+        if let DebugTick::Break(br) = emu.tick_debugger(debugger) {
+            return Some(br);
+        }
+    }
+    None
+}
+
+#[test]
+fn conditional_breakpoint_stops_only_when_condition_holds() {
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &COUNT_LOOP);
+    emu.reset();
+
+    let mut debugger = Debugger::new();
+    debugger
+        .add_conditional_breakpoint(LOOP_PC, "X == 5", HitCondition::Always)
+        .unwrap();
+
+    let br = run_until_break(&mut emu, &mut debugger);
+    assert_eq!(br, Some(DebugBreak::Breakpoint(LOOP_PC)));
+    assert_eq!(emu.cpu.x, 5);
+    assert_eq!(debugger.breakpoints[&LOOP_PC].hits, 1);
+
+    // Resuming must leave the breakpoint PC instead of re-breaking in place.
+    debugger.paused = false;
+    assert_eq!(run_until_break(&mut emu, &mut debugger), None);
+    assert!(emu.cpu.halted);
+}
+
+#[test]
+fn hit_count_breakpoint_skips_earlier_hits() {
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &COUNT_LOOP);
+    emu.reset();
+
+    let mut debugger = Debugger::new();
+    debugger
+        .add_conditional_breakpoint(LOOP_PC, "", HitCondition::Multiple(4))
+        .unwrap();
+
+    assert!(run_until_break(&mut emu, &mut debugger).is_some());
+    assert_eq!(emu.cpu.x, 3);
+    debugger.paused = false;
+    assert!(run_until_break(&mut emu, &mut debugger).is_some());
+    assert_eq!(emu.cpu.x, 7);
+    assert_eq!(debugger.breakpoints[&LOOP_PC].hits, 8);
+}
+
+#[test]
+fn invalid_condition_is_rejected_without_adding_breakpoint() {
+    let mut debugger = Debugger::new();
+    assert!(
+        debugger
+            .add_conditional_breakpoint(0xC000, "A ==", HitCondition::Always)
+            .is_err()
+    );
+    assert!(!debugger.has_breakpoint(0xC000));
+}
+
+// $C000: JSR $C010; LDA #$01; BRK
+// $C010: LDX #$05; loop: DEX; BNE loop; RTS
+fn subroutine_program() -> Emulator {
+    let mut program = vec![0xEA; 0x16];
+    program[..6].copy_from_slice(&[0x20, 0x10, 0xC0, 0xA9, 0x01, 0x00]);
+    program[0x10..].copy_from_slice(&[0xA2, 0x05, 0xCA, 0xD0, 0xFD, 0x60]);
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &program);
+    emu.reset();
+    emu
+}
+
+#[test]
+fn step_over_runs_whole_subroutine() {
+    let mut emu = subroutine_program();
+    let sp = emu.cpu.sp;
+    let mut debugger = Debugger::new();
+
+    debugger.request_step_over();
+    let br = run_until_break(&mut emu, &mut debugger);
+    assert_eq!(br, Some(DebugBreak::StepOver(0xC003)));
+    assert_eq!(emu.cpu.sp, sp);
+    assert_eq!(emu.cpu.x, 0);
+    assert!(debugger.paused);
+
+    // Non-call instructions behave like a plain step.
+    debugger.request_step_over();
+    let br = run_until_break(&mut emu, &mut debugger);
+    assert_eq!(br, Some(DebugBreak::Step(0xC005)));
+}
+
+#[test]
+fn step_out_stops_after_return() {
+    let mut emu = subroutine_program();
+    let mut debugger = Debugger::new();
+
+    debugger.request_step();
+    assert_eq!(
+        run_until_break(&mut emu, &mut debugger),
+        Some(DebugBreak::Step(0xC010))
+    );
+
+    debugger.request_step_out();
+    let br = run_until_break(&mut emu, &mut debugger);
+    assert_eq!(br, Some(DebugBreak::StepOut(0xC003)));
+    assert_eq!(emu.cpu.x, 0);
+}
+
+#[test]
+fn step_out_ignores_balanced_stack_traffic_and_nested_calls() {
+    // $C000: JSR $C010; LDA #$01; BRK
+    // $C010: PHA; PLA; JSR $C020; RTS
+    // $C020: RTS
+    let mut program = vec![0xEA; 0x21];
+    program[..6].copy_from_slice(&[0x20, 0x10, 0xC0, 0xA9, 0x01, 0x00]);
+    program[0x10..0x16].copy_from_slice(&[0x48, 0x68, 0x20, 0x20, 0xC0, 0x60]);
+    program[0x20] = 0x60;
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &program);
+    emu.reset();
+    let mut debugger = Debugger::new();
+
+    for expected in [0xC010, 0xC011] {
+        debugger.request_step();
+        assert_eq!(
+            run_until_break(&mut emu, &mut debugger),
+            Some(DebugBreak::Step(expected))
+        );
+    }
+
+    // Armed with SP one below the frame: after the PLA the inner RTS
+    // leaves SP above it, but only the outer RTS closes the frame.
+    debugger.request_step_out();
+    assert_eq!(
+        run_until_break(&mut emu, &mut debugger),
+        Some(DebugBreak::StepOut(0xC003))
+    );
+}
+
+#[test]
+fn run_to_current_pc_waits_for_the_next_visit() {
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &COUNT_LOOP);
+    emu.reset();
+    let mut debugger = Debugger::new();
+    debugger
+        .add_conditional_breakpoint(LOOP_PC, "X == 3", HitCondition::Always)
+        .unwrap();
+    run_until_break(&mut emu, &mut debugger);
+    debugger.remove_breakpoint(LOOP_PC);
+
+    debugger.request_run_to(LOOP_PC);
+    assert_eq!(
+        run_until_break(&mut emu, &mut debugger),
+        Some(DebugBreak::RunTo(LOOP_PC))
+    );
+    assert_eq!(emu.cpu.x, 4);
+}
+
+#[test]
+fn run_to_stops_before_target_and_breakpoints_cancel_it() {
+    let mut emu = subroutine_program();
+    let mut debugger = Debugger::new();
+
+    debugger.request_run_to(0xC015);
+    let br = run_until_break(&mut emu, &mut debugger);
+    assert_eq!(br, Some(DebugBreak::RunTo(0xC015)));
+    assert_eq!(emu.cpu.pc, 0xC015);
+    assert!(!debugger.step_pending());
+
+    let mut emu = subroutine_program();
+    debugger.add_breakpoint(0xC012);
+    debugger.request_run_to(0xC015);
+    let br = run_until_break(&mut emu, &mut debugger);
+    assert_eq!(br, Some(DebugBreak::Breakpoint(0xC012)));
+    assert!(!debugger.step_pending());
+}
+
+#[test]
+fn debugger_maintains_call_stack_while_attached() {
+    let mut emu = subroutine_program();
+    let mut debugger = Debugger::new();
+
+    debugger.request_step();
+    run_until_break(&mut emu, &mut debugger);
+    let frames = emu.cpu.call_stack().frames();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].kind, FrameKind::Jsr);
+    assert_eq!(frames[0].caller_pc, 0xC000);
+    assert_eq!(frames[0].target, 0xC010);
+    assert_eq!(frames[0].target_bank(), emu.bus.mpr(6));
+
+    debugger.request_step_out();
+    run_until_break(&mut emu, &mut debugger);
+    assert!(emu.cpu.call_stack().frames().is_empty());
+
+    debugger.track_calls = false;
+    debugger.request_step();
+    run_until_break(&mut emu, &mut debugger);
+    assert!(!emu.cpu.call_stack().is_enabled());
+}
+
+#[test]
+fn breakpoints_resolve_symbol_names_with_bank_guard() {
+    let mut emu = subroutine_program();
+    let bank = emu.bus.mpr(6);
+    let mut debugger = Debugger::new();
+    debugger.symbols = crate::symbols::SymbolTable::parse(&format!(
+        "{bank:02X}:C010 delay\n{:02X}:C010 other_bank\nC012 delay_loop\n",
+        bank.wrapping_add(1)
+    ))
+    .unwrap();
+
+    assert_eq!(
+        debugger.add_breakpoint_at("delay", "", HitCondition::Always),
+        Ok(0xC010)
+    );
+    assert_eq!(
+        debugger.breakpoints[&0xC010]
+            .condition
+            .as_ref()
+            .unwrap()
+            .source(),
+        format!("MPR6 == ${bank:02X}")
+    );
+    assert_eq!(
+        run_until_break(&mut emu, &mut debugger),
+        Some(DebugBreak::Breakpoint(0xC010))
+    );
+
+    // Mapped to the other bank, the guard keeps the breakpoint quiet.
+    let mut debugger = Debugger {
+        symbols: debugger.symbols.clone(),
+        ..Debugger::new()
+    };
+    debugger
+        .add_breakpoint_at("other_bank", "X == 0", HitCondition::Always)
+        .unwrap();
+    let mut emu = subroutine_program();
+    assert_eq!(run_until_break(&mut emu, &mut debugger), None);
+
+    assert_eq!(
+        debugger.add_breakpoint_at("delay_loop", "", HitCondition::Always),
+        Ok(0xC012)
+    );
+    assert_eq!(
+        debugger.add_breakpoint_at("$C015", "", HitCondition::Always),
+        Ok(0xC015)
+    );
+    assert!(
+        debugger
+            .add_breakpoint_at("missing", "", HitCondition::Always)
+            .unwrap_err()
+            .contains("unknown symbol")
+    );
+}
+
+#[test]
+fn hex_looking_labels_resolve_as_symbols() {
+    let mut debugger = Debugger::new();
+    debugger.symbols = crate::symbols::SymbolTable::parse("C012 fade\n").unwrap();
+
+    assert_eq!(
+        debugger.add_breakpoint_at("fade", "", HitCondition::Always),
+        Ok(0xC012)
+    );
+    assert!(!debugger.has_breakpoint(0xFADE));
+    // A prefix always means a number; bare hex is used when no label matches.
+    assert_eq!(debugger.resolve_location("$fade"), Ok((0xFADE, None)));
+    assert_eq!(debugger.resolve_location("0xFADE"), Ok((0xFADE, None)));
+    assert_eq!(debugger.resolve_location("beef"), Ok((0xBEEF, None)));
+    assert!(
+        debugger
+            .resolve_location("$fade_in")
+            .unwrap_err()
+            .contains("invalid address")
+    );
+}
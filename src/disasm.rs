@@ -0,0 +1,702 @@
+//! Table-driven HuC6280 disassembler.
+//!
+//! Every one of the 256 opcodes decodes to a mnemonic, addressing mode and
+//! length. Opcodes that are undefined on real hardware decode the way
+//! `Cpu::step` executes them (mostly single-byte NOPs) and are flagged as
+//! unofficial.
+
+use crate::symbols::SymbolTable;
+use std::fmt;
+
+/// Logical address of the HuC6280 zero page ($2000-$20FF).
+pub const ZERO_PAGE_BASE: u16 = 0x2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddrMode {
+    Implied,
+    Accumulator,
+    /// `#$nn`; also the ST0/ST1/ST2 value.
+    Immediate,
+    /// TAM/TMA bit mask, shown as `#$nn`.
+    MprMask,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    /// `($nn)`
+    ZeroPageIndirect,
+    /// `($nn,X)`
+    ZeroPageIndirectX,
+    /// `($nn),Y`
+    ZeroPageIndirectY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    /// `($nnnn)`
+    AbsoluteIndirect,
+    /// `($nnnn,X)`
+    AbsoluteIndirectX,
+    Relative,
+    /// BBR/BBS: `$zp, target`
+    ZeroPageRelative,
+    /// TST `#$nn, $zp`
+    ImmediateZeroPage,
+    /// TST `#$nn, $zp,X`
+    ImmediateZeroPageX,
+    /// TST `#$nn, $nnnn`
+    ImmediateAbsolute,
+    /// TST `#$nn, $nnnn,X`
+    ImmediateAbsoluteX,
+    /// TII/TDD/TIN/TIA/TAI `src, dst, len`
+    BlockTransfer,
+}
+
+impl AddrMode {
+    /// Total instruction length in bytes, including the opcode.
+    pub const fn size(self) -> u8 {
+        match self {
+            Self::Implied | Self::Accumulator => 1,
+            Self::Immediate
+            | Self::MprMask
+            | Self::ZeroPage
+            | Self::ZeroPageX
+            | Self::ZeroPageY
+            | Self::ZeroPageIndirect
+            | Self::ZeroPageIndirectX
+            | Self::ZeroPageIndirectY
+            | Self::Relative => 2,
+            Self::Absolute
+            | Self::AbsoluteX
+            | Self::AbsoluteY
+            | Self::AbsoluteIndirect
+            | Self::AbsoluteIndirectX
+            | Self::ZeroPageRelative
+            | Self::ImmediateZeroPage
+            | Self::ImmediateZeroPageX => 3,
+            Self::ImmediateAbsolute | Self::ImmediateAbsoluteX => 4,
+            Self::BlockTransfer => 7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    /// False for opcodes that are undefined on the HuC6280.
+    pub official: bool,
+}
+
+const fn op(mnemonic: &'static str, mode: AddrMode) -> OpcodeInfo {
+    OpcodeInfo {
+        mnemonic,
+        mode,
+        official: true,
+    }
+}
+
+const fn undoc(mnemonic: &'static str, mode: AddrMode) -> OpcodeInfo {
+    OpcodeInfo {
+        mnemonic,
+        mode,
+        official: false,
+    }
+}
+
+use AddrMode::*;
+
+#[rustfmt::skip]
+pub static OPCODES: [OpcodeInfo; 256] = [
+    // 0x00
+    op("BRK", Implied), op("ORA", ZeroPageIndirectX), op("SXY", Implied), op("ST0", Immediate),
+    op("TSB", ZeroPage), op("ORA", ZeroPage), op("ASL", ZeroPage), op("RMB0", ZeroPage),
+    op("PHP", Implied), op("ORA", Immediate), op("ASL", Accumulator), undoc("ANC", Immediate),
+    op("TSB", Absolute), op("ORA", Absolute), op("ASL", Absolute), op("BBR0", ZeroPageRelative),
+    // 0x10
+    op("BPL", Relative), op("ORA", ZeroPageIndirectY), op("ORA", ZeroPageIndirect), op("ST1", Immediate),
+    op("TRB", ZeroPage), op("ORA", ZeroPageX), op("ASL", ZeroPageX), op("RMB1", ZeroPage),
+    op("CLC", Implied), op("ORA", AbsoluteY), op("INC", Accumulator), undoc("NOP", Implied),
+    op("TRB", Absolute), op("ORA", AbsoluteX), op("ASL", AbsoluteX), op("BBR1", ZeroPageRelative),
+    // 0x20
+    op("JSR", Absolute), op("AND", ZeroPageIndirectX), op("SAX", Implied), op("ST2", Immediate),
+    op("BIT", ZeroPage), op("AND", ZeroPage), op("ROL", ZeroPage), op("RMB2", ZeroPage),
+    op("PLP", Implied), op("AND", Immediate), op("ROL", Accumulator), undoc("ANC", Immediate),
+    op("BIT", Absolute), op("AND", Absolute), op("ROL", Absolute), op("BBR2", ZeroPageRelative),
+    // 0x30
+    op("BMI", Relative), op("AND", ZeroPageIndirectY), op("AND", ZeroPageIndirect), undoc("NOP", Implied),
+    op("BIT", ZeroPageX), op("AND", ZeroPageX), op("ROL", ZeroPageX), op("RMB3", ZeroPage),
+    op("SEC", Implied), op("AND", AbsoluteY), op("DEC", Accumulator), undoc("NOP", Implied),
+    op("BIT", AbsoluteX), op("AND", AbsoluteX), op("ROL", AbsoluteX), op("BBR3", ZeroPageRelative),
+    // 0x40
+    op("RTI", Implied), op("EOR", ZeroPageIndirectX), op("SAY", Implied), op("TMA", MprMask),
+    op("BSR", Relative), op("EOR", ZeroPage), op("LSR", ZeroPage), op("RMB4", ZeroPage),
+    op("PHA", Implied), op("EOR", Immediate), op("LSR", Accumulator), undoc("NOP", Implied),
+    op("JMP", Absolute), op("EOR", Absolute), op("LSR", Absolute), op("BBR4", ZeroPageRelative),
+    // 0x50
+    op("BVC", Relative), op("EOR", ZeroPageIndirectY), op("EOR", ZeroPageIndirect), op("TAM", MprMask),
+    op("CSL", Implied), op("EOR", ZeroPageX), op("LSR", ZeroPageX), op("RMB5", ZeroPage),
+    op("CLI", Implied), op("EOR", AbsoluteY), op("PHY", Implied), undoc("NOP", Implied),
+    undoc("NOP", Implied), op("EOR", AbsoluteX), op("LSR", AbsoluteX), op("BBR5", ZeroPageRelative),
+    // 0x60
+    op("RTS", Implied), op("ADC", ZeroPageIndirectX), op("CLA", Implied), undoc("NOP", Implied),
+    op("STZ", ZeroPage), op("ADC", ZeroPage), op("ROR", ZeroPage), op("RMB6", ZeroPage),
+    op("PLA", Implied), op("ADC", Immediate), op("ROR", Accumulator), undoc("NOP", Implied),
+    op("JMP", AbsoluteIndirect), op("ADC", Absolute), op("ROR", Absolute), op("BBR6", ZeroPageRelative),
+    // 0x70
+    op("BVS", Relative), op("ADC", ZeroPageIndirectY), op("ADC", ZeroPageIndirect), op("TII", BlockTransfer),
+    op("STZ", ZeroPageX), op("ADC", ZeroPageX), op("ROR", ZeroPageX), op("RMB7", ZeroPage),
+    op("SEI", Implied), op("ADC", AbsoluteY), op("PLY", Implied), undoc("RRA", AbsoluteY),
+    op("JMP", AbsoluteIndirectX), op("ADC", AbsoluteX), op("ROR", AbsoluteX), op("BBR7", ZeroPageRelative),
+    // 0x80
+    op("BRA", Relative), op("STA", ZeroPageIndirectX), op("CLX", Implied), op("TST", ImmediateZeroPage),
+    op("STY", ZeroPage), op("STA", ZeroPage), op("STX", ZeroPage), op("SMB0", ZeroPage),
+    op("DEY", Implied), op("BIT", Immediate), op("TXA", Implied), undoc("NOP", Implied),
+    op("STY", Absolute), op("STA", Absolute), op("STX", Absolute), op("BBS0", ZeroPageRelative),
+    // 0x90
+    op("BCC", Relative), op("STA", ZeroPageIndirectY), op("STA", ZeroPageIndirect), op("TST", ImmediateAbsolute),
+    op("STY", ZeroPageX), op("STA", ZeroPageX), op("STX", ZeroPageY), op("SMB1", ZeroPage),
+    op("TYA", Implied), op("STA", AbsoluteY), op("TXS", Implied), undoc("NOP", Implied),
+    op("STZ", Absolute), op("STA", AbsoluteX), op("STZ", AbsoluteX), op("BBS1", ZeroPageRelative),
+    // 0xA0
+    op("LDY", Immediate), op("LDA", ZeroPageIndirectX), op("LDX", Immediate), op("TST", ImmediateZeroPageX),
+    op("LDY", ZeroPage), op("LDA", ZeroPage), op("LDX", ZeroPage), op("SMB2", ZeroPage),
+    op("TAY", Implied), op("LDA", Immediate), op("TAX", Implied), undoc("NOP", Implied),
+    op("LDY", Absolute), op("LDA", Absolute), op("LDX", Absolute), op("BBS2", ZeroPageRelative),
+    // 0xB0
+    op("BCS", Relative), op("LDA", ZeroPageIndirectY), op("LDA", ZeroPageIndirect), op("TST", ImmediateAbsoluteX),
+    op("LDY", ZeroPageX), op("LDA", ZeroPageX), op("LDX", ZeroPageY), op("SMB3", ZeroPage),
+    op("CLV", Implied), op("LDA", AbsoluteY), op("TSX", Implied), undoc("NOP", Implied),
+    op("LDY", AbsoluteX), op("LDA", AbsoluteX), op("LDX", AbsoluteY), op("BBS3", ZeroPageRelative),
+    // 0xC0
+    op("CPY", Immediate), op("CMP", ZeroPageIndirectX), op("CLY", Implied), op("TDD", BlockTransfer),
+    op("CPY", ZeroPage), op("CMP", ZeroPage), op("DEC", ZeroPage), op("SMB4", ZeroPage),
+    op("INY", Implied), op("CMP", Immediate), op("DEX", Implied), undoc("WAI", Implied),
+    op("CPY", Absolute), op("CMP", Absolute), op("DEC", Absolute), op("BBS4", ZeroPageRelative),
+    // 0xD0
+    op("BNE", Relative), op("CMP", ZeroPageIndirectY), op("CMP", ZeroPageIndirect), op("TIN", BlockTransfer),
+    op("CSH", Implied), op("CMP", ZeroPageX), op("DEC", ZeroPageX), op("SMB5", ZeroPage),
+    op("CLD", Implied), op("CMP", AbsoluteY), op("PHX", Implied), undoc("STP", Implied),
+    undoc("NOP", Implied), op("CMP", AbsoluteX), op("DEC", AbsoluteX), op("BBS5", ZeroPageRelative),
+    // 0xE0
+    op("CPX", Immediate), op("SBC", ZeroPageIndirectX), undoc("NOP", Implied), op("TIA", BlockTransfer),
+    op("CPX", ZeroPage), op("SBC", ZeroPage), op("INC", ZeroPage), op("SMB6", ZeroPage),
+    op("INX", Implied), op("SBC", Immediate), op("NOP", Implied), undoc("SBC", Immediate),
+    op("CPX", Absolute), op("SBC", Absolute), op("INC", Absolute), op("BBS6", ZeroPageRelative),
+    // 0xF0
+    op("BEQ", Relative), op("SBC", ZeroPageIndirectY), op("SBC", ZeroPageIndirect), op("TAI", BlockTransfer),
+    op("SET", Implied), op("SBC", ZeroPageX), op("INC", ZeroPageX), op("SMB7", ZeroPage),
+    op("SED", Implied), op("SBC", AbsoluteY), op("PLX", Implied), undoc("NOP", Implied),
+    undoc("NOP", Implied), op("SBC", AbsoluteX), op("INC", AbsoluteX), op("BBS7", ZeroPageRelative),
+];
+
+pub fn opcode_info(opcode: u8) -> OpcodeInfo {
+    OPCODES[opcode as usize]
+}
+
+/// Length in bytes of the instruction starting with `opcode`.
+pub fn instruction_len(opcode: u8) -> u8 {
+    OPCODES[opcode as usize].mode.size()
+}
+
+/// Decoded operand fields with branch targets already resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    None,
+    /// Immediate value, ST0/1/2 value or TAM/TMA mask.
+    Immediate(u8),
+    ZeroPage(u8),
+    Absolute(u16),
+    Branch(u16),
+    /// BBR/BBS: tested zero-page byte and branch target.
+    BitBranch {
+        zp: u8,
+        target: u16,
+    },
+    /// TST: mask and zero-page address.
+    TestZeroPage {
+        mask: u8,
+        zp: u8,
+    },
+    /// TST: mask and absolute address.
+    TestAbsolute {
+        mask: u8,
+        addr: u16,
+    },
+    Block {
+        source: u16,
+        dest: u16,
+        len: u16,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    pub official: bool,
+    pub operand: Operand,
+    bytes: [u8; 7],
+    len: u8,
+}
+
+impl Instruction {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Raw instruction bytes, opcode first.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// Address of the instruction that follows in memory.
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.len as u16)
+    }
+
+    /// Whether the T flag (set by a preceding SET) redirects this
+    /// instruction's accumulator operand to the zero-page byte at X.
+    pub fn uses_t_flag(&self) -> bool {
+        matches!(self.mnemonic, "ADC" | "SBC" | "AND" | "ORA" | "EOR")
+    }
+
+    /// Branch, jump or call destination, if statically known.
+    pub fn branch_target(&self) -> Option<u16> {
+        match (self.operand, self.mnemonic) {
+            (Operand::Branch(target), _) => Some(target),
+            (Operand::BitBranch { target, .. }, _) => Some(target),
+            (Operand::Absolute(addr), "JMP" | "JSR") if self.mode == Absolute => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// Logical address of the memory operand (zero page mapped to $20xx),
+    /// ignoring any index register.
+    pub fn memory_address(&self) -> Option<u16> {
+        match self.operand {
+            Operand::ZeroPage(zp)
+            | Operand::BitBranch { zp, .. }
+            | Operand::TestZeroPage { zp, .. } => Some(ZERO_PAGE_BASE | zp as u16),
+            Operand::Absolute(addr) | Operand::TestAbsolute { addr, .. } => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// True for RTS/RTI/JMP/BRA/BRK/STP, after which execution does not fall
+    /// through to the next instruction.
+    pub fn ends_flow(&self) -> bool {
+        matches!(self.mnemonic, "RTS" | "RTI" | "JMP" | "BRA" | "BRK" | "STP")
+    }
+
+    /// Operand text in assembler syntax, e.g. `$2040,X` or `#$3F`.
+    pub fn operand_text(&self) -> String {
+        self.operand_text_with(|_| None)
+    }
+
+    /// Operand text with addresses replaced by `label(addr)` where it
+    /// returns a name. Zero-page operands are looked up at `$20xx`.
+    pub fn operand_text_with(&self, label: impl Fn(u16) -> Option<String>) -> String {
+        let abs = |a: u16| label(a).unwrap_or_else(|| format!("${a:04X}"));
+        let zp = |z: u8| label(ZERO_PAGE_BASE | z as u16).unwrap_or_else(|| format!("${z:02X}"));
+        match (self.mode, self.operand) {
+            (Implied, _) => String::new(),
+            (Accumulator, _) => "A".to_string(),
+            (Immediate | MprMask, Operand::Immediate(v)) => format!("#${v:02X}"),
+            (ZeroPage, Operand::ZeroPage(z)) => zp(z),
+            (ZeroPageX, Operand::ZeroPage(z)) => format!("{},X", zp(z)),
+            (ZeroPageY, Operand::ZeroPage(z)) => format!("{},Y", zp(z)),
+            (ZeroPageIndirect, Operand::ZeroPage(z)) => format!("({})", zp(z)),
+            (ZeroPageIndirectX, Operand::ZeroPage(z)) => format!("({},X)", zp(z)),
+            (ZeroPageIndirectY, Operand::ZeroPage(z)) => format!("({}),Y", zp(z)),
+            (Absolute, Operand::Absolute(a)) => abs(a),
+            (AbsoluteX, Operand::Absolute(a)) => format!("{},X", abs(a)),
+            (AbsoluteY, Operand::Absolute(a)) => format!("{},Y", abs(a)),
+            (AbsoluteIndirect, Operand::Absolute(a)) => format!("({})", abs(a)),
+            (AbsoluteIndirectX, Operand::Absolute(a)) => format!("({},X)", abs(a)),
+            (Relative, Operand::Branch(t)) => abs(t),
+            (ZeroPageRelative, Operand::BitBranch { zp: z, target }) => {
+                format!("{}, {}", zp(z), abs(target))
+            }
+            (ImmediateZeroPage, Operand::TestZeroPage { mask, zp: z }) => {
+                format!("#${mask:02X}, {}", zp(z))
+            }
+            (ImmediateZeroPageX, Operand::TestZeroPage { mask, zp: z }) => {
+                format!("#${mask:02X}, {},X", zp(z))
+            }
+            (ImmediateAbsolute, Operand::TestAbsolute { mask, addr }) => {
+                format!("#${mask:02X}, {}", abs(addr))
+            }
+            (ImmediateAbsoluteX, Operand::TestAbsolute { mask, addr }) => {
+                format!("#${mask:02X}, {},X", abs(addr))
+            }
+            (BlockTransfer, Operand::Block { source, dest, len }) => {
+                format!("{}, {}, ${len:04X}", abs(source), abs(dest))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Instruction text with operand addresses named from `symbols`,
+    /// resolved against the given MPR mapping.
+    pub fn format_with_symbols(&self, symbols: &SymbolTable, mpr: &[u8; 8]) -> String {
+        let operand = self.operand_text_with(|addr| symbols.lookup(addr, mpr).map(str::to_string));
+        if operand.is_empty() {
+            self.mnemonic.to_string()
+        } else {
+            format!("{} {}", self.mnemonic, operand)
+        }
+    }
+
+    /// One listing line: bank-qualified address, bytes, instruction and,
+    /// for branches and absolute operands, the bank the target resolves to.
+    pub fn listing_line(&self, mpr: &[u8; 8], symbols: Option<&SymbolTable>) -> String {
+        let bytes = self
+            .bytes()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = match symbols {
+            Some(symbols) => self.format_with_symbols(symbols, mpr),
+            None => self.to_string(),
+        };
+        let mut line = format!(
+            "{}  {:<20} {}",
+            BankedAddress::resolve(self.address, mpr),
+            bytes,
+            text
+        );
+        let target = self.branch_target().or(match self.operand {
+            Operand::Absolute(addr) | Operand::TestAbsolute { addr, .. } => Some(addr),
+            _ => None,
+        });
+        if let Some(target) = target {
+            line = format!("{line:<56} ; {}", BankedAddress::resolve(target, mpr));
+        }
+        line
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operand = self.operand_text();
+        if operand.is_empty() {
+            f.write_str(self.mnemonic)
+        } else {
+            write!(f, "{} {}", self.mnemonic, operand)
+        }
+    }
+}
+
+/// A logical address together with the MPR bank it is mapped to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BankedAddress {
+    pub bank: u8,
+    pub addr: u16,
+}
+
+impl BankedAddress {
+    pub fn resolve(addr: u16, mpr: &[u8; 8]) -> Self {
+        Self {
+            bank: mpr[(addr >> 13) as usize],
+            addr,
+        }
+    }
+
+    /// Offset of the address within its 8 KiB bank.
+    pub fn offset(&self) -> u16 {
+        self.addr & 0x1FFF
+    }
+}
+
+impl fmt::Display for BankedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}:{:04X}", self.bank, self.addr)
+    }
+}
+
+/// Decode one instruction at `address`, fetching bytes through `read`.
+pub fn decode_with(address: u16, mut read: impl FnMut(u16) -> u8) -> Instruction {
+    let opcode = read(address);
+    let info = opcode_info(opcode);
+    let len = info.mode.size();
+    let mut bytes = [0u8; 7];
+    bytes[0] = opcode;
+    for (i, slot) in bytes.iter_mut().enumerate().take(len as usize).skip(1) {
+        *slot = read(address.wrapping_add(i as u16));
+    }
+    let word = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let next = address.wrapping_add(len as u16);
+    let relative = |offset: u8| next.wrapping_add(offset as i8 as u16);
+    let operand = match info.mode {
+        Implied | Accumulator => Operand::None,
+        Immediate | MprMask => Operand::Immediate(bytes[1]),
+        ZeroPage | ZeroPageX | ZeroPageY | ZeroPageIndirect | ZeroPageIndirectX
+        | ZeroPageIndirectY => Operand::ZeroPage(bytes[1]),
+        Absolute | AbsoluteX | AbsoluteY | AbsoluteIndirect | AbsoluteIndirectX => {
+            Operand::Absolute(word(1))
+        }
+        Relative => Operand::Branch(relative(bytes[1])),
+        ZeroPageRelative => Operand::BitBranch {
+            zp: bytes[1],
+            target: relative(bytes[2]),
+        },
+        ImmediateZeroPage | ImmediateZeroPageX => Operand::TestZeroPage {
+            mask: bytes[1],
+            zp: bytes[2],
+        },
+        ImmediateAbsolute | ImmediateAbsoluteX => Operand::TestAbsolute {
+            mask: bytes[1],
+            addr: word(2),
+        },
+        BlockTransfer => Operand::Block {
+            source: word(1),
+            dest: word(3),
+            len: word(5),
+        },
+    };
+    Instruction {
+        address,
+        opcode,
+        mnemonic: info.mnemonic,
+        mode: info.mode,
+        official: info.official,
+        operand,
+        bytes,
+        len,
+    }
+}
+
+/// Decode one instruction from `bytes`, which start at logical `address`.
+/// Missing trailing bytes read as zero.
+pub fn decode(address: u16, bytes: &[u8]) -> Instruction {
+    decode_with(address, |addr| {
+        let index = addr.wrapping_sub(address) as usize;
+        bytes.get(index).copied().unwrap_or(0)
+    })
+}
+
+/// Decode `count` consecutive instructions starting at `address`.
+pub fn disassemble_range(
+    address: u16,
+    count: usize,
+    mut read: impl FnMut(u16) -> u8,
+) -> Vec<Instruction> {
+    let mut out = Vec::with_capacity(count);
+    let mut pc = address;
+    for _ in 0..count {
+        let inst = decode_with(pc, &mut read);
+        pc = inst.next_address();
+        out.push(inst);
+    }
+    out
+}
+
+/// Disassemble up to `before` instructions leading up to `pc`, the
+/// instruction at `pc`, and `after` instructions following it.
+///
+/// Code cannot be decoded backwards unambiguously, so this scans forward
+/// from progressively later start points until one lands exactly on `pc`.
+/// If none does, the listing simply starts at `pc`.
+pub fn disassemble_around(
+    pc: u16,
+    before: usize,
+    after: usize,
+    mut read: impl FnMut(u16) -> u8,
+) -> Vec<Instruction> {
+    let mut out = Vec::with_capacity(before + after + 1);
+    let max_back = (before * AddrMode::BlockTransfer.size() as usize).min(0x100);
+    for back in (1..=max_back).rev() {
+        let mut addr = pc.wrapping_sub(back as u16);
+        let mut remaining = back;
+        let mut lead = Vec::new();
+        while remaining > 0 {
+            let inst = decode_with(addr, &mut read);
+            if inst.len() > remaining {
+                break;
+            }
+            remaining -= inst.len();
+            addr = inst.next_address();
+            lead.push(inst);
+        }
+        if remaining == 0 {
+            out.extend_from_slice(&lead[lead.len().saturating_sub(before)..]);
+            break;
+        }
+    }
+    out.extend(disassemble_range(pc, after + 1, read));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_opcode_has_consistent_length() {
+        for opcode in 0..=255u8 {
+            let info = opcode_info(opcode);
+            assert!(!info.mnemonic.is_empty(), "opcode {opcode:02X}");
+            let inst = decode(0xE000, &[opcode, 0, 0, 0, 0, 0, 0]);
+            assert_eq!(inst.len(), info.mode.size() as usize);
+            assert_eq!(inst.bytes()[0], opcode);
+        }
+    }
+
+    #[test]
+    fn decodes_common_instructions() {
+        assert_eq!(decode(0xE000, &[0xA9, 0x3F]).to_string(), "LDA #$3F");
+        assert_eq!(
+            decode(0xE000, &[0xBD, 0x40, 0x20]).to_string(),
+            "LDA $2040,X"
+        );
+        assert_eq!(decode(0xE000, &[0xB1, 0x20]).to_string(), "LDA ($20),Y");
+        assert_eq!(decode(0xE000, &[0x12, 0x20]).to_string(), "ORA ($20)");
+        assert_eq!(
+            decode(0xE000, &[0x7C, 0x00, 0xE1]).to_string(),
+            "JMP ($E100,X)"
+        );
+        assert_eq!(decode(0xE000, &[0x1A]).to_string(), "INC A");
+    }
+
+    #[test]
+    fn decodes_huc6280_specific_instructions() {
+        assert_eq!(decode(0xE000, &[0x03, 0x05]).to_string(), "ST0 #$05");
+        assert_eq!(decode(0xE000, &[0x13, 0x00]).to_string(), "ST1 #$00");
+        assert_eq!(decode(0xE000, &[0x23, 0x7F]).to_string(), "ST2 #$7F");
+        assert_eq!(decode(0xE000, &[0x53, 0x04]).to_string(), "TAM #$04");
+        assert_eq!(decode(0xE000, &[0x43, 0x80]).to_string(), "TMA #$80");
+        assert_eq!(
+            decode(0xE000, &[0x83, 0x10, 0x20]).to_string(),
+            "TST #$10, $20"
+        );
+        assert_eq!(
+            decode(0xE000, &[0xB3, 0x01, 0x00, 0x22]).to_string(),
+            "TST #$01, $2200,X"
+        );
+        assert_eq!(decode(0xE000, &[0x87, 0x30]).to_string(), "SMB0 $30");
+        assert_eq!(decode(0xE000, &[0xF4]).to_string(), "SET");
+        assert!(decode(0xE000, &[0x65, 0x00]).uses_t_flag());
+        assert!(!decode(0xE000, &[0xA5, 0x00]).uses_t_flag());
+    }
+
+    #[test]
+    fn decodes_block_transfers() {
+        for (opcode, name) in [
+            (0x73, "TII"),
+            (0xC3, "TDD"),
+            (0xD3, "TIN"),
+            (0xE3, "TIA"),
+            (0xF3, "TAI"),
+        ] {
+            let inst = decode(0xE000, &[opcode, 0x00, 0x30, 0x02, 0x00, 0x20, 0x00]);
+            assert_eq!(inst.len(), 7);
+            assert_eq!(
+                inst.operand,
+                Operand::Block {
+                    source: 0x3000,
+                    dest: 0x0002,
+                    len: 0x0020
+                }
+            );
+            assert_eq!(inst.to_string(), format!("{name} $3000, $0002, $0020"));
+        }
+    }
+
+    #[test]
+    fn resolves_branch_targets() {
+        let bne = decode(0xC005, &[0xD0, 0xFB]);
+        assert_eq!(bne.branch_target(), Some(0xC002));
+        assert_eq!(bne.to_string(), "BNE $C002");
+
+        let bbr = decode(0xE000, &[0x2F, 0x40, 0x10]);
+        assert_eq!(
+            bbr.operand,
+            Operand::BitBranch {
+                zp: 0x40,
+                target: 0xE013
+            }
+        );
+        assert_eq!(bbr.to_string(), "BBR2 $40, $E013");
+        assert_eq!(bbr.memory_address(), Some(0x2040));
+
+        let bsr = decode(0xE000, &[0x44, 0x80]);
+        assert_eq!(bsr.branch_target(), Some(0xDF82));
+        assert_eq!(
+            decode(0xE000, &[0x20, 0x18, 0xE3]).branch_target(),
+            Some(0xE318)
+        );
+        assert_eq!(decode(0xE000, &[0x6C, 0x18, 0xE3]).branch_target(), None);
+    }
+
+    #[test]
+    fn listing_line_is_bank_qualified() {
+        let mut mpr = [0xFF, 0xF8, 0, 0, 0, 0, 0x05, 0x00];
+        mpr[2] = 0x01;
+        let inst = decode(0xE000, &[0x20, 0x18, 0xC3]);
+        let line = inst.listing_line(&mpr, None);
+        assert!(line.starts_with("00:E000  20 18 C3"), "{line}");
+        assert!(line.contains("JSR $C318"), "{line}");
+        assert!(line.ends_with("; 05:C318"), "{line}");
+        assert_eq!(BankedAddress::resolve(0x4123, &mpr).offset(), 0x0123);
+    }
+
+    #[test]
+    fn symbols_replace_operand_addresses() {
+        let symbols =
+            SymbolTable::parse("00:E318 vsync_wait\nF8:2040 player_x\n05:C000 banked\n").unwrap();
+        let mpr = [0xFF, 0xF8, 0, 0, 0, 0, 0x05, 0x00];
+        let jsr = decode(0xE000, &[0x20, 0x18, 0xE3]);
+        assert_eq!(jsr.format_with_symbols(&symbols, &mpr), "JSR vsync_wait");
+        let lda = decode(0xE000, &[0xB5, 0x40]);
+        assert_eq!(lda.format_with_symbols(&symbols, &mpr), "LDA player_x,X");
+        let tii = decode(0xE000, &[0x73, 0x00, 0xC0, 0x40, 0x20, 0x10, 0x00]);
+        assert_eq!(
+            tii.format_with_symbols(&symbols, &mpr),
+            "TII banked, player_x, $0010"
+        );
+        let other_bank = [0xFF, 0xF8, 0, 0, 0, 0, 0x06, 0x00];
+        assert_eq!(
+            decode(0xE000, &[0x4C, 0x00, 0xC0]).format_with_symbols(&symbols, &other_bank),
+            "JMP $C000"
+        );
+        assert!(
+            jsr.listing_line(&mpr, Some(&symbols))
+                .contains("JSR vsync_wait")
+        );
+    }
+
+    #[test]
+    fn disassemble_range_walks_variable_length_code() {
+        let code = [0xA2, 0x00, 0xE8, 0xE0, 0x10, 0xD0, 0xFB, 0x00];
+        let listing = disassemble_range(0xC000, 5, |addr| {
+            code.get((addr - 0xC000) as usize).copied().unwrap_or(0)
+        });
+        let addrs: Vec<u16> = listing.iter().map(|i| i.address).collect();
+        assert_eq!(addrs, [0xC000, 0xC002, 0xC003, 0xC005, 0xC007]);
+        assert!(listing[4].ends_flow());
+    }
+
+    #[test]
+    fn disassemble_around_resynchronises_before_pc() {
+        // LDA #$A9 / LDA #$A9 / TII ... / NOP: naive backward decoding from
+        // an odd offset would misread the immediates as opcodes.
+        let code = [
+            0xA9, 0xA9, 0xA9, 0xA9, 0x73, 0x00, 0x30, 0x00, 0x22, 0x10, 0x00, 0xEA, 0xEA,
+        ];
+        let read = |addr: u16| {
+            code.get(addr.wrapping_sub(0xC000) as usize)
+                .copied()
+                .unwrap_or(0xEA)
+        };
+        let listing = disassemble_around(0xC00B, 3, 1, read);
+        let addrs: Vec<u16> = listing.iter().map(|i| i.address).collect();
+        assert_eq!(addrs, [0xC000, 0xC002, 0xC004, 0xC00B, 0xC00C]);
+        assert_eq!(listing[2].mnemonic, "TII");
+    }
+}
@@ -184,12 +184,12 @@ impl Emulator {
 
     pub(crate) fn seed_cpu_stack(&mut self) {
         let reset_pc = read_reset_vector(&mut self.bus);
-        if self.bus.read(reset_pc) != 0x40 {
+        if self.bus.peek(reset_pc) != 0x40 {
             return;
         }
 
         let mut entry = self.bus.read_u16(0xFFF8);
-        if !is_valid_reset_vector(entry) || self.bus.read(entry) == 0x00 {
+        if !is_valid_reset_vector(entry) || self.bus.peek(entry) == 0x00 {
             entry = reset_pc.wrapping_add(1);
         }
         let (pcl, pch) = (entry as u8, (entry >> 8) as u8);
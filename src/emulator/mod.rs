@@ -4,9 +4,16 @@ mod hucard;
 #[cfg(test)]
 mod tests;
 
-use crate::bus::{Bus, CompatBusStateV1, IRQ_REQUEST_TIMER};
+use crate::bus::{
+    AudioChannels, BatMap, Bus, CompatBusStateV1, CropMargins, DotClock, IRQ_REQUEST_TIMER,
+    LayerToggles, OutputGeometry, PixelFormat, RgbaImage, SpriteEntry,
+};
+use crate::cdl::CodeDataLog;
+use crate::cheat::{CheatManager, RomPatch};
 use crate::cpu::Cpu;
-use crate::debugger::{DebugBreak, DebugTick, Debugger};
+use crate::debugger::{DebugBreak, DebugTick, Debugger, MachineView};
+use crate::profile::{Profiler, ProfilerSlot};
+use crate::trace::{Tracer, TracerSlot};
 use hucard::{ParsedHuCard, RESET_VECTOR_LEGACY, RESET_VECTOR_PRIMARY};
 use std::error::Error;
 
@@ -17,6 +24,8 @@ pub struct Emulator {
     cycles: u64,
     audio_buffer: Vec<i16>,
     audio_batch_size: usize,
+    tracer: TracerSlot,
+    profiler: ProfilerSlot,
 }
 
 #[derive(Clone, bincode::Encode, bincode::Decode)]
@@ -36,6 +45,8 @@ impl Emulator {
             cycles: 0,
             audio_buffer: Vec::new(),
             audio_batch_size: 1024,
+            tracer: TracerSlot::default(),
+            profiler: ProfilerSlot::default(),
         }
     }
 
@@ -57,7 +68,9 @@ impl Emulator {
     pub fn load_hucard(&mut self, image: &[u8]) -> Result<(), Box<dyn Error>> {
         let parsed = ParsedHuCard::from_bytes(image)?;
         let ParsedHuCard { rom, header } = parsed;
-        self.bus = Bus::new();
+        let log_rom_usage = self.bus.code_data_log().is_enabled();
+        let previous = std::mem::replace(&mut self.bus, Bus::new());
+        self.bus.inherit_host_settings(&previous);
         self.audio_buffer.clear();
         let backup_bytes = header
             .as_ref()
@@ -68,6 +81,9 @@ impl Emulator {
         );
         self.bus.configure_cart_ram(backup_bytes);
         self.bus.load_rom_image(rom);
+        if log_rom_usage {
+            self.bus.set_code_data_log_enabled(true);
+        }
 
         let pages = self.bus.rom_page_count();
         if pages == 0 {
@@ -96,6 +112,18 @@ impl Emulator {
     }
 
     pub fn tick(&mut self) -> u32 {
+        if let Some(tracer) = self.tracer.0.as_deref_mut()
+            && !self.cpu.halted
+        {
+            let interrupt = self.cpu.pending_interrupt(&self.bus);
+            if interrupt.is_some() || !self.cpu.is_waiting() {
+                tracer.before_instruction(&self.cpu, &self.bus, self.cycles, interrupt);
+            }
+        }
+        if let Some(profiler) = self.profiler.0.as_deref_mut() {
+            profiler.before_instruction(&self.cpu, &self.bus);
+        }
+        self.bus.note_instruction_pc(self.cpu.pc);
         let cycles = self.cpu.step(&mut self.bus);
         #[cfg(feature = "trace_hw_writes")]
         self.bus.set_last_pc_for_trace(self.cpu.pc);
@@ -109,6 +137,14 @@ impl Emulator {
             self.cycles += 1;
         }
         self.bus.tick(bus_cycles, self.cpu.clock_high_speed);
+        if let Some(profiler) = self.profiler.0.as_deref_mut() {
+            let spent = if cycles > 0 || self.cpu.is_waiting() {
+                bus_cycles as u64
+            } else {
+                0
+            };
+            profiler.after_instruction(&self.cpu, &self.bus, spent);
+        }
         let mut chunk = self.bus.take_audio_samples();
         if !chunk.is_empty() {
             self.audio_buffer.append(&mut chunk);
@@ -120,20 +156,36 @@ impl Emulator {
         if debugger.paused {
             return DebugTick::Paused;
         }
+        let track_calls =
+            debugger.track_calls || debugger.needs_call_stack() || self.profiler.0.is_some();
+        if self.cpu.call_stack().is_enabled() != track_calls {
+            self.cpu.set_call_stack_enabled(track_calls);
+        }
 
         let pc = self.cpu.pc;
-        if debugger.breakpoints.contains(&pc) {
+        let view = MachineView {
+            cpu: &self.cpu,
+            bus: &self.bus,
+        };
+        if debugger.check_breakpoint(pc, &view) {
             debugger.paused = true;
             let br = DebugBreak::Breakpoint(pc);
             debugger.last_break = Some(br);
             return DebugTick::Break(br);
         }
 
+        let opcode = self.bus.peek(pc);
+        let depth = self.cpu.call_stack().depth();
+        if let Some(br) = debugger.before_instruction(pc, opcode, self.cpu.sp, depth) {
+            debugger.paused = true;
+            debugger.last_break = Some(br);
+            return DebugTick::Break(br);
+        }
+
         let cycles = self.tick();
-        if debugger.step_pending() {
-            debugger.clear_step_pending();
+        let depth = self.cpu.call_stack().depth();
+        if let Some(br) = debugger.after_instruction(self.cpu.pc, depth) {
             debugger.paused = true;
-            let br = DebugBreak::Step(self.cpu.pc);
             debugger.last_break = Some(br);
             DebugTick::Break(br)
         } else {
@@ -168,10 +220,63 @@ impl Emulator {
         self.cycles
     }
 
+    /// Record every following instruction into `tracer`, returning the
+    /// previously attached one.
+    pub fn attach_tracer(&mut self, tracer: Tracer) -> Option<Tracer> {
+        self.tracer.0.replace(Box::new(tracer)).map(|t| *t)
+    }
+
+    pub fn detach_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.0.take().map(|t| *t)
+    }
+
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.0.as_deref()
+    }
+
+    pub fn tracer_mut(&mut self) -> Option<&mut Tracer> {
+        self.tracer.0.as_deref_mut()
+    }
+
+    /// Charge every following CPU cycle to `profiler`, returning the
+    /// previously attached one. Call-stack tracking is switched on so the
+    /// profiler can attribute cycles to routines.
+    pub fn attach_profiler(&mut self, profiler: Profiler) -> Option<Profiler> {
+        self.cpu.set_call_stack_enabled(true);
+        self.profiler.0.replace(Box::new(profiler)).map(|p| *p)
+    }
+
+    pub fn detach_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.0.take().map(|p| *p)
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.0.as_deref()
+    }
+
+    pub fn profiler_mut(&mut self) -> Option<&mut Profiler> {
+        self.profiler.0.as_deref_mut()
+    }
+
+    /// Sample frames per batch from [`take_audio_samples`](Self::take_audio_samples).
+    /// A stereo batch holds two values per frame.
     pub fn set_audio_batch_size(&mut self, samples: usize) {
         self.audio_batch_size = samples.max(1);
     }
 
+    pub fn audio_channels(&self) -> AudioChannels {
+        self.bus.audio_channels()
+    }
+
+    /// Choose a mono downmix (the default) or interleaved stereo.  Samples
+    /// already queued in the old layout are dropped.
+    pub fn set_audio_channels(&mut self, channels: AudioChannels) {
+        if self.bus.audio_channels() != channels {
+            self.bus.set_audio_channels(channels);
+            self.audio_buffer.clear();
+        }
+    }
+
     pub fn set_video_output_enabled(&mut self, enabled: bool) {
         self.bus.set_video_output_enabled(enabled);
     }
@@ -223,11 +328,14 @@ impl Emulator {
         Ok(())
     }
 
+    /// The next full batch, interleaved left/right unless the mono downmix
+    /// is selected.
     pub fn take_audio_samples(&mut self) -> Option<Vec<i16>> {
-        if self.audio_buffer.len() < self.audio_batch_size {
+        let batch_len = self.audio_batch_size * self.bus.audio_channels().count();
+        if self.audio_buffer.len() < batch_len {
             return None;
         }
-        let tail = self.audio_buffer.split_off(self.audio_batch_size);
+        let tail = self.audio_buffer.split_off(batch_len);
         Some(std::mem::replace(&mut self.audio_buffer, tail))
     }
 
@@ -235,8 +343,9 @@ impl Emulator {
         std::mem::take(&mut self.audio_buffer)
     }
 
+    /// Queued sample frames not yet handed out.
     pub fn pending_audio_samples(&self) -> usize {
-        self.audio_buffer.len()
+        self.audio_buffer.len() / self.bus.audio_channels().count()
     }
 
     /// Copy the current frame into `buf`, reusing its allocation.
@@ -265,6 +374,114 @@ impl Emulator {
         self.bus.display_y_offset()
     }
 
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.bus.pixel_format()
+    }
+
+    /// Layout of the `u32` pixels in frames and the framebuffer; see
+    /// [`PixelFormat`].  Kept across ROM and state loads.
+    pub fn set_pixel_format(&mut self, format: PixelFormat) {
+        self.bus.set_pixel_format(format);
+    }
+
+    pub fn output_geometry(&self) -> OutputGeometry {
+        self.bus.output_geometry()
+    }
+
+    /// Size frames by the active display (the default) or emit a fixed
+    /// canvas whatever HDR/VDW say.  Like the other display settings this
+    /// is kept across ROM and state loads.
+    pub fn set_output_geometry(&mut self, geometry: OutputGeometry) {
+        self.bus.set_output_geometry(geometry);
+    }
+
+    pub fn crop_margins(&self) -> CropMargins {
+        self.bus.crop_margins()
+    }
+
+    /// Trim frames on each edge after the output geometry is applied.
+    pub fn set_crop_margins(&mut self, crop: CropMargins) {
+        self.bus.set_crop_margins(crop);
+    }
+
+    /// The whole background map with the on-screen area marked; see
+    /// [`BatMap`].
+    pub fn render_bat_map(&self) -> BatMap {
+        self.bus.render_bat_map()
+    }
+
+    /// All 64 SATB entries, decoded; see [`SpriteEntry`].
+    pub fn sprite_entries(&self) -> Vec<SpriteEntry> {
+        self.bus.sprite_entries()
+    }
+
+    /// SATB entry `index` drawn on its own, with colour 0 transparent.
+    pub fn render_sprite(&self, index: usize) -> RgbaImage {
+        self.bus.render_sprite(index)
+    }
+
+    /// What the renderer currently draws; see [`LayerToggles`].
+    pub fn layer_toggles(&self) -> LayerToggles {
+        self.bus.layer_toggles()
+    }
+
+    /// Replace every layer toggle at once, e.g. from a settings panel.
+    pub fn set_layer_toggles(&mut self, layers: LayerToggles) {
+        self.bus.set_layer_toggles(layers);
+    }
+
+    /// Show or hide the background layer.  Hidden BG shows the backdrop.
+    pub fn set_background_layer_enabled(&mut self, enabled: bool) {
+        self.bus
+            .update_layer_toggles(|layers| layers.background = enabled);
+    }
+
+    pub fn set_sprite_layer_enabled(&mut self, enabled: bool) {
+        self.bus
+            .update_layer_toggles(|layers| layers.sprites = enabled);
+    }
+
+    /// Show or hide SATB entry `index` (0-63).
+    pub fn set_sprite_enabled(&mut self, index: usize, enabled: bool) {
+        self.bus
+            .update_layer_toggles(|layers| layers.set_sprite_visible(index, enabled));
+    }
+
+    /// With priority off every sprite is drawn in front of the background.
+    pub fn set_sprite_priority_enabled(&mut self, enabled: bool) {
+        self.bus
+            .update_layer_toggles(|layers| layers.sprite_priority = enabled);
+    }
+
+    /// With the limit off every sprite on a line is drawn; the overflow
+    /// flag is still raised as the VDC would.
+    pub fn set_sprite_line_limit_enabled(&mut self, enabled: bool) {
+        self.bus
+            .update_layer_toggles(|layers| layers.sprite_line_limit = enabled);
+    }
+
+    /// Pixel clock the VCE is running at.
+    pub fn dot_clock(&self) -> DotClock {
+        self.bus.dot_clock()
+    }
+
+    /// Width/height of one output pixel on a 4:3 display: 8:7 at 5.37 MHz,
+    /// 6:7 at 7.16 MHz and 4:7 at 10.74 MHz.  Scale a frame's width by this
+    /// to show it at the right shape.
+    pub fn pixel_aspect_ratio(&self) -> f64 {
+        self.bus.dot_clock().pixel_aspect_ratio()
+    }
+
+    /// Scanlines per field: 262, or 263 in the VCE's 263-line mode.
+    pub fn frame_lines(&self) -> u16 {
+        self.bus.frame_lines()
+    }
+
+    /// Whether the VCE's colour-burst strip bit is set (grayscale output).
+    pub fn grayscale(&self) -> bool {
+        self.bus.grayscale()
+    }
+
     pub fn backup_ram(&self) -> Option<&[u8]> {
         self.bus.cart_ram()
     }
@@ -303,6 +520,41 @@ impl Emulator {
         self.bus.bram().to_vec()
     }
 
+    /// Start or stop the ROM code/data logger (see [`crate::cdl`]).
+    pub fn set_code_data_log_enabled(&mut self, enabled: bool) {
+        self.bus.set_code_data_log_enabled(enabled);
+    }
+
+    pub fn code_data_log(&self) -> &CodeDataLog {
+        self.bus.code_data_log()
+    }
+
+    /// Merge a previously saved `.cdl` file and enable logging.
+    pub fn load_code_data_log(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.bus.set_code_data_log_enabled(true);
+        self.bus.code_data_log_mut().merge(data)?;
+        Ok(())
+    }
+
+    /// Contents for a `.cdl` file, if logging is enabled.
+    pub fn save_code_data_log(&self) -> Option<Vec<u8>> {
+        let log = self.bus.code_data_log();
+        log.is_enabled().then(|| log.flags().to_vec())
+    }
+
+    /// Apply enabled cheats: RAM cheats write work RAM and cart RAM (one
+    /// buffer, cart RAM after the work page), ROM cheats replace the bytes
+    /// substituted on ROM reads. Call once per frame or host iteration.
+    pub fn apply_cheats(&mut self, cheats: &mut CheatManager) {
+        let (work_ram, cart_ram) = self.bus.work_and_cart_ram_mut();
+        cheats.apply_split(work_ram, cart_ram);
+        self.bus.set_rom_patches(cheats.rom_patches());
+    }
+
+    pub fn rom_patches(&self) -> &[RomPatch] {
+        self.bus.rom_patches()
+    }
+
     pub fn work_ram(&self) -> &[u8] {
         self.bus.work_ram()
     }
@@ -317,6 +569,14 @@ impl Emulator {
         state.audio_batch_size = self.audio_batch_size;
         state.audio_buffer.clear();
         let _ = state.bus.take_audio_samples();
+        state.tracer = std::mem::take(&mut self.tracer);
+        state.profiler = std::mem::take(&mut self.profiler);
+        if state.profiler.0.is_some() {
+            state.cpu.set_call_stack_enabled(true);
+        }
+        *state.bus.code_data_log_mut() = std::mem::take(self.bus.code_data_log_mut());
+        state.bus.restore_rom_patches(self.bus.take_rom_patches());
+        state.bus.inherit_host_settings(&self.bus);
         *self = state;
     }
 }
@@ -329,6 +589,8 @@ impl From<CompatEmulatorStateV1> for Emulator {
             cycles: value.cycles,
             audio_buffer: value.audio_buffer,
             audio_batch_size: value.audio_batch_size,
+            tracer: TracerSlot::default(),
+            profiler: ProfilerSlot::default(),
         }
     }
 }
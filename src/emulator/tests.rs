@@ -1,5 +1,5 @@
 use super::*;
-use crate::bus::PAGE_SIZE;
+use crate::bus::{PAGE_SIZE, PaletteWriter};
 use crate::vdc::VDC_VBLANK_INTERVAL;
 use hucard::{HUCARD_HEADER_SIZE, HUCARD_MAGIC_HI, HUCARD_MAGIC_LO, HUCARD_TYPE_PCE, HucardHeader};
 
@@ -124,28 +124,29 @@ fn bram_round_trip_via_emulator_api() {
     assert_eq!(emu.bus.read(0x4000), 0x5A);
 }
 
+// Starts the timer, waits for its IRQ and counts it at $4000.
+const TIMER_IRQ_PROGRAM: [u8; 26] = [
+    // Set MPR[0]=$FF for I/O access at $0000-$1FFF
+    0xA9, 0xFF, // LDA #$FF
+    0x53, 0x01, // TAM #$01 (MPR[0] = $FF)
+    0xA9, 0x04, // LDA #$04 (timer reload)
+    0x8D, 0x00, 0x0C, // STA $0C00
+    0xA9, 0x01, // LDA #$01 (start timer)
+    0x8D, 0x01, 0x0C, // STA $0C01
+    0x58, // CLI
+    0xCB, // WAI
+    0x00, // BRK
+    // IRQ handler immediately after the main routine:
+    0xAD, 0x00, 0x40, // LDA $4000
+    0x69, 0x01, // ADC #$01
+    0x8D, 0x00, 0x40, // STA $4000
+    0x40, // RTI
+];
+
 #[test]
 fn wai_unblocks_when_timer_irq_fires() {
-    let program = [
-        // Set MPR[0]=$FF for I/O access at $0000-$1FFF
-        0xA9, 0xFF, // LDA #$FF
-        0x53, 0x01, // TAM #$01 (MPR[0] = $FF)
-        0xA9, 0x04, // LDA #$04 (timer reload)
-        0x8D, 0x00, 0x0C, // STA $0C00
-        0xA9, 0x01, // LDA #$01 (start timer)
-        0x8D, 0x01, 0x0C, // STA $0C01
-        0x58, // CLI
-        0xCB, // WAI
-        0x00, // BRK
-        // IRQ handler immediately after the main routine:
-        0xAD, 0x00, 0x40, // LDA $4000
-        0x69, 0x01, // ADC #$01
-        0x8D, 0x00, 0x40, // STA $4000
-        0x40, // RTI
-    ];
-
     let mut emu = Emulator::new();
-    emu.load_program(0x8000, &program);
+    emu.load_program(0x8000, &TIMER_IRQ_PROGRAM);
     emu.bus.write_u16(0xFFFA, 0x8011);
     emu.reset();
 
@@ -154,6 +155,33 @@ fn wai_unblocks_when_timer_irq_fires() {
     assert!(emu.bus.read(0x4000) > 0);
 }
 
+#[test]
+fn tracer_records_interrupt_dispatch_before_the_handler() {
+    use crate::debugger::FrameKind;
+    use crate::trace::Tracer;
+
+    let mut emu = Emulator::new();
+    emu.load_program(0x8000, &TIMER_IRQ_PROGRAM);
+    emu.bus.write_u16(0xFFFA, 0x8011);
+    emu.reset();
+    emu.attach_tracer(Tracer::ring(64));
+    emu.run_until_halt(Some(10_000));
+
+    let tracer = emu.detach_tracer().unwrap();
+    let entries: Vec<_> = tracer.entries().collect();
+    let dispatch = entries
+        .iter()
+        .position(|entry| entry.interrupt.is_some())
+        .unwrap();
+    assert_eq!(entries[dispatch].interrupt, Some(FrameKind::Timer));
+    assert!(entries[dispatch].opcode_bytes().is_empty());
+    // WAI ran, the timer fired, then the handler's first instruction.
+    assert_eq!(entries[dispatch - 1].pc, 0x800F);
+    assert_eq!(entries[dispatch + 1].pc, 0x8011);
+    assert_eq!(entries[dispatch + 1].interrupt, None);
+    assert_eq!(entries.iter().filter(|entry| entry.pc == 0x800F).count(), 1);
+}
+
 #[test]
 fn load_state_accepts_legacy_truncated_payload() {
     let mut emu = Emulator::new();
@@ -226,6 +254,33 @@ fn load_state_accepts_previous_render_cache_layout() {
     assert_eq!(restored.bus.vdc_register(0x0B), Some(0x5678));
 }
 
+#[test]
+fn loaded_states_and_reset_agree_on_field_length() {
+    let emu = Emulator::new();
+    assert_eq!(emu.frame_lines(), 262);
+    let compat = CompatEmulatorStateV1 {
+        cpu: emu.cpu.clone(),
+        bus: emu.bus.compat_state_v1(),
+        cycles: emu.cycles,
+        audio_buffer: emu.audio_buffer.clone(),
+        audio_batch_size: emu.audio_batch_size,
+    };
+    let bytes = bincode::encode_to_vec(&compat, bincode::config::standard()).unwrap();
+    let path = std::env::temp_dir().join(format!("pce_field_length_{}.state", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+
+    let mut restored = Emulator::new();
+    restored.bus.write_io(0x0400, 0x04);
+    assert_eq!(restored.frame_lines(), 263);
+    let load_result = restored.load_state_from_file(&path);
+    let _ = std::fs::remove_file(&path);
+    assert!(load_result.is_ok(), "compat load should succeed");
+    assert_eq!(restored.frame_lines(), 262);
+
+    restored.bus.clear();
+    assert_eq!(restored.frame_lines(), 262);
+}
+
 #[test]
 fn load_state_invalidates_current_render_cache_round_trip() {
     let mut emu = Emulator::new();
@@ -275,3 +330,310 @@ fn load_state_invalidates_current_render_cache_round_trip() {
     }
     assert!(fresh_frame, "loaded emulator should produce a fresh frame");
 }
+
+// LDX #$00; loop: INX; CPX #$10; BNE loop; BRK
+const TRACE_LOOP: [u8; 8] = [0xA2, 0x00, 0xE8, 0xE0, 0x10, 0xD0, 0xFB, 0x00];
+
+#[test]
+fn ring_tracer_records_window_between_triggers() {
+    use crate::trace::{TraceTrigger, Tracer};
+
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &TRACE_LOOP);
+    emu.reset();
+    let tracer = Tracer::ring(4)
+        .start_when(TraceTrigger::Pc(0xC005))
+        .stop_when(TraceTrigger::Count(6));
+    assert!(emu.attach_tracer(tracer).is_none());
+    emu.run_until_halt(Some(1_000));
+
+    let tracer = emu.detach_tracer().unwrap();
+    assert!(tracer.is_finished());
+    assert_eq!(tracer.recorded(), 6);
+    let entries: Vec<_> = tracer.entries().collect();
+    assert_eq!(entries.len(), 4);
+    // BNE, INX, CPX, BNE ... ring keeps the last four of six.
+    let pcs: Vec<u16> = entries.iter().map(|e| e.pc).collect();
+    assert_eq!(pcs, [0xC003, 0xC005, 0xC002, 0xC003]);
+    assert_eq!(entries[1].opcode_bytes(), [0xD0, 0xFB]);
+    assert_eq!(entries[1].x, 2);
+    assert_eq!(entries[1].bank, emu.bus.mpr(6));
+    assert!(entries[1].cycles < entries[3].cycles);
+}
+
+#[test]
+fn count_start_trigger_is_ignored() {
+    use crate::trace::{TraceTrigger, Tracer};
+
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &TRACE_LOOP);
+    emu.reset();
+    let tracer = Tracer::ring(4)
+        .start_when(TraceTrigger::Count(3))
+        .stop_when(TraceTrigger::Count(3));
+    emu.attach_tracer(tracer);
+    emu.run_until_halt(Some(1_000));
+
+    let tracer = emu.detach_tracer().unwrap();
+    assert_eq!(tracer.recorded(), 3);
+    assert_eq!(tracer.entries().next().map(|e| e.pc), Some(0xC000));
+}
+
+#[test]
+fn streaming_tracer_writes_text_lines_and_survives_state_load() {
+    use crate::trace::Tracer;
+
+    let path = std::env::temp_dir().join(format!("pce_trace_{}.log", std::process::id()));
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &TRACE_LOOP);
+    emu.reset();
+    emu.attach_tracer(Tracer::to_file(&path).unwrap());
+    for _ in 0..3 {
+        emu.tick();
+    }
+
+    // Save states neither carry nor drop the attached tracer.
+    let bytes = bincode::encode_to_vec(&emu, bincode::config::standard()).unwrap();
+    let state_path = path.with_extension("state");
+    std::fs::write(&state_path, &bytes).unwrap();
+    emu.load_state_from_file(&state_path).unwrap();
+    let _ = std::fs::remove_file(&state_path);
+    emu.tick();
+
+    let mut tracer = emu.detach_tracer().unwrap();
+    tracer.flush().unwrap();
+    drop(tracer);
+    let text = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].contains(":C000  A2 00"), "{}", lines[0]);
+    assert!(lines[0].contains("LDX #$00"), "{}", lines[0]);
+    assert!(lines[3].contains(":C005  D0 FB"), "{}", lines[3]);
+    assert!(lines[3].contains("X:01"), "{}", lines[3]);
+}
+
+#[test]
+fn code_data_log_classifies_rom_bytes() {
+    use crate::cdl::{CDL_BLOCK_SOURCE, CDL_DATA, CDL_OPCODE, CDL_OPERAND, CDL_VRAM_SOURCE};
+
+    let mut rom = vec![0u8; PAGE_SIZE * 4];
+    rom[PAGE_SIZE - 2] = 0x00;
+    rom[PAGE_SIZE - 1] = 0xE1;
+    let program = [
+        0xA9, 0x3F, // LDA #$3F
+        0xAD, 0x00, 0xE8, // LDA $E800
+        0x73, 0x10, 0xE8, 0x00, 0x22, 0x04, 0x00, // TII $E810, $2200, 4
+        0xE3, 0x20, 0xE8, 0x02, 0x00, 0x04, 0x00, // TIA $E820, $0002, 4
+        0x80, 0xFE, // BRA *
+    ];
+    rom[0x100..0x100 + program.len()].copy_from_slice(&program);
+
+    let mut emu = Emulator::new();
+    emu.set_code_data_log_enabled(true);
+    emu.load_hucard(&rom).unwrap();
+    emu.reset();
+    assert_eq!(emu.cpu.pc, 0xE100);
+    emu.bus.set_mpr(0, 0xFF);
+    for _ in 0..6 {
+        emu.tick();
+    }
+
+    let flags = emu.code_data_log().flags();
+    assert_eq!(flags.len(), rom.len());
+    assert_eq!(flags[0x100], CDL_OPCODE);
+    assert_eq!(flags[0x101], CDL_OPERAND);
+    assert_eq!(flags[0x103], CDL_OPERAND);
+    assert_eq!(flags[0x105], CDL_OPCODE);
+    assert_eq!(flags[0x800], CDL_DATA);
+    assert_eq!(flags[0x810..0x814], [CDL_BLOCK_SOURCE; 4]);
+    assert_eq!(flags[0x814], 0);
+    assert_eq!(flags[0x820..0x824], [CDL_BLOCK_SOURCE | CDL_VRAM_SOURCE; 4]);
+    assert_eq!(flags[0x200], 0);
+
+    let saved = emu.save_code_data_log().unwrap();
+    let mut fresh = Emulator::new();
+    fresh.load_hucard(&rom).unwrap();
+    assert!(fresh.save_code_data_log().is_none());
+    fresh.load_code_data_log(&saved).unwrap();
+    assert_eq!(fresh.code_data_log().summary().opcode, 5);
+    assert!(fresh.load_code_data_log(&saved[..16]).is_err());
+}
+
+#[test]
+fn profiler_splits_wai_interrupt_and_block_transfer_cycles() {
+    use crate::profile::Profiler;
+    use crate::symbols::SymbolTable;
+
+    let program = [
+        0x20, 0x20, 0x80, // JSR $8020
+        0xA9, 0xFF, // LDA #$FF
+        0x53, 0x01, // TAM #$01
+        0xA9, 0x04, // LDA #$04
+        0x8D, 0x00, 0x0C, // STA $0C00
+        0xA9, 0x01, // LDA #$01
+        0x8D, 0x01, 0x0C, // STA $0C01
+        0x58, // CLI
+        0xCB, // WAI
+        0x00, // BRK
+        // $8014: timer handler
+        0xEE, 0x00, 0x40, // INC $4000
+        0x40, // RTI
+        0xEA, 0xEA, 0xEA, 0xEA, 0xEA, 0xEA, 0xEA, 0xEA, // padding
+        // $8020: copy routine
+        0x73, 0x00, 0x40, 0x10, 0x40, 0x20, 0x00, // TII $4000,$4010,#$20
+        0x60, // RTS
+    ];
+    let mut emu = Emulator::new();
+    emu.load_program(0x8000, &program);
+    emu.bus.write_u16(0xFFFA, 0x8014);
+    emu.reset();
+    let bank = emu.bus.mpr(4);
+    let symbols = SymbolTable::parse(&format!("{bank:02X}:8020 copy\n8014 timer_isr\n")).unwrap();
+    assert!(
+        emu.attach_profiler(Profiler::new().with_symbols(symbols))
+            .is_none()
+    );
+    emu.run_until_halt(Some(20_000));
+    assert!(emu.cpu.halted);
+
+    let profiler = emu.detach_profiler().unwrap();
+    let totals = profiler.totals();
+    // TII costs 17 + 6 per byte.
+    assert_eq!(totals.block_transfer, 17 + 6 * 0x20);
+    assert_eq!(profiler.cycles_at(0x8020), totals.block_transfer);
+    assert_eq!(
+        profiler.cycles_at_banked(bank, 0x8020),
+        totals.block_transfer
+    );
+    assert!(totals.wai > 0);
+    assert!(totals.interrupt > profiler.cycles_at(0x8014));
+    assert!(profiler.cycles_at(0x8014) > 0);
+    assert_eq!(
+        totals.total,
+        profiler.frames().iter().map(|f| f.total).sum::<u64>() + profiler.current_frame().total
+    );
+    assert_eq!(profiler.hotspots(1)[0].addr, 0x8020);
+
+    let mut report = Vec::new();
+    profiler.write_report(&mut report, 5).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    assert!(
+        report.contains(&format!("{bank:02X}:8020  copy")),
+        "{report}"
+    );
+
+    let mut collapsed = Vec::new();
+    profiler.write_collapsed(&mut collapsed).unwrap();
+    let collapsed = String::from_utf8(collapsed).unwrap();
+    let lines: Vec<&str> = collapsed.lines().collect();
+    assert!(
+        lines.contains(&format!("main;copy {}", 17 + 6 * 0x20 + 7).as_str()),
+        "{collapsed}"
+    );
+    assert!(
+        lines
+            .iter()
+            .any(|l| l.starts_with("main;[TIMER] timer_isr ")),
+        "{collapsed}"
+    );
+    assert!(
+        lines.iter().any(|l| l.starts_with("main;[wai] ")),
+        "{collapsed}"
+    );
+}
+
+#[test]
+fn pixel_aspect_ratio_follows_vce_dot_clock() {
+    let mut emu = Emulator::new();
+    let ratios: Vec<f64> = [0x00, 0x01, 0x02]
+        .into_iter()
+        .map(|control| {
+            emu.bus.write_io(0x0400, control);
+            emu.pixel_aspect_ratio()
+        })
+        .collect();
+    assert_eq!(emu.dot_clock(), DotClock::Mhz10);
+    assert!((ratios[0] - 8.0 / 7.0).abs() < 1e-9);
+    assert!((ratios[1] - 6.0 / 7.0).abs() < 1e-9);
+    assert!((ratios[2] - 4.0 / 7.0).abs() < 1e-9);
+    // Each mode's usual width shows at about the same physical size.
+    for (width, ratio) in [256.0, 336.0, 512.0].into_iter().zip(ratios) {
+        assert!((width * ratio - 292.0).abs() < 5.0);
+    }
+}
+
+#[test]
+fn output_settings_survive_rom_and_state_loads() {
+    let crop = CropMargins {
+        left: 8,
+        right: 8,
+        top: 0,
+        bottom: 0,
+    };
+    let mut emu = Emulator::new();
+    emu.set_output_geometry(OutputGeometry::FULL_OVERSCAN);
+    emu.set_crop_margins(crop);
+    emu.load_hucard(&vec![0xFF; PAGE_SIZE * 8]).unwrap();
+    assert_eq!(emu.output_geometry(), OutputGeometry::FULL_OVERSCAN);
+    assert_eq!((emu.display_width(), emu.display_height()), (326, 242));
+
+    let bytes = bincode::encode_to_vec(&emu, bincode::config::standard()).unwrap();
+    let path = std::env::temp_dir().join(format!("pce_output_{}.state", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    emu.load_state_from_file(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(emu.output_geometry(), OutputGeometry::FULL_OVERSCAN);
+    assert_eq!(emu.crop_margins(), crop);
+}
+
+#[test]
+fn palette_writes_are_credited_to_the_writing_instruction() {
+    let mut emu = Emulator::new();
+    let program = [
+        0xA9, 0x05, // LDA #$05
+        0x8D, 0x02, 0x04, // STA $0402 (VCE address)
+        0xA9, 0x3F, // LDA #$3F
+        0x8D, 0x04, 0x04, // STA $0404 (VCE data low)
+        0x00,
+    ];
+
+    emu.load_program(0xC000, &program);
+    emu.reset();
+    emu.bus.set_mpr(0, 0xFF);
+    emu.run_until_halt(Some(20));
+
+    assert_eq!(emu.bus.vce_palette_word(0x005), 0x003F);
+    assert_eq!(
+        emu.bus.vce_palette_writer(0x005),
+        Some(PaletteWriter::Cpu(0xC007))
+    );
+    assert_eq!(emu.bus.vce_palette_writer(0x006), None);
+
+    emu.bus.set_vce_palette_word(0x005, 0xFFC0);
+    assert_eq!(emu.bus.vce_palette_word(0x005), 0x01C0);
+    assert_eq!(emu.bus.vce_palette_rgb(0x005), 0x0000_FF00);
+    assert_eq!(emu.bus.vce_palette_writer(0x005), Some(PaletteWriter::Host));
+}
+
+#[test]
+fn audio_batches_count_whole_sample_frames() {
+    let mut emu = Emulator::new();
+    emu.load_program(0xC000, &[0x4C, 0x00, 0xC0]); // JMP $C000
+    emu.reset();
+    emu.set_audio_batch_size(64);
+    emu.set_audio_channels(AudioChannels::Stereo);
+    while emu.pending_audio_samples() < 100 {
+        emu.tick();
+    }
+
+    let batch = emu.take_audio_samples().unwrap();
+    assert_eq!(batch.len(), 128);
+
+    emu.set_audio_channels(AudioChannels::Mono);
+    assert_eq!(emu.pending_audio_samples(), 0);
+    while emu.pending_audio_samples() < 64 {
+        emu.tick();
+    }
+    assert_eq!(emu.take_audio_samples().unwrap().len(), 64);
+}
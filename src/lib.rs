@@ -1,8 +1,15 @@
 pub mod bus;
+pub mod cdl;
 pub mod cheat;
 pub mod cpu;
 pub mod debugger;
+pub mod disasm;
 pub mod emulator;
+pub mod patch;
+pub mod profile;
 pub(crate) mod psg;
+pub mod ripper;
+pub mod symbols;
+pub mod trace;
 pub(crate) mod vce;
 pub(crate) mod vdc;
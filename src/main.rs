@@ -1,15 +1,41 @@
+use std::io::{self, Write};
 use std::{env, error::Error, fs, path::PathBuf};
 
+use pce::bus::SheetPalette;
+use pce::cdl::cdl_path_for;
 use pce::emulator::Emulator;
+use pce::patch::{patch_hucard_image, patch_path_for};
+use pce::profile::Profiler;
+use pce::ripper::export_graphics;
+use pce::symbols::SymbolTable;
+use pce::trace::{TraceTrigger, Tracer};
+
+/// Hot spots listed by `--profile`.
+const PROFILE_REPORT_LINES: usize = 50;
+/// Frames `rip` runs before dumping VRAM when no state is loaded.
+const RIP_DEFAULT_FRAMES: usize = 60;
+/// Cycle budget per frame `rip` waits for, well above a real frame.
+const RIP_FRAME_BUDGET: u64 = 1_000_000;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut args = env::args().skip(1);
+    let mut args = env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "rip").is_some() {
+        return run_rip(args);
+    }
     let mut rom_path: Option<PathBuf> = None;
     let mut load_backup: Option<PathBuf> = None;
     let mut save_backup: Option<PathBuf> = None;
     let mut load_bram: Option<PathBuf> = None;
     let mut save_bram: Option<PathBuf> = None;
     let mut frame_limit: Option<usize> = None;
+    let mut log_rom_usage = false;
+    let mut trace_path: Option<PathBuf> = None;
+    let mut trace_from: Option<TraceTrigger> = None;
+    let mut trace_until: Option<TraceTrigger> = None;
+    let mut sym_path: Option<PathBuf> = None;
+    let mut patch_paths: Vec<PathBuf> = Vec::new();
+    let mut profile_report: Option<PathBuf> = None;
+    let mut profile_stacks: Option<PathBuf> = None;
 
     while let Some(arg) = args.next() {
         match arg.as_str() {
@@ -59,6 +85,62 @@ fn main() -> Result<(), Box<dyn Error>> {
                     return Ok(());
                 }
             }
+            "--cdl" => log_rom_usage = true,
+            "--trace" => {
+                if let Some(path) = args.next() {
+                    trace_path = Some(PathBuf::from(path));
+                } else {
+                    eprintln!("--trace requires a file path");
+                    return Ok(());
+                }
+            }
+            "--profile" | "--profile-stacks" => {
+                let Some(path) = args.next() else {
+                    eprintln!("{arg} requires a file path");
+                    return Ok(());
+                };
+                if arg == "--profile" {
+                    profile_report = Some(PathBuf::from(path));
+                } else {
+                    profile_stacks = Some(PathBuf::from(path));
+                }
+            }
+            "--patch" => {
+                if let Some(path) = args.next() {
+                    patch_paths.push(PathBuf::from(path));
+                } else {
+                    eprintln!("--patch requires a file path");
+                    return Ok(());
+                }
+            }
+            "--sym" => {
+                if let Some(path) = args.next() {
+                    sym_path = Some(PathBuf::from(path));
+                } else {
+                    eprintln!("--sym requires a file path");
+                    return Ok(());
+                }
+            }
+            "--trace-from" | "--trace-until" => {
+                let Some(value) = args.next() else {
+                    eprintln!("{arg} requires a trigger");
+                    return Ok(());
+                };
+                match value.parse::<TraceTrigger>() {
+                    Ok(trigger) if arg == "--trace-from" && !trigger.can_start() => {
+                        eprintln!(
+                            "--trace-from does not accept count:N; use it with --trace-until"
+                        );
+                        return Ok(());
+                    }
+                    Ok(trigger) if arg == "--trace-from" => trace_from = Some(trigger),
+                    Ok(trigger) => trace_until = Some(trigger),
+                    Err(err) => {
+                        eprintln!("invalid {arg} value: {err}");
+                        return Ok(());
+                    }
+                }
+            }
             "--help" | "-h" => {
                 print_usage();
                 return Ok(());
@@ -80,13 +162,22 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     };
 
-    let rom = fs::read(&rom_path)?;
+    let mut rom = fs::read(&rom_path)?;
 
     let mut emulator = Emulator::new();
     let is_pce = rom_path
         .extension()
         .map(|ext| ext.eq_ignore_ascii_case("pce"))
         .unwrap_or(false);
+    if is_pce && patch_paths.is_empty() {
+        patch_paths.extend(patch_path_for(&rom_path));
+    }
+    for path in &patch_paths {
+        let patch = fs::read(path)?;
+        rom = patch_hucard_image(&rom, &patch)
+            .map_err(|err| format!("failed to apply patch {}: {err}", path.display()))?;
+        println!("applied patch {}", path.display());
+    }
     let default_backup = if is_pce {
         Some(rom_path.with_extension("sav"))
     } else {
@@ -140,11 +231,71 @@ fn main() -> Result<(), Box<dyn Error>> {
                 ),
             }
         }
+        if log_rom_usage {
+            emulator.set_code_data_log_enabled(true);
+            let cdl_path = cdl_path_for(&rom_path);
+            if cdl_path.exists() {
+                match fs::read(&cdl_path) {
+                    Ok(bytes) => {
+                        if let Err(err) = emulator.load_code_data_log(&bytes) {
+                            eprintln!(
+                                "warning: failed to load CDL from {}: {err}",
+                                cdl_path.display()
+                            );
+                        }
+                    }
+                    Err(err) => eprintln!(
+                        "warning: could not read CDL file {}: {err}",
+                        cdl_path.display()
+                    ),
+                }
+            }
+        }
     } else {
         emulator.load_program(0xC000, &rom);
     }
     emulator.reset();
 
+    let symbols = if trace_path.is_some() || profile_report.is_some() || profile_stacks.is_some() {
+        let sym_path = sym_path.or_else(|| {
+            let path = rom_path.with_extension("sym");
+            path.exists().then_some(path)
+        });
+        sym_path.and_then(|sym_path| match SymbolTable::load(&sym_path) {
+            Ok(symbols) => Some(symbols),
+            Err(err) => {
+                eprintln!(
+                    "warning: failed to load symbols from {}: {err}",
+                    sym_path.display()
+                );
+                None
+            }
+        })
+    } else {
+        None
+    };
+
+    if let Some(path) = &trace_path {
+        let mut tracer = Tracer::to_file(path)?;
+        if let Some(symbols) = &symbols {
+            tracer = tracer.with_symbols(symbols.clone());
+        }
+        if let Some(trigger) = trace_from {
+            tracer = tracer.start_when(trigger);
+        }
+        if let Some(trigger) = trace_until {
+            tracer = tracer.stop_when(trigger);
+        }
+        emulator.attach_tracer(tracer);
+    }
+    if profile_report.is_some() || profile_stacks.is_some() {
+        let mut profiler = Profiler::new();
+        if let Some(symbols) = symbols {
+            profiler = profiler.with_symbols(symbols);
+        }
+        emulator.attach_profiler(profiler);
+    }
+
     if let Some(limit) = frame_limit {
         const MAX_FRAME_BUDGET: u64 = 50_000_000;
         let mut frames = 0usize;
@@ -176,6 +327,41 @@ fn main() -> Result<(), Box<dyn Error>> {
         emulator.cpu.pc,
     );
 
+    if let Some(mut tracer) = emulator.detach_tracer()
+        && let Err(err) = tracer.flush()
+    {
+        eprintln!("warning: failed to write trace: {err}");
+    }
+
+    if let Some(profiler) = emulator.detach_profiler() {
+        if let Some(path) = &profile_report {
+            let written = fs::File::create(path).and_then(|file| {
+                let mut out = io::BufWriter::new(file);
+                profiler.write_report(&mut out, PROFILE_REPORT_LINES)?;
+                out.flush()
+            });
+            if let Err(err) = written {
+                eprintln!(
+                    "warning: failed to write profile to {}: {err}",
+                    path.display()
+                );
+            }
+        }
+        if let Some(path) = &profile_stacks {
+            let written = fs::File::create(path).and_then(|file| {
+                let mut out = io::BufWriter::new(file);
+                profiler.write_collapsed(&mut out)?;
+                out.flush()
+            });
+            if let Err(err) = written {
+                eprintln!(
+                    "warning: failed to write profile stacks to {}: {err}",
+                    path.display()
+                );
+            }
+        }
+    }
+
     if let Some(snapshot) = emulator.save_backup_ram() {
         let save_path = save_backup.or_else(|| default_backup.clone());
         if let Some(path) = save_path {
@@ -190,6 +376,16 @@ fn main() -> Result<(), Box<dyn Error>> {
         eprintln!("warning: no backup RAM present for this program; nothing saved");
     }
 
+    if let Some(log) = emulator.save_code_data_log() {
+        let cdl_path = cdl_path_for(&rom_path);
+        if let Err(err) = fs::write(&cdl_path, log) {
+            eprintln!(
+                "warning: failed to write CDL to {}: {err}",
+                cdl_path.display()
+            );
+        }
+    }
+
     if is_pce {
         let snapshot = emulator.save_bram();
         let save_path = save_bram.or_else(|| default_bram.clone());
@@ -203,6 +399,99 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// `pce rip`: run a ROM (or load a state) and write its graphics as PNGs.
+fn run_rip(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut rom_path: Option<PathBuf> = None;
+    let mut out_dir = PathBuf::from("rip");
+    let mut frames: Option<usize> = None;
+    let mut state_path: Option<PathBuf> = None;
+    let mut palette = SheetPalette::Referenced;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" | "--frames" | "--state" | "--palette" => {
+                let Some(value) = args.next() else {
+                    eprintln!("{arg} requires a value");
+                    return Ok(());
+                };
+                match arg.as_str() {
+                    "--out" => out_dir = PathBuf::from(value),
+                    "--state" => state_path = Some(PathBuf::from(value)),
+                    "--frames" => match value.parse::<usize>() {
+                        Ok(count) => frames = Some(count),
+                        Err(_) => {
+                            eprintln!("invalid --frames value: {value}");
+                            return Ok(());
+                        }
+                    },
+                    _ => match value.parse::<u8>() {
+                        _ if value == "auto" => palette = SheetPalette::Referenced,
+                        Ok(bank) if bank < 16 => palette = SheetPalette::Bank(bank),
+                        _ => {
+                            eprintln!("invalid --palette value: {value} (expected auto or 0-15)");
+                            return Ok(());
+                        }
+                    },
+                }
+            }
+            "--help" | "-h" => {
+                print_usage();
+                return Ok(());
+            }
+            _ if rom_path.is_none() => rom_path = Some(PathBuf::from(arg)),
+            other => {
+                eprintln!("Unknown argument: {other}");
+                print_usage();
+                return Ok(());
+            }
+        }
+    }
+    let Some(rom_path) = rom_path else {
+        print_usage();
+        return Ok(());
+    };
+
+    let rom = fs::read(&rom_path)?;
+    let mut emulator = Emulator::new();
+    let is_pce = rom_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pce"));
+    if is_pce {
+        emulator.load_hucard(&rom)?;
+    } else {
+        emulator.load_program(0xC000, &rom);
+    }
+    emulator.reset();
+    if let Some(path) = &state_path {
+        emulator.load_state_from_file(path)?;
+    }
+
+    let frames = frames.unwrap_or(if state_path.is_some() {
+        0
+    } else {
+        RIP_DEFAULT_FRAMES
+    });
+    let mut collected = 0usize;
+    let mut remaining = RIP_FRAME_BUDGET.saturating_mul(frames as u64);
+    while collected < frames && remaining > 0 {
+        let cycles = emulator.tick() as u64;
+        remaining = remaining.saturating_sub(cycles.max(1));
+        if emulator.take_frame().is_some() {
+            collected += 1;
+        }
+    }
+    if collected < frames {
+        eprintln!(
+            "warning: only {collected} / {frames} frames ran before the cycle budget ran out"
+        );
+    }
+
+    for path in export_graphics(&emulator.bus, &out_dir, palette)? {
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
 fn print_usage() {
     eprintln!(
         "Usage: pce <program.[bin|pce]> [--load-backup <file>] [--save-backup <file>] [--load-bram <file>] [--save-bram <file>]"
@@ -215,8 +504,35 @@ fn print_usage() {
     eprintln!("  --load-bram <file>    Load Ten no Koe 2 BRAM (2KB) from file before reset");
     eprintln!("  --save-bram <file>    Save Ten no Koe 2 BRAM (2KB) to file after run");
     eprintln!("  --frame-limit <n>     Run until N frames are produced (or budget exhausted)");
+    eprintln!(
+        "  --patch <file>        Apply an IPS/BPS/UPS patch before loading (repeatable; default: ROM_NAME.ips/.bps/.ups)"
+    );
+    eprintln!(
+        "  --cdl                 Log ROM code/data usage to ROM_NAME.cdl (merged across runs)"
+    );
+    eprintln!("  --trace <file>        Write an instruction trace to file");
+    eprintln!("  --trace-from <trig>   Start tracing at pc:E123, pc:F8:E123, frame:N or cycle:N");
+    eprintln!("  --trace-until <trig>  Stop tracing at a trigger above or count:N instructions");
+    eprintln!(
+        "  --profile <file>      Write a cycle hot-spot report (WAI/IRQ/block-transfer split)"
+    );
+    eprintln!("  --profile-stacks <f>  Write collapsed call stacks for flame graph tools");
+    eprintln!(
+        "  --sym <file>          Label traces and profiles from a symbol file (default: ROM_NAME.sym)"
+    );
     eprintln!("  --help                Show this message");
     eprintln!();
+    eprintln!(
+        "       pce rip <program.[bin|pce]> [--out <dir>] [--frames <n>] [--state <file>] [--palette auto|0-15]"
+    );
+    eprintln!(
+        "  Write VRAM as bg_tiles.png (8x8), sprite_patterns.png (16x16) and bat_map.png to <dir> (default: rip)"
+    );
+    eprintln!(
+        "  after running N frames (default: {RIP_DEFAULT_FRAMES}, or 0 with --state). --palette auto (default)"
+    );
+    eprintln!("  colours each tile/pattern with the bank the BAT/SATB uses it with.");
+    eprintln!();
     eprintln!(
         "When running a .pce HuCard, backup RAM automatically loads/saves from the \
          ROM path with .sav (cart RAM) and .brm (Ten no Koe 2 BRAM) extensions unless overridden."
@@ -0,0 +1,543 @@
+//! IPS, BPS and UPS patches applied to ROM images at load time.
+//!
+//! BPS and UPS carry CRC32s of the source, target and patch; all three are
+//! checked and a mismatch is an error rather than a silently broken ROM.
+//! IPS has no checksums and is applied as-is.
+//!
+//! Translation patches for HuCards are usually made against headerless
+//! dumps. [`patch_hucard_image`] therefore patches the payload after a
+//! 512-byte copier header when one is present (keeping the header in
+//! place), and for BPS/UPS falls back to the whole image if the payload's
+//! CRC does not match but the image's does. The result then goes through
+//! the normal HuCard loader, header handling included.
+
+use std::path::{Path, PathBuf};
+
+const HEADER_SIZE: usize = 512;
+const PAGE_SIZE: usize = 0x2000;
+/// Largest BPS/UPS target accepted. The biggest HuCard is 2.5 MiB, so this
+/// leaves room for headers and hacks while stopping a corrupt size field
+/// from allocating gigabytes.
+const MAX_TARGET_SIZE: usize = 4 * 1024 * 1024;
+
+/// Patch extensions looked for next to a ROM, in priority order.
+pub const PATCH_EXTENSIONS: [&str; 3] = ["ips", "bps", "ups"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Ups,
+}
+
+impl PatchFormat {
+    /// Identify a patch by its magic bytes.
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(b"PATCH") {
+            Some(Self::Ips)
+        } else if patch.starts_with(b"BPS1") {
+            Some(Self::Bps)
+        } else if patch.starts_with(b"UPS1") {
+            Some(Self::Ups)
+        } else {
+            None
+        }
+    }
+
+    fn has_checksums(self) -> bool {
+        !matches!(self, Self::Ips)
+    }
+}
+
+/// First patch with the ROM's name and an `.ips`, `.bps` or `.ups`
+/// extension, if any exists.
+pub fn patch_path_for(rom_path: &Path) -> Option<PathBuf> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|ext| rom_path.with_extension(ext))
+        .find(|path| path.exists())
+}
+
+/// Apply a patch of any supported format to `source`.
+pub fn apply_patch(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(source, patch),
+        Some(PatchFormat::Bps) => apply_bps(source, patch),
+        Some(PatchFormat::Ups) => apply_ups(source, patch),
+        None => Err("unrecognised patch format".to_string()),
+    }
+}
+
+/// Apply a patch to a HuCard image, skipping a 512-byte copier header as
+/// described in the module docs.
+pub fn patch_hucard_image(image: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let format = PatchFormat::detect(patch).ok_or("unrecognised patch format")?;
+    if image.len() % PAGE_SIZE != HEADER_SIZE {
+        return apply_patch(image, patch);
+    }
+    let (header, body) = image.split_at(HEADER_SIZE);
+    match apply_patch(body, patch) {
+        Ok(patched) => {
+            let mut out = header.to_vec();
+            out.extend_from_slice(&patched);
+            Ok(out)
+        }
+        Err(err) if format.has_checksums() => apply_patch(image, patch).map_err(|_| err),
+        Err(err) => Err(err),
+    }
+}
+
+/// Standard CRC-32 (IEEE, reflected), as used by BPS, UPS and zip.
+pub fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0u32, |crc, &byte| {
+        TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Cursor over patch bytes with truncation reported as an error.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| format!("patch truncated at offset {}", self.pos))?;
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn be(&mut self, len: usize) -> Result<usize, String> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize))
+    }
+
+    /// BPS/UPS variable-length integer.
+    fn varint(&mut self) -> Result<usize, String> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|v| v.checked_add(value))
+                .ok_or("patch varint overflows")?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or("patch varint overflows")?;
+            value = value.checked_add(shift).ok_or("patch varint overflows")?;
+        }
+    }
+}
+
+pub fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if !patch.starts_with(b"PATCH") {
+        return Err("not an IPS patch".to_string());
+    }
+    let mut out = source.to_vec();
+    let mut reader = Reader::new(patch, 5);
+    loop {
+        let header = reader.bytes(3)?;
+        if header == b"EOF" {
+            break;
+        }
+        let offset = header
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        let size = reader.be(2)?;
+        let (len, fill) = if size == 0 {
+            (reader.be(2)?, Some(reader.byte()?))
+        } else {
+            (size, None)
+        };
+        if out.len() < offset + len {
+            out.resize(offset + len, 0);
+        }
+        match fill {
+            Some(value) => out[offset..offset + len].fill(value),
+            None => out[offset..offset + len].copy_from_slice(reader.bytes(len)?),
+        }
+    }
+    // Optional truncation extension.
+    if patch.len() - reader.pos == 3 {
+        out.truncate(reader.be(3)?);
+    }
+    Ok(out)
+}
+
+/// Split off and check the CRC32 footer shared by BPS and UPS, returning
+/// the expected source and target CRCs.
+fn checked_footer(patch: &[u8], name: &str) -> Result<(u32, u32), String> {
+    if patch.len() < 4 + 12 {
+        return Err(format!("{name} patch too short"));
+    }
+    let footer = &patch[patch.len() - 12..];
+    let word = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    let actual = crc32(&patch[..patch.len() - 4]);
+    if actual != word(8) {
+        return Err(format!(
+            "{name} patch CRC mismatch (expected {:08X}, got {actual:08X})",
+            word(8)
+        ));
+    }
+    Ok((word(0), word(4)))
+}
+
+fn check_target_size(name: &str, target_size: usize) -> Result<(), String> {
+    if target_size > MAX_TARGET_SIZE {
+        return Err(format!(
+            "{name} patch target size {target_size} exceeds the {MAX_TARGET_SIZE}-byte limit"
+        ));
+    }
+    Ok(())
+}
+
+fn check_crc(what: &str, expected: u32, data: &[u8]) -> Result<(), String> {
+    let actual = crc32(data);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(format!(
+            "{what} CRC mismatch (expected {expected:08X}, got {actual:08X})"
+        ))
+    }
+}
+
+pub fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if !patch.starts_with(b"BPS1") {
+        return Err("not a BPS patch".to_string());
+    }
+    let (source_crc, target_crc) = checked_footer(patch, "BPS")?;
+    check_crc("source ROM", source_crc, source)?;
+
+    let actions_end = patch.len() - 12;
+    let mut reader = Reader::new(&patch[..actions_end], 4);
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+    check_target_size("BPS", target_size)?;
+    if source_size != source.len() {
+        return Err(format!(
+            "source ROM is {} bytes, patch expects {source_size}",
+            source.len()
+        ));
+    }
+
+    let mut target: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    let relative = |base: &mut usize, delta: usize| -> Result<usize, String> {
+        let magnitude = delta >> 1;
+        *base = if delta & 1 != 0 {
+            base.checked_sub(magnitude)
+        } else {
+            base.checked_add(magnitude)
+        }
+        .ok_or("BPS copy offset out of range")?;
+        Ok(*base)
+    };
+    while reader.pos < actions_end {
+        let data = reader.varint()?;
+        let len = (data >> 2) + 1;
+        if len > target_size - target.len() {
+            return Err("BPS patch writes past the target size".to_string());
+        }
+        let out_of_range = || "BPS action reads past the end of its data".to_string();
+        match data & 3 {
+            // SourceRead
+            0 => {
+                let at = target.len();
+                target.extend_from_slice(source.get(at..at + len).ok_or_else(out_of_range)?);
+            }
+            // TargetRead
+            1 => target.extend_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                let delta = reader.varint()?;
+                let start = relative(&mut source_offset, delta)?;
+                target.extend_from_slice(source.get(start..start + len).ok_or_else(out_of_range)?);
+                source_offset += len;
+            }
+            // TargetCopy: may overlap the bytes being written.
+            _ => {
+                let delta = reader.varint()?;
+                let start = relative(&mut target_offset, delta)?;
+                if start >= target.len() {
+                    return Err(out_of_range());
+                }
+                for i in start..start + len {
+                    target.push(target[i]);
+                }
+                target_offset += len;
+            }
+        }
+    }
+    if target.len() != target_size {
+        return Err(format!(
+            "BPS patch produced {} bytes, expected {target_size}",
+            target.len()
+        ));
+    }
+    check_crc("patched ROM", target_crc, &target)?;
+    Ok(target)
+}
+
+pub fn apply_ups(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if !patch.starts_with(b"UPS1") {
+        return Err("not a UPS patch".to_string());
+    }
+    let (source_crc, target_crc) = checked_footer(patch, "UPS")?;
+    check_crc("source ROM", source_crc, source)?;
+
+    let records_end = patch.len() - 12;
+    let mut reader = Reader::new(&patch[..records_end], 4);
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    check_target_size("UPS", target_size)?;
+    if source_size != source.len() {
+        return Err(format!(
+            "source ROM is {} bytes, patch expects {source_size}",
+            source.len()
+        ));
+    }
+
+    let mut target = source.to_vec();
+    target.resize(target_size, 0);
+    let mut offset = 0usize;
+    while reader.pos < records_end {
+        offset = offset
+            .checked_add(reader.varint()?)
+            .ok_or("UPS record offset overflows")?;
+        loop {
+            let xor = reader.byte()?;
+            if xor == 0 {
+                offset = offset.checked_add(1).ok_or("UPS record offset overflows")?;
+                break;
+            }
+            if let Some(byte) = target.get_mut(offset) {
+                *byte ^= xor;
+            }
+            offset = offset.checked_add(1).ok_or("UPS record offset overflows")?;
+        }
+    }
+    check_crc("patched ROM", target_crc, &target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let low = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(0x80 | low);
+                return;
+            }
+            out.push(low);
+            value -= 1;
+        }
+    }
+
+    /// BPS action word: `command` over `len` bytes.
+    fn action(len: usize, command: usize) -> usize {
+        ((len - 1) << 2) | command
+    }
+
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let crc = crc32(&patch);
+        patch.extend_from_slice(&crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn crc32_matches_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn ips_applies_records_rle_growth_and_truncation() {
+        let source = [0u8; 8];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0x7E]);
+        patch.extend_from_slice(b"EOF");
+        let out = apply_patch(&source, &patch).unwrap();
+        assert_eq!(out, [0, 0, 0xAA, 0xBB, 0, 0, 0x7E, 0x7E, 0x7E, 0x7E]);
+
+        patch.extend_from_slice(&[0x00, 0x00, 0x03]);
+        assert_eq!(apply_ips(&source, &patch).unwrap(), [0, 0, 0xAA]);
+
+        assert!(apply_ips(&source, b"PATCH\x00\x00\x01\x00\x05\x01").is_err());
+    }
+
+    #[test]
+    fn bps_applies_all_actions_and_checks_crcs() {
+        let source = b"HELLO, PC ENGINE".to_vec();
+        let target = b"HELLO, TURBOGRAFX GRAFX!".to_vec();
+        let mut patch = b"BPS1".to_vec();
+        varint(source.len(), &mut patch);
+        varint(target.len(), &mut patch);
+        varint(0, &mut patch);
+        // SourceRead "HELLO, "
+        varint(action(7, 0), &mut patch);
+        // TargetRead "TURBOGRAFX"
+        varint(action(10, 1), &mut patch);
+        patch.extend_from_slice(b"TURBOGRAFX");
+        // SourceCopy " " from source offset 6
+        varint(action(1, 2), &mut patch);
+        varint(6 << 1, &mut patch);
+        // TargetCopy "GRAFX" from target offset 12
+        varint(action(5, 3), &mut patch);
+        varint(12 << 1, &mut patch);
+        // TargetRead "!"
+        varint(action(1, 1), &mut patch);
+        patch.push(b'!');
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+
+        let err = apply_bps(b"HELLO, PC ENGINF", &patch).unwrap_err();
+        assert!(err.contains("source ROM CRC mismatch"), "{err}");
+        let mut corrupt = patch.clone();
+        corrupt[6] ^= 1;
+        assert!(
+            apply_bps(&source, &corrupt)
+                .unwrap_err()
+                .contains("patch CRC")
+        );
+    }
+
+    #[test]
+    fn ups_xors_records_and_resizes() {
+        let source = vec![0x10, 0x20, 0x30, 0x40];
+        let target = vec![0x10, 0x21, 0x30, 0x40, 0x00, 0x55];
+        let mut patch = b"UPS1".to_vec();
+        varint(source.len(), &mut patch);
+        varint(target.len(), &mut patch);
+        varint(1, &mut patch);
+        patch.extend_from_slice(&[0x20 ^ 0x21, 0x00]);
+        // Each record's terminator also advances one byte: 3 + 2 = 5.
+        varint(2, &mut patch);
+        patch.extend_from_slice(&[0x55, 0x00]);
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+        assert!(apply_ups(&target[..4], &patch).is_err());
+    }
+
+    #[test]
+    fn oversized_target_sizes_are_rejected_before_allocating() {
+        let source = b"ROM".to_vec();
+        for magic in [&b"BPS1"[..], b"UPS1"] {
+            let mut patch = magic.to_vec();
+            varint(source.len(), &mut patch);
+            varint(usize::MAX >> 8, &mut patch);
+            varint(0, &mut patch);
+            let patch = with_footer(patch, &source, &source);
+            let err = apply_patch(&source, &patch).unwrap_err();
+            assert!(err.contains("exceeds"), "{err}");
+        }
+    }
+
+    #[test]
+    fn ups_record_offsets_cannot_wrap() {
+        let source = b"ROM".to_vec();
+        let mut patch = b"UPS1".to_vec();
+        varint(source.len(), &mut patch);
+        varint(source.len(), &mut patch);
+        // Skip to the last addressable offset; the terminator steps past it.
+        varint(usize::MAX, &mut patch);
+        patch.push(0);
+        let patch = with_footer(patch, &source, &source);
+        let err = apply_ups(&source, &patch).unwrap_err();
+        assert!(err.contains("overflows"), "{err}");
+    }
+
+    #[test]
+    fn bps_actions_stop_at_the_target_size() {
+        let source = b"HELLO".to_vec();
+        let mut patch = b"BPS1".to_vec();
+        varint(source.len(), &mut patch);
+        varint(3, &mut patch);
+        varint(0, &mut patch);
+        // TargetRead of 2 bytes, then a TargetCopy of 4 that would overrun.
+        varint(action(2, 1), &mut patch);
+        patch.extend_from_slice(b"AB");
+        varint(action(4, 3), &mut patch);
+        varint(0, &mut patch);
+        let patch = with_footer(patch, &source, b"ABA");
+        let err = apply_bps(&source, &patch).unwrap_err();
+        assert!(err.contains("past the target size"), "{err}");
+    }
+
+    #[test]
+    fn hucard_patches_skip_copier_header() {
+        let mut image = vec![0xEEu8; HEADER_SIZE];
+        image.extend(vec![0u8; PAGE_SIZE]);
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x01, 0x4C]);
+        patch.extend_from_slice(b"EOF");
+        let out = patch_hucard_image(&image, &patch).unwrap();
+        assert_eq!(out.len(), image.len());
+        assert_eq!(out[0], 0xEE);
+        assert_eq!(out[HEADER_SIZE], 0x4C);
+
+        // A BPS made against the headered image still applies.
+        let mut target = image.clone();
+        target[HEADER_SIZE + 1] = 0x99;
+        let mut bps = b"BPS1".to_vec();
+        varint(image.len(), &mut bps);
+        varint(target.len(), &mut bps);
+        varint(0, &mut bps);
+        varint(action(HEADER_SIZE + 1, 0), &mut bps);
+        varint(action(1, 1), &mut bps);
+        bps.push(0x99);
+        let rest = image.len() - HEADER_SIZE - 2;
+        varint(action(rest, 2), &mut bps);
+        varint((HEADER_SIZE + 2) << 1, &mut bps);
+        let bps = with_footer(bps, &image, &target);
+        assert_eq!(patch_hucard_image(&image, &bps).unwrap(), target);
+    }
+}
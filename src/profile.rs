@@ -0,0 +1,412 @@
+//! Cycle-level CPU profiler.
+//!
+//! Attached to an [`Emulator`](crate::emulator::Emulator), the profiler
+//! charges every CPU cycle to the instruction that spent it, keyed both by
+//! logical PC and by `(bank, address)`. It also keeps per-frame totals and
+//! splits out the time spent idling in `WAI`, inside interrupt handlers and
+//! in block transfers, which is usually what explains an overrunning vblank
+//! handler.
+//!
+//! Two exports are provided: a text hot-spot report sorted by cycles, and
+//! collapsed stacks (`caller;callee;leaf cycles`) that `flamegraph.pl` or
+//! `inferno-flamegraph` turn into an SVG. Stacks come from the CPU's shadow
+//! call stack, which is switched on while a profiler is attached.
+
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::debugger::{CallFrame, FrameKind};
+use crate::disasm::BankedAddress;
+use crate::symbols::SymbolTable;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// TII, TDD, TIN, TIA, TAI.
+const BLOCK_TRANSFER_OPCODES: [u8; 5] = [0x73, 0xC3, 0xD3, 0xE3, 0xF3];
+
+/// Cycle counts for one frame or for the whole session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CycleTotals {
+    pub total: u64,
+    /// Cycles idling in `WAI` until an interrupt arrives.
+    pub wai: u64,
+    /// Cycles spent with an interrupt frame on the call stack, including
+    /// the dispatch itself.
+    pub interrupt: u64,
+    /// Cycles spent in TII/TDD/TIN/TIA/TAI.
+    pub block_transfer: u64,
+    pub instructions: u64,
+}
+
+impl CycleTotals {
+    fn add(&mut self, cycles: u64, kind: SampleKind, in_interrupt: bool) {
+        self.total += cycles;
+        match kind {
+            SampleKind::Wai => self.wai += cycles,
+            SampleKind::BlockTransfer => self.block_transfer += cycles,
+            SampleKind::Instruction | SampleKind::Dispatch => {}
+        }
+        if in_interrupt {
+            self.interrupt += cycles;
+        }
+        if kind != SampleKind::Wai {
+            self.instructions += 1;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hotspot {
+    pub bank: u8,
+    pub addr: u16,
+    pub cycles: u64,
+    /// Instructions executed at this address.
+    pub count: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SampleKind {
+    Instruction,
+    BlockTransfer,
+    Wai,
+    /// Interrupt entry taken instead of an instruction.
+    Dispatch,
+}
+
+/// One level of a collapsed stack: the entered routine and how it was
+/// entered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct StackEntry {
+    kind: FrameKind,
+    target: BankedAddress,
+}
+
+impl From<&CallFrame> for StackEntry {
+    fn from(frame: &CallFrame) -> Self {
+        Self {
+            kind: frame.kind,
+            target: BankedAddress {
+                bank: frame.target_bank(),
+                addr: frame.target,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Pending {
+    pc: u16,
+    bank: u8,
+    waiting: bool,
+    opcode: u8,
+    depth: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+    by_pc: HashMap<u16, u64>,
+    by_bank: HashMap<BankedAddress, (u64, u64)>,
+    stacks: HashMap<Vec<StackEntry>, u64>,
+    wai_stacks: HashMap<Vec<StackEntry>, u64>,
+    totals: CycleTotals,
+    frames: Vec<CycleTotals>,
+    current_frame: CycleTotals,
+    last_scanline: u16,
+    pending: Option<Pending>,
+    stack_key: Vec<StackEntry>,
+    symbols: Option<SymbolTable>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name routines and hot spots from `symbols` in the exports.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    pub fn reset(&mut self) {
+        let symbols = self.symbols.take();
+        *self = Self {
+            symbols,
+            ..Self::default()
+        };
+    }
+
+    pub fn totals(&self) -> CycleTotals {
+        self.totals
+    }
+
+    /// Totals of every completed frame, oldest first.
+    pub fn frames(&self) -> &[CycleTotals] {
+        &self.frames
+    }
+
+    /// Totals of the frame in progress.
+    pub fn current_frame(&self) -> CycleTotals {
+        self.current_frame
+    }
+
+    /// Cycles charged to logical `pc`, whatever bank was mapped.
+    pub fn cycles_at(&self, pc: u16) -> u64 {
+        self.by_pc.get(&pc).copied().unwrap_or(0)
+    }
+
+    pub fn cycles_at_banked(&self, bank: u8, addr: u16) -> u64 {
+        self.by_bank
+            .get(&BankedAddress { bank, addr })
+            .map_or(0, |&(cycles, _)| cycles)
+    }
+
+    /// Up to `limit` addresses by descending cycle count.
+    pub fn hotspots(&self, limit: usize) -> Vec<Hotspot> {
+        let mut spots: Vec<Hotspot> = self
+            .by_bank
+            .iter()
+            .map(|(at, &(cycles, count))| Hotspot {
+                bank: at.bank,
+                addr: at.addr,
+                cycles,
+                count,
+            })
+            .collect();
+        spots.sort_by(|a, b| {
+            b.cycles
+                .cmp(&a.cycles)
+                .then((a.bank, a.addr).cmp(&(b.bank, b.addr)))
+        });
+        spots.truncate(limit);
+        spots
+    }
+
+    /// Text report: session totals, frame statistics and the `limit`
+    /// hottest addresses.
+    pub fn write_report<W: Write>(&self, out: &mut W, limit: usize) -> io::Result<()> {
+        let totals = self.totals;
+        let percent = |part: u64| {
+            if totals.total == 0 {
+                0.0
+            } else {
+                part as f64 * 100.0 / totals.total as f64
+            }
+        };
+        writeln!(
+            out,
+            "cycles: {}  instructions: {}  frames: {}",
+            totals.total,
+            totals.instructions,
+            self.frames.len()
+        )?;
+        writeln!(
+            out,
+            "wai: {} ({:.1}%)  interrupt: {} ({:.1}%)  block transfer: {} ({:.1}%)",
+            totals.wai,
+            percent(totals.wai),
+            totals.interrupt,
+            percent(totals.interrupt),
+            totals.block_transfer,
+            percent(totals.block_transfer)
+        )?;
+        if !self.frames.is_empty() {
+            let busy = |frame: &CycleTotals| frame.total - frame.wai;
+            let average = self.frames.iter().map(busy).sum::<u64>() / self.frames.len() as u64;
+            let (worst_index, worst) = self
+                .frames
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, frame)| busy(frame))
+                .expect("frames is not empty");
+            let worst_irq = self.frames.iter().map(|f| f.interrupt).max().unwrap_or(0);
+            writeln!(
+                out,
+                "busy cycles/frame: avg {average}  max {} (frame {worst_index})  \
+                 max interrupt cycles/frame: {worst_irq}",
+                busy(worst)
+            )?;
+        }
+        writeln!(out)?;
+        writeln!(out, "{:>12} {:>6} {:>10}  address", "cycles", "%", "count")?;
+        for spot in self.hotspots(limit) {
+            let mut line = format!(
+                "{:>12} {:>5.1}% {:>10}  {}",
+                spot.cycles,
+                percent(spot.cycles),
+                spot.count,
+                BankedAddress {
+                    bank: spot.bank,
+                    addr: spot.addr
+                }
+            );
+            if let Some(name) = self.label(spot.bank, spot.addr) {
+                line.push_str(&format!("  {name}"));
+            }
+            writeln!(out, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Collapsed stacks, one `frame;frame;... cycles` line per distinct
+    /// stack, for flame graph tools. Idle `WAI` time appears as a `[wai]`
+    /// leaf.
+    pub fn write_collapsed<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut lines: Vec<(String, u64)> = self
+            .stacks
+            .iter()
+            .map(|(stack, &cycles)| (self.collapse(stack, None), cycles))
+            .chain(
+                self.wai_stacks
+                    .iter()
+                    .map(|(stack, &cycles)| (self.collapse(stack, Some("[wai]")), cycles)),
+            )
+            .collect();
+        lines.sort();
+        for (stack, cycles) in lines {
+            writeln!(out, "{stack} {cycles}")?;
+        }
+        Ok(())
+    }
+
+    fn label(&self, bank: u8, addr: u16) -> Option<&str> {
+        self.symbols.as_ref()?.lookup_banked(bank, addr)
+    }
+
+    fn collapse(&self, stack: &[StackEntry], leaf: Option<&str>) -> String {
+        let mut names = vec!["main".to_string()];
+        for entry in stack {
+            let name = match self.label(entry.target.bank, entry.target.addr) {
+                Some(name) => name.to_string(),
+                None => entry.target.to_string(),
+            };
+            names.push(if entry.kind.is_interrupt() {
+                format!("[{}] {name}", entry.kind.label())
+            } else {
+                name
+            });
+        }
+        names.extend(leaf.map(str::to_string));
+        names.join(";")
+    }
+
+    /// Called by the emulator before each CPU step.
+    pub(crate) fn before_instruction(&mut self, cpu: &Cpu, bus: &Bus) {
+        let pc = cpu.pc;
+        let frames = cpu.call_stack().frames();
+        self.pending = Some(Pending {
+            pc,
+            bank: bus.mpr((pc >> 13) as usize),
+            waiting: cpu.is_waiting(),
+            opcode: bus.peek(pc),
+            depth: frames.len(),
+        });
+        // The stack the step's cycles are charged to: an RTS/RTI still
+        // belongs to the routine it leaves.
+        self.stack_key.clear();
+        self.stack_key.extend(frames.iter().map(StackEntry::from));
+    }
+
+    /// Called by the emulator once the step's `cycles` have been run on the
+    /// bus.
+    pub(crate) fn after_instruction(&mut self, cpu: &Cpu, bus: &Bus, cycles: u64) {
+        let Some(before) = self.pending.take() else {
+            return;
+        };
+
+        let frames = cpu.call_stack().frames();
+        let entered = frames.last().filter(|frame| {
+            frames.len() > before.depth
+                && frame.is_interrupt()
+                && frame.kind != FrameKind::Brk
+                && frame.target == cpu.pc
+        });
+        let kind = if entered.is_some() {
+            SampleKind::Dispatch
+        } else if before.waiting {
+            SampleKind::Wai
+        } else if BLOCK_TRANSFER_OPCODES.contains(&before.opcode) {
+            SampleKind::BlockTransfer
+        } else {
+            SampleKind::Instruction
+        };
+
+        if cycles > 0 {
+            // An interrupt entry is charged to the handler it entered.
+            if let Some(frame) = entered {
+                self.stack_key.push(StackEntry::from(frame));
+            }
+            let in_interrupt = self.stack_key.iter().any(|entry| entry.kind.is_interrupt());
+
+            self.totals.add(cycles, kind, in_interrupt);
+            self.current_frame.add(cycles, kind, in_interrupt);
+
+            let stacks = if kind == SampleKind::Wai {
+                &mut self.wai_stacks
+            } else {
+                &mut self.stacks
+            };
+            match stacks.get_mut(self.stack_key.as_slice()) {
+                Some(total) => *total += cycles,
+                None => {
+                    stacks.insert(self.stack_key.clone(), cycles);
+                }
+            }
+
+            let (pc, bank) = match entered {
+                Some(frame) => (frame.target, frame.target_bank()),
+                None => (before.pc, before.bank),
+            };
+            if kind != SampleKind::Wai {
+                *self.by_pc.entry(pc).or_default() += cycles;
+                let spot = self
+                    .by_bank
+                    .entry(BankedAddress { bank, addr: pc })
+                    .or_default();
+                spot.0 += cycles;
+                spot.1 += 1;
+            }
+        }
+
+        let scanline = bus.vdc_current_scanline();
+        if scanline < self.last_scanline {
+            self.frames.push(std::mem::take(&mut self.current_frame));
+        }
+        self.last_scanline = scanline;
+    }
+}
+
+/// Emulator slot holding an attached profiler. Like the tracer slot it is
+/// not machine state: it encodes as zero bytes and clones empty.
+#[derive(Debug, Default)]
+pub(crate) struct ProfilerSlot(pub(crate) Option<Box<Profiler>>);
+
+impl Clone for ProfilerSlot {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
+impl bincode::Encode for ProfilerSlot {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for ProfilerSlot {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for ProfilerSlot {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
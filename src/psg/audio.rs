@@ -3,48 +3,47 @@ use super::channel::PsgChannel;
 use super::tables::*;
 
 impl Psg {
-    pub(crate) fn render_host_sample(&mut self, psg_cycles: u32) -> i16 {
+    /// Render one host sample frame as `(left, right)`.
+    pub(crate) fn render_host_sample(&mut self, psg_cycles: u32) -> (i16, i16) {
         // A small internal oversample substantially reduces aliasing on
         // high-frequency tones/noise (notably NF=31 percussion) without
         // changing the external 44.1 kHz sample rate or save-state format.
         const OVERSAMPLE: u32 = 2;
 
-        let mut mixed_sum: i64 = 0;
+        let mut left_sum: i64 = 0;
+        let mut right_sum: i64 = 0;
         let base_cycles = psg_cycles / OVERSAMPLE;
         let remainder = psg_cycles % OVERSAMPLE;
 
         for phase in 0..OVERSAMPLE {
             let cycles = base_cycles + u32::from(phase < remainder);
             self.clock(cycles);
-            mixed_sum += i64::from(self.mix_current_state());
+            let (left, right) = self.mix_current_state();
+            left_sum += i64::from(left);
+            right_sum += i64::from(right);
         }
 
-        let mixed = (mixed_sum / i64::from(OVERSAMPLE)) as i32;
-        self.finalize_sample(mixed)
+        let left = (left_sum / i64::from(OVERSAMPLE)) as i32;
+        let right = (right_sum / i64::from(OVERSAMPLE)) as i32;
+        let left = finalize_sample(left, &mut self.dc_prev_input, &mut self.post_filter_state);
+        let right = finalize_sample(
+            right,
+            &mut self.dc_prev_input_right,
+            &mut self.post_filter_state_right,
+        );
+        (left, right)
     }
 
-    fn mix_current_state(&self) -> i32 {
-        let mut mix: i32 = 0;
+    fn mix_current_state(&self) -> (i32, i32) {
+        let mut left: i32 = 0;
+        let mut right: i32 = 0;
         for channel_index in 0..PSG_CHANNEL_COUNT {
             let state = self.channels[channel_index];
-            mix += self.sample_channel(channel_index, state);
+            let (l, r) = self.sample_channel(channel_index, state);
+            left += l;
+            right += r;
         }
-        mix
-    }
-
-    fn finalize_sample(&mut self, mix: i32) -> i16 {
-        // sample_channel() returns values with 16 fractional bits.
-        // Per-channel max = 31 * 65536 = 2,031,616; 6-channel max = 12,189,696.
-        // Apply gain and shift: (mix * gain) >> 16.
-        let scaled = ((mix as i64 * PSG_OUTPUT_GAIN as i64) >> 16) as i32;
-        let input = scaled.clamp(i16::MIN as i32, i16::MAX as i32) as f64;
-        // PSG samples are unsigned around the midpoint, so remove the resulting DC bias
-        // before converting back to i16.  Keeping this transient preserves old save states.
-        const DC_BLOCK_R: f64 = 0.995;
-        let output = input - *self.dc_prev_input + DC_BLOCK_R * self.post_filter_state;
-        self.dc_prev_input.0 = input;
-        self.post_filter_state = output;
-        output.clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        (left, right)
     }
 
     pub(crate) fn clock(&mut self, psg_cycles: u32) {
@@ -103,9 +102,9 @@ impl Psg {
         }
     }
 
-    fn sample_channel(&self, channel: usize, state: PsgChannel) -> i32 {
+    fn sample_channel(&self, channel: usize, state: PsgChannel) -> (i32, i32) {
         if state.control & PSG_CH_CTRL_KEY_ON == 0 {
-            return 0;
+            return (0, 0);
         }
         let raw = if state.control & PSG_CH_CTRL_DDA != 0 {
             sample_to_signed(state.dda_sample)
@@ -122,7 +121,7 @@ impl Psg {
             sample_to_signed(self.waveform_ram[wave_index])
         };
         if raw == 0 {
-            return 0;
+            return (0, 0);
         }
 
         // Logarithmic volume mixing (Mednafen-compatible).
@@ -151,7 +150,7 @@ impl Psg {
         // after accumulating all channels and applying the output gain.
         let left = raw as i64 * db_table[vol_l] as i64;
         let right = raw as i64 * db_table[vol_r] as i64;
-        ((left + right) / 2) as i32
+        (left as i32, right as i32)
     }
 
     fn lfo_depth(&self) -> u8 {
@@ -189,6 +188,22 @@ impl Psg {
     }
 }
 
+/// Scale one side's mix to i16 and run it through that side's DC blocker.
+fn finalize_sample(mix: i32, prev_input: &mut f64, filter_state: &mut f64) -> i16 {
+    // sample_channel() returns values with 16 fractional bits.
+    // Per-channel max = 31 * 65536 = 2,031,616; 6-channel max = 12,189,696.
+    // Apply gain and shift: (mix * gain) >> 16.
+    let scaled = ((mix as i64 * PSG_OUTPUT_GAIN as i64) >> 16) as i32;
+    let input = scaled.clamp(i16::MIN as i32, i16::MAX as i32) as f64;
+    // PSG samples are unsigned around the midpoint, so remove the resulting DC bias
+    // before converting back to i16.
+    const DC_BLOCK_R: f64 = 0.995;
+    let output = input - *prev_input + DC_BLOCK_R * *filter_state;
+    *prev_input = input;
+    *filter_state = output;
+    output.clamp(i16::MIN as f64, i16::MAX as f64) as i16
+}
+
 #[inline]
 fn tone_divider(period: u16) -> u32 {
     let period = (period & 0x0FFF) as u32;
@@ -62,6 +62,10 @@ pub(crate) struct Psg {
     pub(crate) waveform_ram: [u8; PSG_CHANNEL_COUNT * PSG_WAVE_SIZE],
     post_filter_state: f64,
     dc_prev_input: TransientF64,
+    /// Right-hand DC blocker history; the left side keeps the original
+    /// fields so older save states still line up.
+    post_filter_state_right: TransientF64,
+    dc_prev_input_right: TransientF64,
 }
 
 impl Psg {
@@ -79,6 +83,8 @@ impl Psg {
             waveform_ram: [0; PSG_CHANNEL_COUNT * PSG_WAVE_SIZE],
             post_filter_state: 0.0,
             dc_prev_input: TransientF64(0.0),
+            post_filter_state_right: TransientF64(0.0),
+            dc_prev_input_right: TransientF64(0.0),
         }
     }
 
@@ -93,6 +99,8 @@ impl Psg {
         // load, so restart the filter cleanly.
         self.post_filter_state = 0.0;
         self.dc_prev_input = TransientF64(0.0);
+        self.post_filter_state_right = TransientF64(0.0);
+        self.dc_prev_input_right = TransientF64(0.0);
     }
 
     pub(crate) fn write_address(&mut self, value: u8) {
@@ -116,13 +124,7 @@ impl Psg {
     }
 
     pub(crate) fn read_data(&mut self) -> u8 {
-        let index = self.select as usize;
-        let value = if index < PSG_REG_COUNT {
-            self.regs[index]
-        } else {
-            let wave_index = index - PSG_REG_COUNT;
-            self.waveform_ram[wave_index % self.waveform_ram.len()]
-        };
+        let value = self.read_direct(self.select as usize);
         self.select = self.select.wrapping_add(1);
         value
     }
@@ -136,7 +138,7 @@ impl Psg {
         }
     }
 
-    pub(crate) fn read_direct(&mut self, index: usize) -> u8 {
+    pub(crate) fn read_direct(&self, index: usize) -> u8 {
         if index < PSG_REG_COUNT {
             self.regs[index]
         } else {
@@ -145,7 +147,7 @@ impl Psg {
         }
     }
 
-    pub(crate) fn read_status(&mut self) -> u8 {
+    pub(crate) fn read_status(&self) -> u8 {
         let mut status = 0;
         if self.irq_pending {
             status |= PSG_STATUS_IRQ;
@@ -293,10 +295,32 @@ mod tests {
         let mut psg = Psg::new();
         psg.post_filter_state = 123.0;
         psg.dc_prev_input = TransientF64(45.0);
+        psg.post_filter_state_right = TransientF64(67.0);
+        psg.dc_prev_input_right = TransientF64(89.0);
 
         psg.post_load_fixup();
 
         assert_eq!(psg.post_filter_state, 0.0);
         assert_eq!(*psg.dc_prev_input, 0.0);
+        assert_eq!(*psg.post_filter_state_right, 0.0);
+        assert_eq!(*psg.dc_prev_input_right, 0.0);
+    }
+
+    #[test]
+    fn balance_pans_each_channel_independently() {
+        let mut psg = Psg::new();
+        psg.waveform_ram[..PSG_WAVE_SIZE].fill(0x1F);
+        psg.channels[0].control = PSG_CH_CTRL_KEY_ON | 0x1F;
+        psg.channels[0].balance = 0xF0;
+
+        let (left, right) = psg.render_host_sample(0);
+        assert!(left > 0);
+        assert_eq!(right, 0);
+
+        psg.channels[0].balance = 0x0F;
+        psg.post_load_fixup();
+        let (left, right) = psg.render_host_sample(0);
+        assert_eq!(left, 0);
+        assert!(right > 0);
     }
 }
@@ -0,0 +1,184 @@
+//! Graphics ripper: VRAM written out as PNG images.
+//!
+//! [`export_graphics`] writes every 8x8 background tile and every 16x16
+//! sprite pattern as sheets with colour 0 transparent, plus the whole
+//! background map as the BG layer would show it. Sheets are coloured with
+//! a [`SheetPalette`]: one bank for everything, or the bank each tile and
+//! pattern is used with in the BAT and SATB.
+//!
+//! The PNGs are stored uncompressed, which keeps the encoder tiny; any
+//! image tool will recompress them on save.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::bus::{Bus, RgbaImage, SheetPalette};
+use crate::patch::crc32;
+
+/// Background tile sheet written by [`export_graphics`].
+pub const TILE_SHEET_FILE: &str = "bg_tiles.png";
+/// Sprite pattern sheet written by [`export_graphics`].
+pub const SPRITE_SHEET_FILE: &str = "sprite_patterns.png";
+/// Composed background map written by [`export_graphics`].
+pub const BAT_MAP_FILE: &str = "bat_map.png";
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// Largest payload of one stored deflate block.
+const STORED_BLOCK_MAX: usize = 0xFFFF;
+
+/// Write the tile sheet, sprite sheet and background map into `dir`,
+/// creating it if needed, and return the paths written.
+pub fn export_graphics(bus: &Bus, dir: &Path, palette: SheetPalette) -> io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let images = [
+        (TILE_SHEET_FILE, bus.render_tile_sheet(palette)),
+        (SPRITE_SHEET_FILE, bus.render_sprite_sheet(palette)),
+        (BAT_MAP_FILE, bus.render_bat_map().image),
+    ];
+    let mut written = Vec::with_capacity(images.len());
+    for (name, image) in images {
+        let path = dir.join(name);
+        std::fs::write(&path, encode_png(&image))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Encode `image` as an 8-bit RGBA PNG.
+pub fn encode_png(image: &RgbaImage) -> Vec<u8> {
+    let stride = image.width * 4;
+    let mut raw = Vec::with_capacity((stride + 1) * image.height);
+    for row in image.pixels.chunks_exact(stride.max(1)).take(image.height) {
+        raw.push(0); // filter: none
+        raw.extend_from_slice(row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// A zlib stream holding `data` in stored (uncompressed) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(STORED_BLOCK_MAX).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(STORED_BLOCK_MAX).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65_521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunk `kind`'s payload, after checking its CRC.
+    fn chunk<'a>(png: &'a [u8], kind: &[u8; 4]) -> &'a [u8] {
+        let mut pos = PNG_SIGNATURE.len();
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let body = &png[pos + 4..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc);
+            if &body[..4] == kind {
+                return &body[4..];
+            }
+            pos += 12 + len;
+        }
+        panic!("no {} chunk", String::from_utf8_lossy(kind));
+    }
+
+    #[test]
+    fn adler32_matches_reference_value() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn png_carries_header_and_unfiltered_rows() {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_rgb(1, 0, 0x12_34_56);
+        let png = encode_png(&image);
+
+        assert_eq!(png[..8], PNG_SIGNATURE);
+        assert_eq!(
+            chunk(&png, b"IHDR"),
+            [0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0]
+        );
+        assert!(chunk(&png, b"IEND").is_empty());
+        let idat = chunk(&png, b"IDAT");
+        let raw = [
+            0, 0, 0, 0, 0, 0x12, 0x34, 0x56, 0xFF, //
+            0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(idat[..2], [0x78, 0x01]);
+        assert_eq!(idat[2..7], [0x01, 18, 0, !18, 0xFF]);
+        assert_eq!(idat[7..25], raw);
+        assert_eq!(idat[25..], adler32(&raw).to_be_bytes());
+    }
+
+    #[test]
+    fn large_images_split_into_stored_blocks() {
+        let data = vec![0xAB; STORED_BLOCK_MAX + 10];
+        let stream = zlib_stored(&data);
+        assert_eq!(stream[2], 0x00);
+        assert_eq!(stream[3..5], [0xFF, 0xFF]);
+        let second = 7 + STORED_BLOCK_MAX;
+        assert_eq!(stream[second], 0x01);
+        assert_eq!(stream[second + 1..second + 3], [10, 0]);
+        assert_eq!(stream.len(), data.len() + 2 * 5 + 6);
+    }
+
+    #[test]
+    fn export_writes_every_sheet() {
+        let dir = std::env::temp_dir().join(format!("pce_ripper_test_{}", std::process::id()));
+        let written = export_graphics(&Bus::new(), &dir, SheetPalette::Bank(0)).unwrap();
+        let names: Vec<_> = written
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, [TILE_SHEET_FILE, SPRITE_SHEET_FILE, BAT_MAP_FILE]);
+        for path in &written {
+            let png = std::fs::read(path).unwrap();
+            assert_eq!(png[..8], PNG_SIGNATURE);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+//! Symbol tables loaded from assembler output.
+//!
+//! Accepted line forms (hex with optional `$`, `;`/`#` comments ignored):
+//!
+//! - `BB:AAAA name` — bank-qualified label lists and WLA-DX style `.sym`
+//! - `BB AAAA name` — PCEAS/HuC `.sym` tables (bank, address, label)
+//! - `name = $AAAA` / `name EQU $AAAA` — unbanked equates
+//! - `AAAA name` — unbanked label
+//!
+//! Banks are MPR values, so the same logical address can carry different
+//! names depending on which bank is mapped when it is looked up. Unbanked
+//! symbols match under any mapping.
+
+use crate::disasm::BankedAddress;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// MPR value the symbol lives in, or `None` for any bank.
+    pub bank: Option<u8>,
+    pub addr: u16,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    banked: BTreeMap<BankedAddress, String>,
+    unbanked: BTreeMap<u16, String>,
+    by_name: HashMap<String, Vec<Symbol>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text)?)
+    }
+
+    /// Parse a symbol file; errors name the offending line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut table = Self::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.split([';', '#']).next().unwrap_or_default().trim();
+            if line.is_empty() || is_header(line) {
+                continue;
+            }
+            let symbol = parse_line(line).ok_or_else(|| {
+                format!(
+                    "line {}: unrecognised symbol entry '{}'",
+                    index + 1,
+                    raw.trim()
+                )
+            })?;
+            table.insert(symbol);
+        }
+        Ok(table)
+    }
+
+    /// Add a symbol. The first name seen for an address is the one shown
+    /// in listings; later aliases remain resolvable by name.
+    pub fn insert(&mut self, symbol: Symbol) {
+        match symbol.bank {
+            Some(bank) => {
+                self.banked
+                    .entry(BankedAddress {
+                        bank,
+                        addr: symbol.addr,
+                    })
+                    .or_insert_with(|| symbol.name.clone());
+            }
+            None => {
+                self.unbanked
+                    .entry(symbol.addr)
+                    .or_insert_with(|| symbol.name.clone());
+            }
+        }
+        self.by_name
+            .entry(symbol.name.clone())
+            .or_default()
+            .push(symbol);
+    }
+
+    pub fn merge(&mut self, other: SymbolTable) {
+        for symbols in other.by_name.into_values() {
+            for symbol in symbols {
+                self.insert(symbol);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// Name for `addr` with `bank` mapped at it.
+    pub fn lookup_banked(&self, bank: u8, addr: u16) -> Option<&str> {
+        self.banked
+            .get(&BankedAddress { bank, addr })
+            .or_else(|| self.unbanked.get(&addr))
+            .map(String::as_str)
+    }
+
+    /// Name for logical `addr` under the given MPR mapping.
+    pub fn lookup(&self, addr: u16, mpr: &[u8; 8]) -> Option<&str> {
+        self.lookup_banked(mpr[(addr >> 13) as usize], addr)
+    }
+
+    /// All symbols called `name` (one per bank it is defined in).
+    pub fn find(&self, name: &str) -> &[Symbol] {
+        self.by_name.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// `name` if the address is labelled, `$AAAA` otherwise.
+    pub fn format_address(&self, addr: u16, mpr: &[u8; 8]) -> String {
+        match self.lookup(addr, mpr) {
+            Some(name) => name.to_string(),
+            None => format!("${addr:04X}"),
+        }
+    }
+}
+
+fn is_header(line: &str) -> bool {
+    line.starts_with('[')
+        || line
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| word.eq_ignore_ascii_case("bank"))
+        || line.chars().all(|c| c == '-' || c.is_whitespace())
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    let digits = text
+        .trim_start_matches('$')
+        .trim_start_matches("0x")
+        .trim_end_matches(['h', 'H']);
+    if digits.is_empty() {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+fn parse_addr(text: &str) -> Option<u16> {
+    parse_hex(text).and_then(|v| u16::try_from(v).ok())
+}
+
+fn parse_bank(text: &str) -> Option<u8> {
+    parse_hex(text).and_then(|v| u8::try_from(v).ok())
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '@'))
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '@'))
+}
+
+fn parse_line(line: &str) -> Option<Symbol> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let symbol = |name: &str, bank, addr| {
+        is_identifier(name).then(|| Symbol {
+            name: name.to_string(),
+            bank,
+            addr,
+        })
+    };
+    match fields.as_slice() {
+        [name, op, value] if *op == "=" || op.eq_ignore_ascii_case("equ") => {
+            symbol(name, None, parse_addr(value)?)
+        }
+        [location, name] => match location.split_once(':') {
+            Some((bank, addr)) => symbol(name, Some(parse_bank(bank)?), parse_addr(addr)?),
+            None => symbol(name, None, parse_addr(location)?),
+        },
+        [bank, addr, name, ..] => symbol(name, Some(parse_bank(bank)?), parse_addr(addr)?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PCEAS_SYM: &str = "\
+; generated by PCEAS
+Bank  Addr  Label
+----  ----  -----
+  00  E000  reset
+  00  E318  vsync_wait
+  01  C000  load_tiles    ; bank 1 overlay
+  02  C000  play_music
+";
+
+    #[test]
+    fn parses_pceas_tables_and_resolves_by_bank() {
+        let table = SymbolTable::parse(PCEAS_SYM).unwrap();
+        assert_eq!(table.len(), 4);
+        let mut mpr = [0xFF, 0xF8, 0, 0, 0, 0, 0x01, 0x00];
+        assert_eq!(table.lookup(0xE318, &mpr), Some("vsync_wait"));
+        assert_eq!(table.lookup(0xC000, &mpr), Some("load_tiles"));
+        mpr[6] = 0x02;
+        assert_eq!(table.lookup(0xC000, &mpr), Some("play_music"));
+        mpr[6] = 0x03;
+        assert_eq!(table.lookup(0xC000, &mpr), None);
+        assert_eq!(table.format_address(0xC000, &mpr), "$C000");
+    }
+
+    #[test]
+    fn parses_label_lists_and_equates() {
+        let table = SymbolTable::parse(
+            "F8:2040 player_x\n\
+             # comment\n\
+             vdc_data = $0002\n\
+             joypad EQU $1000\n\
+             $E123 nmi_stub\n",
+        )
+        .unwrap();
+        assert_eq!(table.lookup_banked(0xF8, 0x2040), Some("player_x"));
+        assert_eq!(table.lookup_banked(0xF9, 0x2040), None);
+        assert_eq!(table.lookup_banked(0x42, 0x0002), Some("vdc_data"));
+        assert_eq!(table.lookup_banked(0xFF, 0x1000), Some("joypad"));
+        assert_eq!(table.find("nmi_stub")[0].addr, 0xE123);
+        assert_eq!(table.find("nmi_stub")[0].bank, None);
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        let err = SymbolTable::parse("00 E000 ok\nnot a symbol line at all\n").unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+        assert!(SymbolTable::parse("100:E000 too_big_bank").is_err());
+    }
+}
@@ -0,0 +1,549 @@
+//! Per-instruction execution trace.
+//!
+//! A [`Tracer`] attached with [`Emulator::attach_tracer`] records the machine
+//! state before every instruction: PC, the bank mapped at PC, opcode bytes,
+//! registers, flags, the global cycle counter and the VDC scanline. Entries go
+//! to a ring buffer or stream to any `Write` sink in a fixed text format:
+//!
+//! ```text
+//! F8:E123  A9 3F                 LDA #$3F            A:00 X:1F Y:00 S:F5 P:nvTbdIzc CYC:123456 SL:012
+//! ```
+//!
+//! Flags print uppercase when set, lowercase when clear. An interrupt
+//! dispatch gets its own record at the interrupted PC, with no opcode bytes
+//! and the interrupt kind where the instruction would be; the next record is
+//! the first instruction of the handler:
+//!
+//! ```text
+//! F8:E123                        <IRQ1>              A:00 X:1F Y:00 S:F5 P:nvTbdIzc CYC:123460 SL:012
+//! ```
+//!
+//! [`Emulator::attach_tracer`]: crate::emulator::Emulator::attach_tracer
+
+use crate::bus::Bus;
+use crate::cpu::Cpu;
+use crate::debugger::FrameKind;
+use crate::disasm::{self, BankedAddress};
+use crate::symbols::SymbolTable;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+const FLAG_NAMES: [u8; 8] = *b"NVTBDIZC";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub bank: u8,
+    /// MPR0–7 when the instruction was fetched.
+    pub mpr: [u8; 8],
+    pub bytes: [u8; 7],
+    pub len: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub status: u8,
+    /// Emulator cycle count before the instruction executed.
+    pub cycles: u64,
+    pub scanline: u16,
+    /// Frames seen since the tracer was attached.
+    pub frame: u64,
+    /// Set when this record is an interrupt dispatch at `pc` rather than
+    /// the instruction there; `bytes` is then empty.
+    pub interrupt: Option<FrameKind>,
+}
+
+impl TraceEntry {
+    pub fn opcode_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl TraceEntry {
+    /// The trace line, with operand addresses named from `symbols` and a
+    /// `label:` line in front when the PC itself is labelled.
+    pub fn format_with_symbols(&self, symbols: &SymbolTable) -> String {
+        if let Some(kind) = self.interrupt {
+            return self.format_line(&format!("<{}>", kind.label()));
+        }
+        let inst =
+            disasm::decode(self.pc, self.opcode_bytes()).format_with_symbols(symbols, &self.mpr);
+        let line = self.format_line(&inst);
+        match symbols.lookup_banked(self.bank, self.pc) {
+            Some(label) => format!("{label}:\n{line}"),
+            None => line,
+        }
+    }
+
+    fn format_line(&self, inst: &str) -> String {
+        let bytes = self
+            .opcode_bytes()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let flags: String = FLAG_NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| {
+                if self.status & (0x80 >> i) != 0 {
+                    name as char
+                } else {
+                    name.to_ascii_lowercase() as char
+                }
+            })
+            .collect();
+        format!(
+            "{}  {:<20}  {:<18}  A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{} CYC:{} SL:{:03}",
+            BankedAddress {
+                bank: self.bank,
+                addr: self.pc
+            },
+            bytes,
+            inst,
+            self.a,
+            self.x,
+            self.y,
+            self.sp,
+            flags,
+            self.cycles,
+            self.scanline
+        )
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inst = match self.interrupt {
+            Some(kind) => format!("<{}>", kind.label()),
+            None => disasm::decode(self.pc, self.opcode_bytes()).to_string(),
+        };
+        f.write_str(&self.format_line(&inst))
+    }
+}
+
+/// Condition that starts or stops recording.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceTrigger {
+    /// Execution reaches this logical PC.
+    Pc(u16),
+    /// Execution reaches this PC with `bank` mapped at it.
+    BankedPc(BankedAddress),
+    /// The given frame (counted from when the tracer was attached) begins.
+    Frame(u64),
+    /// The emulator cycle counter reaches this value.
+    Cycle(u64),
+    /// This many instructions have been recorded (stop trigger only).
+    Count(u64),
+}
+
+impl TraceTrigger {
+    /// Whether this trigger can start a trace. `Count` only stops one:
+    /// nothing has been recorded before the trace starts.
+    pub fn can_start(self) -> bool {
+        !matches!(self, Self::Count(_))
+    }
+
+    fn matches(self, entry: &TraceEntry, recorded: u64) -> bool {
+        let executes_pc = entry.interrupt.is_none();
+        match self {
+            Self::Pc(pc) => executes_pc && entry.pc == pc,
+            Self::BankedPc(at) => executes_pc && entry.pc == at.addr && entry.bank == at.bank,
+            Self::Frame(frame) => entry.frame >= frame,
+            Self::Cycle(cycle) => entry.cycles >= cycle,
+            Self::Count(count) => recorded >= count,
+        }
+    }
+}
+
+impl FromStr for TraceTrigger {
+    type Err = String;
+
+    /// Parse `pc:E123`, `pc:F8:E123`, `frame:60`, `cycle:100000` or
+    /// `count:5000`. Addresses are hex with an optional `$`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("trigger '{s}' must be kind:value"))?;
+        let hex = |v: &str, what: &str| {
+            u16::from_str_radix(v.trim().trim_start_matches('$'), 16)
+                .map_err(|_| format!("invalid {what} '{v}'"))
+        };
+        let dec = |v: &str| {
+            v.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid number '{v}'"))
+        };
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "pc" => match value.split_once(':') {
+                Some((bank, addr)) => {
+                    let bank = hex(bank, "bank")?;
+                    let bank =
+                        u8::try_from(bank).map_err(|_| format!("invalid bank '{bank:X}'"))?;
+                    Ok(Self::BankedPc(BankedAddress {
+                        bank,
+                        addr: hex(addr, "address")?,
+                    }))
+                }
+                None => Ok(Self::Pc(hex(value, "address")?)),
+            },
+            "frame" => Ok(Self::Frame(dec(value)?)),
+            "cycle" => Ok(Self::Cycle(dec(value)?)),
+            "count" => Ok(Self::Count(dec(value)?)),
+            other => Err(format!("unknown trigger kind '{other}'")),
+        }
+    }
+}
+
+enum Sink {
+    Ring {
+        entries: VecDeque<TraceEntry>,
+        capacity: usize,
+    },
+    Stream(BufWriter<Box<dyn Write + Send>>),
+}
+
+/// Execution trace recorder; see the module docs for the output format.
+pub struct Tracer {
+    sink: Sink,
+    start: Option<TraceTrigger>,
+    stop: Option<TraceTrigger>,
+    active: bool,
+    finished: bool,
+    recorded: u64,
+    frame: u64,
+    last_scanline: u16,
+    symbols: Option<SymbolTable>,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    /// Keep the most recent `capacity` entries in memory.
+    pub fn ring(capacity: usize) -> Self {
+        Self::with_sink(Sink::Ring {
+            entries: VecDeque::with_capacity(capacity.min(1 << 16)),
+            capacity: capacity.max(1),
+        })
+    }
+
+    /// Stream formatted lines to `writer`.
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with_sink(Sink::Stream(BufWriter::new(Box::new(writer))))
+    }
+
+    /// Stream formatted lines to a newly created file.
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::to_writer(File::create(path)?))
+    }
+
+    fn with_sink(sink: Sink) -> Self {
+        Self {
+            sink,
+            start: None,
+            stop: None,
+            active: true,
+            finished: false,
+            recorded: 0,
+            frame: 0,
+            last_scanline: 0,
+            symbols: None,
+            error: None,
+        }
+    }
+
+    /// Record nothing until `trigger` fires; the triggering instruction is
+    /// the first one recorded.  A trigger that [cannot
+    /// start](TraceTrigger::can_start) a trace is ignored.
+    pub fn start_when(mut self, trigger: TraceTrigger) -> Self {
+        if trigger.can_start() {
+            self.start = Some(trigger);
+            self.active = false;
+        }
+        self
+    }
+
+    /// Stop for good once `trigger` fires; the triggering instruction is not
+    /// recorded.
+    pub fn stop_when(mut self, trigger: TraceTrigger) -> Self {
+        self.stop = Some(trigger);
+        self
+    }
+
+    /// Name PCs and operand addresses in the text output.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    fn format_entry(&self, entry: &TraceEntry) -> String {
+        match &self.symbols {
+            Some(symbols) => entry.format_with_symbols(symbols),
+            None => entry.to_string(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Number of instructions recorded so far (including ones the ring
+    /// buffer has since dropped).
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+
+    /// Entries held by a ring-buffer tracer, oldest first. Empty for
+    /// streaming tracers.
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        let ring = match &self.sink {
+            Sink::Ring { entries, .. } => Some(entries.iter()),
+            Sink::Stream(_) => None,
+        };
+        ring.into_iter().flatten()
+    }
+
+    /// Write the ring buffer contents in the trace text format.
+    pub fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for entry in self.entries() {
+            writeln!(out, "{}", self.format_entry(entry))?;
+        }
+        Ok(())
+    }
+
+    /// Flush a streaming sink, reporting any write error seen while tracing.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        match &mut self.sink {
+            Sink::Stream(writer) => writer.flush(),
+            Sink::Ring { .. } => Ok(()),
+        }
+    }
+
+    /// Called by the emulator before each CPU step, with the interrupt the
+    /// step will dispatch instead of running the instruction at PC.
+    pub(crate) fn before_instruction(
+        &mut self,
+        cpu: &Cpu,
+        bus: &Bus,
+        cycles: u64,
+        interrupt: Option<FrameKind>,
+    ) {
+        let scanline = bus.vdc_current_scanline();
+        if scanline < self.last_scanline {
+            self.frame += 1;
+        }
+        self.last_scanline = scanline;
+        if self.finished {
+            return;
+        }
+
+        let pc = cpu.pc;
+        let len = match interrupt {
+            Some(_) => 0,
+            None => disasm::instruction_len(bus.peek(pc)),
+        };
+        let mut bytes = [0u8; 7];
+        for (i, byte) in bytes.iter_mut().enumerate().take(len as usize) {
+            *byte = bus.peek(pc.wrapping_add(i as u16));
+        }
+        let entry = TraceEntry {
+            pc,
+            bank: bus.mpr((pc >> 13) as usize),
+            mpr: bus.mpr_array(),
+            bytes,
+            len,
+            a: cpu.a,
+            x: cpu.x,
+            y: cpu.y,
+            sp: cpu.sp,
+            status: cpu.status,
+            cycles,
+            scanline,
+            frame: self.frame,
+            interrupt,
+        };
+
+        if !self.active {
+            match self.start {
+                Some(trigger) if trigger.matches(&entry, self.recorded) => self.active = true,
+                _ => return,
+            }
+        }
+        if let Some(trigger) = self.stop
+            && trigger.matches(&entry, self.recorded)
+        {
+            self.active = false;
+            self.finished = true;
+            return;
+        }
+
+        self.recorded += 1;
+        let line = match (&self.sink, &self.symbols) {
+            (Sink::Stream(_), Some(symbols)) => Some(entry.format_with_symbols(symbols)),
+            (Sink::Stream(_), None) => Some(entry.to_string()),
+            (Sink::Ring { .. }, _) => None,
+        };
+        match &mut self.sink {
+            Sink::Ring { entries, capacity } => {
+                if entries.len() == *capacity {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+            Sink::Stream(writer) => {
+                if self.error.is_none()
+                    && let Some(line) = line
+                    && let Err(err) = writeln!(writer, "{line}")
+                {
+                    self.error = Some(err);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("start", &self.start)
+            .field("stop", &self.stop)
+            .field("active", &self.active)
+            .field("finished", &self.finished)
+            .field("recorded", &self.recorded)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Emulator slot holding an attached tracer.
+///
+/// Tracing is a debugging aid rather than machine state: the slot encodes
+/// as zero bytes, decodes empty, and cloning an emulator does not clone its
+/// tracer (a file sink cannot be duplicated).
+#[derive(Debug, Default)]
+pub(crate) struct TracerSlot(pub(crate) Option<Box<Tracer>>);
+
+impl Clone for TracerSlot {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
+impl bincode::Encode for TracerSlot {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for TracerSlot {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for TracerSlot {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pc: u16) -> TraceEntry {
+        TraceEntry {
+            pc,
+            bank: 0xF8,
+            mpr: [0xFF, 0xF8, 0, 0, 0, 0, 0, 0xF8],
+            bytes: [0xA9, 0x3F, 0, 0, 0, 0, 0],
+            len: 2,
+            a: 0x00,
+            x: 0x1F,
+            y: 0x00,
+            sp: 0xF5,
+            status: 0x24,
+            cycles: 123_456,
+            scanline: 12,
+            frame: 0,
+            interrupt: None,
+        }
+    }
+
+    #[test]
+    fn entry_formats_in_trace_text_format() {
+        assert_eq!(
+            entry(0xE123).to_string(),
+            "F8:E123  A9 3F                 LDA #$3F            \
+             A:00 X:1F Y:00 S:F5 P:nvTbdIzc CYC:123456 SL:012"
+        );
+    }
+
+    #[test]
+    fn interrupt_dispatch_formats_without_opcode_bytes() {
+        let mut dispatch = entry(0xE123);
+        dispatch.len = 0;
+        dispatch.interrupt = Some(FrameKind::Irq1);
+        assert_eq!(
+            dispatch.to_string(),
+            "F8:E123                        <IRQ1>              \
+             A:00 X:1F Y:00 S:F5 P:nvTbdIzc CYC:123456 SL:012"
+        );
+        let symbols = SymbolTable::parse("F8:E123 init\n").unwrap();
+        assert_eq!(dispatch.format_with_symbols(&symbols), dispatch.to_string());
+        assert!(!TraceTrigger::Pc(0xE123).matches(&dispatch, 0));
+    }
+
+    #[test]
+    fn entry_formats_with_symbols() {
+        let symbols = SymbolTable::parse("F8:E123 init\n").unwrap();
+        let mut lda = entry(0xE123);
+        lda.bytes[..3].copy_from_slice(&[0xAD, 0x23, 0xE1]);
+        lda.len = 3;
+        let text = lda.format_with_symbols(&symbols);
+        let (label, line) = text.split_once('\n').unwrap();
+        assert_eq!(label, "init:");
+        assert!(line.contains("LDA init "), "{line}");
+    }
+
+    #[test]
+    fn triggers_parse_from_strings() {
+        assert_eq!("pc:E123".parse(), Ok(TraceTrigger::Pc(0xE123)));
+        assert_eq!(
+            "PC:$F8:E123".parse(),
+            Ok(TraceTrigger::BankedPc(BankedAddress {
+                bank: 0xF8,
+                addr: 0xE123
+            }))
+        );
+        assert_eq!("frame:60".parse(), Ok(TraceTrigger::Frame(60)));
+        assert_eq!("count:10".parse(), Ok(TraceTrigger::Count(10)));
+        assert!(TraceTrigger::Frame(60).can_start());
+        assert!(!TraceTrigger::Count(10).can_start());
+        assert!("pc:1FF:E000".parse::<TraceTrigger>().is_err());
+        assert!("line:3".parse::<TraceTrigger>().is_err());
+        assert!("E123".parse::<TraceTrigger>().is_err());
+    }
+}
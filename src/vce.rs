@@ -1,11 +1,153 @@
+/// HuC6260 input clock (6 × NTSC colour subcarrier).
+const VCE_CLOCK_HZ: u32 = 21_477_270;
+/// VCE clocks in one scanline.
+const VCE_CLOCKS_PER_LINE: usize = 1365;
+
+const VCE_CTRL_DOT_CLOCK: u16 = 0x0003;
+const VCE_CTRL_263_LINES: u16 = 0x0004;
+const VCE_CTRL_STRIP_COLORBURST: u16 = 0x0080;
+
+/// Pixel clock selected by VCE control bits 0-1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DotClock {
+    /// 5.37 MHz, the usual 256-pixel mode.
+    Mhz5,
+    /// 7.16 MHz, 336/344-pixel modes.
+    Mhz7,
+    /// 10.74 MHz, 512-pixel mode.
+    Mhz10,
+}
+
+impl DotClock {
+    /// VCE clocks per pixel.
+    pub fn divider(self) -> u32 {
+        match self {
+            DotClock::Mhz5 => 4,
+            DotClock::Mhz7 => 3,
+            DotClock::Mhz10 => 2,
+        }
+    }
+
+    pub fn hz(self) -> f64 {
+        VCE_CLOCK_HZ as f64 / self.divider() as f64
+    }
+
+    /// Pixels in a whole scanline (341, 455 or 682).  The VDC cannot
+    /// display more than this on one line, whatever HDR asks for.
+    ///
+    /// This only caps the width: where the picture starts and how wide it
+    /// is still come from HDS and HDW alone, and HSW/HDE are not checked
+    /// against the line length, so a mode whose horizontal timing adds up
+    /// to more or less than one line is not shifted or squeezed.
+    pub fn dots_per_line(self) -> usize {
+        VCE_CLOCKS_PER_LINE / self.divider() as usize
+    }
+
+    /// Width/height of one pixel on a 4:3 NTSC display.  Square pixels at
+    /// 240 lines need 135/22 MHz, which is 2/7 of the VCE clock, so this
+    /// is 8:7, 6:7 and 4:7 for the three clocks.
+    pub fn pixel_aspect_ratio(self) -> f64 {
+        2.0 * self.divider() as f64 / 7.0
+    }
+}
+
+/// Layout of the `u32` pixels the renderer writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// `0x00RRGGBB`.
+    #[default]
+    Xrgb8888,
+    /// `0xAABBGGRR` with opaque alpha: R, G, B, A bytes in little-endian
+    /// memory, ready for an RGBA texture upload.
+    Abgr8888,
+    /// RGB565 in the low 16 bits.
+    Rgb565,
+    /// The 9-bit VCE palette index each pixel was drawn from, so output
+    /// does not depend on palette contents.  Blanked pixels read as
+    /// [`PixelFormat::BLANK_INDEX`].
+    Index9,
+}
+
+impl PixelFormat {
+    /// What [`PixelFormat::Index9`] writes where the VCE outputs black
+    /// without reading the palette.
+    pub const BLANK_INDEX: u32 = 0x200;
+
+    /// Encode an `0x00RRGGBB` colour, or `index` for raw-index output.
+    pub fn encode(self, rgb: u32, index: usize) -> u32 {
+        let (r, g, b) = ((rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF);
+        match self {
+            PixelFormat::Xrgb8888 => rgb,
+            PixelFormat::Abgr8888 => 0xFF00_0000 | (b << 16) | (g << 8) | r,
+            PixelFormat::Rgb565 => ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3),
+            PixelFormat::Index9 => index as u32 & 0x1FF,
+        }
+    }
+
+    /// Pixel value for black the VCE outputs on its own.
+    pub fn blank(self) -> u32 {
+        match self {
+            PixelFormat::Xrgb8888 | PixelFormat::Abgr8888 => 0xFF00_0000,
+            PixelFormat::Rgb565 => 0,
+            PixelFormat::Index9 => Self::BLANK_INDEX,
+        }
+    }
+}
+
+/// Every palette entry expanded to the output pixel format, rebuilt as
+/// entries are written so the renderer only looks colours up.  Encodes as
+/// zero bytes; the owner rebuilds it after a state load.
+#[derive(Clone)]
+struct ColourCache {
+    format: PixelFormat,
+    colours: [u32; 0x200],
+}
+
+impl Default for ColourCache {
+    fn default() -> Self {
+        Self {
+            format: PixelFormat::default(),
+            colours: [0; 0x200],
+        }
+    }
+}
+
+impl bincode::Encode for ColourCache {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for ColourCache {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for ColourCache {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
 #[derive(Clone, bincode::Encode, bincode::Decode)]
 pub(crate) struct Vce {
+    /// Raw palette words.  Write through [`Vce::set_palette_word`], or call
+    /// [`Vce::refresh_colours`] after poking this directly.
     pub(crate) palette: [u16; 0x200],
     control: u16,
     address: u16,
     data_latch: u16,
     write_phase: VcePhase,
     read_phase: VcePhase,
+    colours: ColourCache,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, bincode::Encode, bincode::Decode)]
@@ -18,11 +160,15 @@ impl Vce {
     pub(crate) fn new() -> Self {
         Self {
             palette: [0; 0x200],
+            // Power-on control is 0: 5.37 MHz and 262-line fields.  State
+            // files saved before these bits took effect hold the same
+            // value, so loading one and resetting agree on field length.
             control: 0,
             address: 0,
             data_latch: 0,
             write_phase: VcePhase::Low,
             read_phase: VcePhase::Low,
+            colours: ColourCache::default(),
         }
     }
 
@@ -33,6 +179,47 @@ impl Vce {
         self.data_latch = 0;
         self.write_phase = VcePhase::Low;
         self.read_phase = VcePhase::Low;
+        self.refresh_colours();
+    }
+
+    pub(crate) fn pixel_format(&self) -> PixelFormat {
+        self.colours.format
+    }
+
+    pub(crate) fn set_pixel_format(&mut self, format: PixelFormat) {
+        self.colours.format = format;
+        self.refresh_colours();
+    }
+
+    /// Entry `index` in the output pixel format.
+    #[inline]
+    pub(crate) fn colour(&self, index: usize) -> u32 {
+        self.colours.colours[index & 0x1FF]
+    }
+
+    /// Black in the output pixel format, for pixels the VCE blanks.
+    pub(crate) fn blank_colour(&self) -> u32 {
+        self.colours.format.blank()
+    }
+
+    /// Store a palette word and re-expand that entry.
+    pub(crate) fn set_palette_word(&mut self, index: usize, word: u16) {
+        if let Some(slot) = self.palette.get_mut(index) {
+            *slot = word;
+            self.refresh_colour(index);
+        }
+    }
+
+    fn refresh_colour(&mut self, index: usize) {
+        self.colours.colours[index] = self.colours.format.encode(self.palette_rgb(index), index);
+    }
+
+    /// Re-expand every entry, after a format or colour-burst change or
+    /// direct writes to `palette`.
+    pub(crate) fn refresh_colours(&mut self) {
+        for index in 0..self.palette.len() {
+            self.refresh_colour(index);
+        }
     }
 
     fn index(&self) -> usize {
@@ -50,13 +237,40 @@ impl Vce {
     }
 
     pub(crate) fn write_control_low(&mut self, value: u8) {
+        let was_grayscale = self.grayscale();
         self.control = (self.control & 0xFF00) | (value as u16 & 0x0087);
+        if self.grayscale() != was_grayscale {
+            self.refresh_colours();
+        }
         self.read_phase = VcePhase::Low;
         self.write_phase = VcePhase::Low;
         #[cfg(feature = "trace_hw_writes")]
         eprintln!("  VCE control low <= {:02X}", value);
     }
 
+    pub(crate) fn dot_clock(&self) -> DotClock {
+        match self.control & VCE_CTRL_DOT_CLOCK {
+            0x00 => DotClock::Mhz5,
+            0x01 => DotClock::Mhz7,
+            _ => DotClock::Mhz10,
+        }
+    }
+
+    /// Scanlines per field: 263, or 262 with control bit 2 clear.
+    pub(crate) fn frame_lines(&self) -> u16 {
+        if self.control & VCE_CTRL_263_LINES != 0 {
+            263
+        } else {
+            262
+        }
+    }
+
+    /// Control bit 7 strips the colour burst from the composite signal,
+    /// so a TV shows the picture in grayscale.
+    pub(crate) fn grayscale(&self) -> bool {
+        self.control & VCE_CTRL_STRIP_COLORBURST != 0
+    }
+
     pub(crate) fn write_control_high(&mut self, _value: u8) {
         self.read_phase = VcePhase::Low;
         self.write_phase = VcePhase::Low;
@@ -101,9 +315,8 @@ impl Vce {
         // low byte of the current palette entry, preserving the high byte.
         // No phase tracking needed — each port write takes effect immediately.
         let idx = self.index();
-        if let Some(slot) = self.palette.get_mut(idx) {
-            *slot = (*slot & 0xFF00) | value as u16;
-        }
+        let word = (self.palette_word(idx) & 0xFF00) | value as u16;
+        self.set_palette_word(idx, word);
         // Keep latch in sync for reads
         self.data_latch = self.palette.get(idx).copied().unwrap_or(0);
         #[cfg(feature = "trace_hw_writes")]
@@ -119,9 +332,8 @@ impl Vce {
         // then auto-increments the address.  No phase tracking.
         let idx = self.index();
         let high = (value as u16) & 0x01;
-        if let Some(slot) = self.palette.get_mut(idx) {
-            *slot = (*slot & 0x00FF) | (high << 8);
-        }
+        let word = (self.palette_word(idx) & 0x00FF) | (high << 8);
+        self.set_palette_word(idx, word);
         // Keep latch in sync for reads
         self.data_latch = self.palette.get(idx).copied().unwrap_or(0);
         #[cfg(feature = "trace_hw_writes")]
@@ -132,19 +344,33 @@ impl Vce {
         self.increment_index();
     }
 
-    pub(crate) fn read_data_low(&mut self) -> u8 {
+    /// Palette word the data ports would read next.
+    fn peek_latch(&self) -> u16 {
         if self.read_phase == VcePhase::Low {
-            self.data_latch = self.palette.get(self.index()).copied().unwrap_or(0);
+            self.palette.get(self.index()).copied().unwrap_or(0)
+        } else {
+            self.data_latch
         }
+    }
+
+    pub(crate) fn peek_data_low(&self) -> u8 {
+        (self.peek_latch() & 0x00FF) as u8
+    }
+
+    pub(crate) fn peek_data_high(&self) -> u8 {
+        ((self.peek_latch() >> 8) as u8 & 0x01) | 0xFE
+    }
+
+    pub(crate) fn read_data_low(&mut self) -> u8 {
+        let value = self.peek_data_low();
+        self.data_latch = self.peek_latch();
         self.read_phase = VcePhase::High;
-        (self.data_latch & 0x00FF) as u8
+        value
     }
 
     pub(crate) fn read_data_high(&mut self) -> u8 {
-        if self.read_phase == VcePhase::Low {
-            self.data_latch = self.palette.get(self.index()).copied().unwrap_or(0);
-        }
-        let value = ((self.data_latch >> 8) as u8 & 0x01) | 0xFE;
+        let value = self.peek_data_high();
+        self.data_latch = self.peek_latch();
         self.increment_index();
         self.read_phase = VcePhase::Low;
         value
@@ -157,11 +383,7 @@ impl Vce {
 
     pub(crate) fn palette_access_stall_pixels(&self, cpu_high_speed: bool) -> usize {
         let cpu_master_cycles = if cpu_high_speed { 1usize } else { 4usize };
-        let dot_divider = match self.control & 0x0003 {
-            0x00 => 4usize,
-            0x01 => 3usize,
-            _ => 2usize,
-        };
+        let dot_divider = self.dot_clock().divider() as usize;
         cpu_master_cycles.div_ceil(dot_divider).max(1)
     }
 
@@ -203,6 +425,10 @@ impl Vce {
         let r = component(red);
         let g = component(green);
         let b = component(blue);
+        if self.grayscale() {
+            let luma = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+            return (luma << 16) | (luma << 8) | luma;
+        }
         ((r as u32) << 16) | ((g as u32) << 8) | b as u32
     }
 }
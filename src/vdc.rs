@@ -50,6 +50,86 @@ pub(crate) enum VdcReadPhase {
     High,
 }
 
+/// One SATB entry as the VDC reads it.  Shared by the sprite renderer and
+/// the sprite viewer so both agree on position, size and pattern.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct SatbEntry {
+    /// Left edge relative to the display start (SATB X - 32).
+    pub(crate) x: i32,
+    /// Top edge relative to the first active line (SATB Y - 64).
+    pub(crate) y: i32,
+    pub(crate) width_cells: usize,
+    /// CGY: 0, 1 and 2/3 give 1, 2 and 4 cells.
+    pub(crate) height_code: usize,
+    /// First 16x16 pattern, with the bits the sprite size spans cleared.
+    pub(crate) pattern: usize,
+    /// Pattern word bit 0: which plane pair the 2-bit CG mode reads.
+    pub(crate) upper_cg_pair: bool,
+    pub(crate) palette: u8,
+    pub(crate) h_flip: bool,
+    pub(crate) v_flip: bool,
+    pub(crate) high_priority: bool,
+}
+
+impl SatbEntry {
+    /// Decode entry `index` of `satb`; words past the end read as 0.
+    pub(crate) fn decode(satb: &[u16], index: usize) -> Self {
+        let word = |offset: usize| satb.get(index * 4 + offset).copied().unwrap_or(0);
+        let (y_word, x_word, pattern_word, attr_word) = (word(0), word(1), word(2), word(3));
+        // MAME sprite Y: src_y = (m_current_segment_start - sat_y) & 0x3FF
+        // m_current_segment_start = 0x40 at first active line.
+        // So sat_y=64 (0x40) → src_y=0 → first row at display row 0.
+        // Screen Y = sat_y - 64 (no +1; the -1 in "raster_count - 1"
+        // is already factored into m_current_segment_start).
+        let mut entry = Self {
+            x: (x_word & 0x03FF) as i32 - 32,
+            y: (y_word & 0x03FF) as i32 - 64,
+            width_cells: if attr_word & 0x0100 != 0 { 2 } else { 1 },
+            height_code: ((attr_word >> 12) & 0x03) as usize,
+            pattern: 0,
+            upper_cg_pair: pattern_word & 0x0001 != 0,
+            palette: (attr_word & 0x000F) as u8,
+            h_flip: attr_word & 0x0800 != 0,
+            v_flip: attr_word & 0x8000 != 0,
+            high_priority: attr_word & 0x0080 != 0,
+        };
+        entry.pattern = entry.align_pattern(((pattern_word >> 1) & 0x03FF) as usize);
+        entry
+    }
+
+    /// Clear the pattern bits this sprite's size spans.  MAME: each
+    /// height-code bit independently masks a pattern bit (cgy bit 0 →
+    /// pattern bit 1, cgy bit 1 → pattern bit 2).
+    pub(crate) fn align_pattern(&self, mut pattern: usize) -> usize {
+        if self.width_cells == 2 {
+            pattern &= !0x0001;
+        }
+        if self.height_code & 1 != 0 {
+            pattern &= !0x0002;
+        }
+        if self.height_code & 2 != 0 {
+            pattern &= !0x0004;
+        }
+        pattern
+    }
+
+    pub(crate) fn height_cells(&self) -> usize {
+        match self.height_code {
+            0 => 1,
+            1 => 2,
+            _ => 4,
+        }
+    }
+
+    pub(crate) fn width(&self) -> usize {
+        self.width_cells * SPRITE_PATTERN_WIDTH
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.height_cells() * SPRITE_PATTERN_HEIGHT
+    }
+}
+
 #[derive(Clone, Copy, bincode::Encode, bincode::Decode)]
 pub(crate) struct VerticalWindow {
     pub(crate) timing_programmed: bool,
@@ -106,6 +186,73 @@ impl core::ops::DerefMut for TransientLineU16 {
     }
 }
 
+/// Scanline the VDC has finished displaying but the bus has not rendered
+/// yet.  Lives only between `Vdc::tick` and `Bus::tick`, so it is never
+/// saved.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct TransientScanline(pub(crate) Option<u16>);
+
+impl bincode::Encode for TransientScanline {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for TransientScanline {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for TransientScanline {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+/// Scanlines per field as programmed on the VCE (262 or 263).  The bus
+/// re-syncs it from the VCE after a state load, so it is never saved.
+#[derive(Clone, Copy)]
+pub(crate) struct TransientFrameLines(pub(crate) u16);
+
+impl Default for TransientFrameLines {
+    fn default() -> Self {
+        Self(LINES_PER_FRAME)
+    }
+}
+
+impl bincode::Encode for TransientFrameLines {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        _encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        Ok(())
+    }
+}
+
+impl<Context> bincode::Decode<Context> for TransientFrameLines {
+    fn decode<D: bincode::de::Decoder>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
+impl<'de, Context> bincode::BorrowDecode<'de, Context> for TransientFrameLines {
+    fn borrow_decode<D: bincode::de::BorrowDecoder<'de>>(
+        _decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        Ok(Self::default())
+    }
+}
+
 #[derive(Clone, bincode::Encode, bincode::Decode)]
 pub(crate) struct CompatVdcStateV1 {
     pub(crate) registers: [u16; VDC_REGISTER_COUNT],
@@ -234,10 +381,14 @@ pub(crate) struct Vdc {
     #[cfg(feature = "trace_hw_writes")]
     pub(crate) st0_hold_addr_hist: [u32; 0x100],
     pub(crate) st0_locked_until_commit: bool,
-    /// Scanline that needs post-ISR scroll adjustment.  Set when an RCR
-    /// interrupt fires; consumed on re-entry to apply the h-sync bg_y_offset
-    /// increment so the next scanline renders at BYR+1.
+    /// No longer used: lines are drawn from live register state, so RCR
+    /// handlers need no post-ISR fix-up.  Kept so save states keep their
+    /// layout.
     pub(crate) rcr_post_isr_line: Option<u16>,
+    /// Scanline completed by the last `tick()`, waiting for the bus to
+    /// render it before timing moves on to the next line.
+    pub(crate) completed_line: TransientScanline,
+    pub(crate) frame_lines: TransientFrameLines,
 }
 
 /// Cached env-var flag: returns `true` when the env var is set (`.is_ok()`).
@@ -337,6 +488,8 @@ impl Vdc {
             st0_hold_addr_hist: [0; 0x100],
             st0_locked_until_commit: false,
             rcr_post_isr_line: None,
+            completed_line: TransientScanline::default(),
+            frame_lines: TransientFrameLines::default(),
         };
         vdc.registers[0x04] = VDC_CTRL_ENABLE_BACKGROUND_LEGACY | VDC_CTRL_ENABLE_SPRITES_LEGACY;
         vdc.registers[0x05] = vdc.registers[0x04];
@@ -381,6 +534,8 @@ impl Vdc {
         self.satb_written = false;
         self.in_vblank = true;
         self.frame_trigger = false;
+        self.completed_line = TransientScanline::default();
+        self.frame_lines = TransientFrameLines::default();
         self.registers[0x09] = 0x0010;
         self.refresh_activity_flags();
         self.status &= !VDC_STATUS_BUSY;
@@ -435,6 +590,15 @@ impl Vdc {
         value
     }
 
+    /// Status byte a read would return, without acknowledging it.
+    pub(crate) fn peek_status(&self) -> u8 {
+        if self.busy_cycles > 0 {
+            self.status | VDC_STATUS_BUSY
+        } else {
+            self.status & !VDC_STATUS_BUSY
+        }
+    }
+
     #[allow(dead_code)]
     pub(crate) fn raise_status(&mut self, mask: u8) {
         self.status |= mask;
@@ -533,7 +697,7 @@ impl Vdc {
             };
         }
 
-        let lines_per_frame = LINES_PER_FRAME as usize;
+        let lines_per_frame = self.frame_lines() as usize;
         let vpr = self.registers[0x0C];
         let vsw = (vpr & 0x001F) as usize;
         let vds = ((vpr >> 8) & 0x00FF) as usize;
@@ -564,7 +728,7 @@ impl Vdc {
 
     #[inline]
     pub(crate) fn frame_line_for_output_row(&self, window: &VerticalWindow, row: usize) -> usize {
-        let lines_per_frame = LINES_PER_FRAME as usize;
+        let lines_per_frame = self.frame_lines() as usize;
         if window.timing_programmed {
             (window.active_start_line + row) % lines_per_frame
         } else {
@@ -572,6 +736,19 @@ impl Vdc {
         }
     }
 
+    /// Framebuffer row fed by `scanline`; the inverse of
+    /// [`Self::frame_line_for_output_row`].
+    pub(crate) fn output_row_for_scanline(&self, scanline: usize) -> Option<usize> {
+        let window = self.vertical_window();
+        let lines_per_frame = self.frame_lines() as usize;
+        let row = if window.timing_programmed {
+            (scanline + lines_per_frame - window.active_start_line) % lines_per_frame
+        } else {
+            scanline
+        };
+        (row < FRAME_HEIGHT).then_some(row)
+    }
+
     pub(crate) fn active_row_for_output_row(&self, row: usize) -> Option<usize> {
         let window = self.vertical_window();
         if !window.timing_programmed {
@@ -617,6 +794,15 @@ impl Vdc {
             && (self.scanline as usize) < window.vblank_start_line
     }
 
+    /// Scanlines per field, 262 or 263 as selected on the VCE.
+    pub(crate) fn frame_lines(&self) -> u16 {
+        self.frame_lines.0
+    }
+
+    pub(crate) fn set_frame_lines(&mut self, lines: u16) {
+        self.frame_lines = TransientFrameLines(lines.clamp(262, LINES_PER_FRAME));
+    }
+
     pub(crate) fn vblank_start_scanline(&self) -> u16 {
         self.vertical_window().vblank_start_line as u16
     }
@@ -629,148 +815,94 @@ impl Vdc {
             // scanline = active_start + (target - 0x40).
             let window = self.vertical_window();
             let relative = (target - VDC_ACTIVE_COUNTER_BASE as u16) as usize;
-            let line = (window.active_start_line + relative) % (LINES_PER_FRAME as usize);
-            if line < LINES_PER_FRAME as usize {
-                Some(line as u16)
-            } else {
-                None
-            }
+            let line = (window.active_start_line + relative) % (self.frame_lines() as usize);
+            Some(line as u16)
         } else {
             None
         }
     }
 
-    /// Re-latch scroll/zoom/control for a scanline whose RCR ISR has now
-    /// completed.  Called once per RCR event, just before the scanline
-    /// advances.  Updates bg_y_offset to account for the ISR's BYR write.
-    pub(crate) fn consume_post_isr_scroll(&mut self, line: usize) {
-        // After the CPU services an RCR ISR, consume any pending scroll/zoom
-        // writes so bg_y_offset is updated.  On real hardware the VDC has
-        // already started rendering the RCR scanline with the *pre-ISR*
-        // latch values, so we do NOT overwrite the scroll arrays for the
-        // current scanline.
-        //
-        // We do NOT increment bg_y_offset here.  latch_line_state() for
-        // the RCR scanline already performed the per-scanline increment
-        // before the RCR was detected.  Adding another increment would
-        // double-count, shifting BG content 1 line down at every RCR
-        // boundary and creating visible gaps between sprites and BG.
-        //
-        // If BYR was written during the ISR, apply_pending_scroll() resets
-        // bg_y_offset to 0.  The next latch_line_state() (scanline S+1)
-        // stores 0 and then increments to 1, so scanline S+1 renders at
-        // new_BYR+0 and S+2 at new_BYR+1 — matching MAME's m_byr_latched
-        // semantics.
-        self.apply_pending_scroll();
-        self.apply_pending_zoom();
-
-        // Relatch BXR (horizontal scroll) and the control register for
-        // the RCR scanline.  The RCR fires during h-sync, before the
-        // active display portion of the scanline begins.  Unlike BYR
-        // (which uses a latch-and-relatch mechanism taking effect on the
-        // *next* scanline), BXR is read directly from the register
-        // during pixel output, so the ISR's BXR write takes effect
-        // immediately on the current scanline.  CR similarly takes
-        // effect immediately.
-        //
-        // We do NOT update scroll_line_y or scroll_line_y_offset here
-        // because BYR uses m_byr_latched semantics: the relatch occurs
-        // at the next h-sync, so BYR changes affect the next scanline.
-        if line < self.scroll_line_x.len() {
-            self.scroll_line_x[line] = self.scroll_x;
-            self.control_line[line] = self.control_for_render();
-        }
-    }
-
-    pub(crate) fn tick(&mut self, phi_cycles: u32) -> bool {
+    /// Advance display timing by `phi_cycles`.  Timing stops as soon as a
+    /// scanline has been fully displayed: the caller renders it (see
+    /// [`Self::take_completed_line`]) and then calls
+    /// [`Self::start_next_scanline`] to continue with the remaining cycles.
+    pub(crate) fn tick(&mut self, phi_cycles: u32) {
         if phi_cycles == 0 {
-            return false;
+            return;
         }
 
         self.consume_busy(phi_cycles);
 
-        let frame_cycles = VDC_VBLANK_INTERVAL as u64;
         self.phi_scaled = self
             .phi_scaled
             .saturating_add(phi_cycles as u64 * LINES_PER_FRAME as u64);
 
-        let mut irq_recalc = false;
-        while self.phi_scaled >= frame_cycles {
-            // Preserve per-frame line latches until the renderer consumes this frame.
-            // Some HuC6280 instructions run long enough to cover many scanlines; if
-            // we march past VBlank start in one call, line-state snapshots can be
-            // overwritten before render_frame_from_vram() runs.
-            if self.frame_trigger {
-                break;
-            }
-            self.phi_scaled -= frame_cycles;
-
-            // If an RCR ISR was pending, the CPU has now executed it
-            // (a full scanline's worth of cycles elapsed for this loop
-            // iteration).  Consume the ISR's scroll/zoom writes and
-            // account for the h-sync BYR-latch increment, but keep
-            // the pre-ISR scroll state for the RCR scanline (the VDC
-            // has already rendered it with the old values).
-            if let Some(line) = self.rcr_post_isr_line.take() {
-                self.consume_post_isr_scroll(line as usize);
-            }
+        if self.completed_line.0.is_none() {
+            self.finish_scanline();
+        }
+    }
 
-            let wrapped = self.advance_scanline();
-            if wrapped {
-                irq_recalc = true;
-            }
+    /// Scanline finished by the last `tick()`/`start_next_scanline()`, if
+    /// any.  Its line state is final, and VRAM, SATB and palette hold what
+    /// the line was displayed with.
+    pub(crate) fn take_completed_line(&mut self) -> Option<u16> {
+        self.completed_line.0.take()
+    }
 
-            // Latch line state with pre-ISR values.  If an RCR interrupt
-            // fires below, we record the scanline: once the CPU finishes
-            // the ISR (next tick()), consume_post_isr_scroll() accounts
-            // for the h-sync BYR-latch increment without overwriting the
-            // already-latched scroll for this scanline.
-            self.latch_line_state(self.scanline as usize);
-
-            // Trigger frame render at the LAST active scanline (one before
-            // VBlank).  Games like Power League III write sprite pattern data
-            // to VRAM during active display; rendering at VBlank start would
-            // miss those writes.  By deferring the trigger to the end of
-            // active display, the batch renderer captures all mid-frame VRAM
-            // updates while per-line scroll/control state is still intact.
-            let vbl = self.vblank_start_scanline();
-            if !self.in_vblank && !self.frame_trigger && vbl > 0 && self.scanline == vbl - 1 {
-                self.frame_trigger = true;
-            }
+    /// Once a line's worth of cycles has elapsed, mark the current scanline
+    /// as completed.
+    fn finish_scanline(&mut self) {
+        let frame_cycles = VDC_VBLANK_INTERVAL as u64;
+        if self.phi_scaled < frame_cycles {
+            return;
+        }
+        self.phi_scaled -= frame_cycles;
 
-            if self.scanline == vbl {
-                self.in_vblank = true;
-                self.raise_status(VDC_STATUS_VBL);
-                self.refresh_activity_flags();
+        // The last active line closes the frame.
+        let vbl = self.vblank_start_scanline();
+        if !self.in_vblank && vbl > 0 && self.scanline == vbl - 1 {
+            self.frame_trigger = true;
+        }
+        self.completed_line = TransientScanline(Some(self.scanline));
+    }
+
+    /// Move on to the scanline after the completed one and keep running on
+    /// any cycles left over from `tick()`.  Stops early at VBlank start and
+    /// at an RCR match so the CPU can service the interrupt first.
+    pub(crate) fn start_next_scanline(&mut self) -> bool {
+        let mut irq_recalc = self.advance_scanline();
+        self.latch_byr();
+
+        let vbl = self.vblank_start_scanline();
+        if self.scanline == vbl {
+            self.in_vblank = true;
+            self.raise_status(VDC_STATUS_VBL);
+            self.refresh_activity_flags();
+            irq_recalc = true;
+            if self.handle_vblank_start() {
                 irq_recalc = true;
-                if self.handle_vblank_start() {
-                    irq_recalc = true;
-                }
             }
+        }
 
-            let rcr_target = self.registers[0x06] & 0x03FF;
-            if let Some(rcr_scanline) = self.rcr_scanline_for_target(rcr_target) {
-                if self.scanline == rcr_scanline {
-                    // Per HuC6270 hardware (confirmed by MAME): the RR status
-                    // flag is only raised when CR bit 2 (RCR interrupt enable)
-                    // is set.  Games like Kato-chan & Ken-chan rely on this —
-                    // the ISR checks the RR bit to decide whether to apply a
-                    // scroll offset, so raising it unconditionally would cause
-                    // an incorrect BYR value on the title screen.
-                    if self.control() & 0x0004 != 0 {
-                        self.raise_status(VDC_STATUS_RCR);
-                        self.rcr_post_isr_line = Some(self.scanline);
-                        irq_recalc = true;
-                        break;
-                    }
+        let rcr_target = self.registers[0x06] & 0x03FF;
+        if let Some(rcr_scanline) = self.rcr_scanline_for_target(rcr_target) {
+            if self.scanline == rcr_scanline {
+                // Per HuC6270 hardware (confirmed by MAME): the RR status
+                // flag is only raised when CR bit 2 (RCR interrupt enable)
+                // is set.  Games like Kato-chan & Ken-chan rely on this —
+                // the ISR checks the RR bit to decide whether to apply a
+                // scroll offset, so raising it unconditionally would cause
+                // an incorrect BYR value on the title screen.
+                if self.control() & 0x0004 != 0 {
+                    self.raise_status(VDC_STATUS_RCR);
+                    return true;
                 }
             }
-
-            if self.frame_trigger || self.scanline == vbl {
-                break;
-            }
         }
 
+        if self.scanline != vbl {
+            self.finish_scanline();
+        }
         irq_recalc
     }
 
@@ -830,6 +962,26 @@ impl Vdc {
         }
     }
 
+    /// Value `read_port` would return, leaving the read latch, MARR and
+    /// status untouched.
+    pub(crate) fn peek_port(&self, port: usize) -> u8 {
+        let reg = self.selected_register() as usize;
+        let word = if port == 0 {
+            return self.peek_status();
+        } else if reg != 0x02 {
+            self.registers.get(reg).copied().unwrap_or(0)
+        } else if self.read_phase == VdcReadPhase::Low {
+            *self.vram.get((self.marr as usize) & 0x7FFF).unwrap_or(&0)
+        } else {
+            self.read_buffer
+        };
+        match port {
+            1 => (word & 0x00FF) as u8,
+            2 => (word >> 8) as u8,
+            _ => 0,
+        }
+    }
+
     pub(crate) fn selected_register(&self) -> u8 {
         self.map_register_index(self.selected & 0x1F)
     }
@@ -1314,10 +1466,7 @@ impl Vdc {
     }
 
     pub(crate) fn apply_pending_scroll(&mut self) {
-        if self.scroll_x_dirty {
-            self.scroll_x = self.scroll_x_pending & 0x03FF;
-            self.scroll_x_dirty = false;
-        }
+        self.apply_pending_scroll_x();
         if self.scroll_y_dirty {
             self.scroll_y = self.scroll_y_pending & 0x01FF;
             self.scroll_y_dirty = false;
@@ -1327,6 +1476,13 @@ impl Vdc {
         }
     }
 
+    fn apply_pending_scroll_x(&mut self) {
+        if self.scroll_x_dirty {
+            self.scroll_x = self.scroll_x_pending & 0x03FF;
+            self.scroll_x_dirty = false;
+        }
+    }
+
     pub(crate) fn apply_pending_zoom(&mut self) {
         if self.zoom_x_dirty {
             self.zoom_x = self.zoom_x_pending & 0x001F;
@@ -1338,24 +1494,38 @@ impl Vdc {
         }
     }
 
-    pub(crate) fn latch_line_state(&mut self, line: usize) {
+    /// H-sync of the current scanline: BYR writes made during the previous
+    /// line take effect now (MAME's `m_byr_latched`), and the BG line
+    /// counter steps on.  The renderer computes
+    /// `sample_y = BYR + offset * zoom_step`, where the offset:
+    ///  - resets to 0 at the first active scanline of each frame,
+    ///  - resets to 0 on the line after a BYR write,
+    ///  - otherwise increments once per active scanline.
+    fn latch_byr(&mut self) {
+        let byr_written = self.scroll_y_dirty;
         self.apply_pending_scroll();
         self.apply_pending_zoom();
 
-        // HuC6270 BG Y offset tracking:
-        //  - At the first active scanline of each frame, the offset resets to 0.
-        //  - Each subsequent active scanline, the offset auto-increments.
-        //  - Writing BYR mid-frame resets the offset to 0 (handled in
-        //    apply_pending_scroll above).
-        // The renderer computes: sample_y = BYR + offset * zoom_step.
+        let line = self.scanline as usize;
         let window = self.vertical_window();
-        let is_active =
-            !self.in_vblank && line >= window.active_start_line && line < window.vblank_start_line;
-
-        if is_active && !self.bg_y_offset_loaded {
+        if self.in_vblank || line < window.active_start_line || line >= window.vblank_start_line {
+            return;
+        }
+        if !self.bg_y_offset_loaded {
             self.bg_y_offset = 0;
             self.bg_y_offset_loaded = true;
+        } else if !byr_written && line > window.active_start_line {
+            self.bg_y_offset = self.bg_y_offset.wrapping_add(1);
         }
+    }
+
+    /// Record the state `line` is drawn with.  BXR, zoom, CR and the
+    /// horizontal timing registers are read as they are now, since the VDC
+    /// reads them during pixel output; BYR and the BG line counter are the
+    /// values latched at the line's h-sync.
+    pub(crate) fn latch_line_state(&mut self, line: usize) {
+        self.apply_pending_scroll_x();
+        self.apply_pending_zoom();
 
         let idx = line % self.scroll_line_x.len();
         self.scroll_line_x[idx] = self.scroll_x;
@@ -1367,11 +1537,6 @@ impl Vdc {
         self.hsr_line[idx] = self.registers[0x0A];
         self.hdr_line[idx] = self.registers[0x0B];
         self.scroll_line_valid[idx] = true;
-
-        // After latching, increment the offset for the next active scanline.
-        if is_active && self.bg_y_offset_loaded {
-            self.bg_y_offset = self.bg_y_offset.wrapping_add(1);
-        }
     }
 
     pub(crate) fn ensure_line_state(&mut self, line: usize) {
@@ -1488,7 +1653,7 @@ impl Vdc {
     pub(crate) fn advance_scanline(&mut self) -> bool {
         self.scanline = self.scanline.wrapping_add(1);
         let mut wrapped = false;
-        if self.scanline >= LINES_PER_FRAME {
+        if self.scanline >= self.frame_lines() {
             self.scanline = 0;
             self.in_vblank = false;
             self.scroll_line_valid.fill(false);
@@ -1496,12 +1661,9 @@ impl Vdc {
             self.bg_y_offset_loaded = false;
             wrapped = true;
         }
-        // Don't latch here — the tick() loop latches after the RCR check
-        // so that the CPU ISR can modify scroll registers before the
-        // RCR scanline's state is committed.  The scroll_line_valid array
-        // serves as the implicit "needs latch" flag: the new scanline's
-        // entry is still false (cleared on frame wrap), so the tick loop
-        // knows to latch it.
+        // Don't latch here — the line state is recorded when the line is
+        // drawn.  The scroll_line_valid array tells lines drawn this frame
+        // from ones ensure_line_state() still has to synthesise.
         wrapped
     }
 
@@ -1509,6 +1671,7 @@ impl Vdc {
     pub(crate) fn advance_scanline_for_test(&mut self) {
         self.advance_scanline();
         // Tests expect line state to be latched immediately after advancing.
+        self.latch_byr();
         self.latch_line_state(self.scanline as usize);
     }
 
@@ -1531,6 +1694,22 @@ impl Vdc {
         }
     }
 
+    /// Map pixel the BG layer showed at the left edge of `line`: BXR, and
+    /// BYR advanced by the lines counted since it was latched, as the
+    /// renderer samples them.  Lines not drawn this frame use the live
+    /// scroll registers.
+    pub(crate) fn bg_origin_for_line(&self, line: usize) -> (usize, usize) {
+        if !self.scroll_line_valid(line) {
+            return (self.scroll_x as usize, self.scroll_y as usize);
+        }
+        let step_y = Self::zoom_step_value(self.zoom_line_y[line]);
+        let y_offset = self.scroll_line_y_offset[line] as usize;
+        (
+            self.scroll_line_x[line] as usize,
+            self.scroll_line_y[line] as usize + ((step_y * y_offset) >> 4),
+        )
+    }
+
     pub(crate) fn zoom_line(&self, line: usize) -> (u16, u16) {
         if line < self.zoom_line_x.len() {
             (self.zoom_line_x[line], self.zoom_line_y[line])
@@ -1616,6 +1795,8 @@ impl From<CompatVdcStateV1> for Vdc {
             st0_hold_addr_hist: [0; 0x100],
             st0_locked_until_commit: value.st0_locked_until_commit,
             rcr_post_isr_line: value.rcr_post_isr_line,
+            completed_line: TransientScanline::default(),
+            frame_lines: TransientFrameLines::default(),
         }
     }
 }